@@ -0,0 +1,119 @@
+// cost of the pieces of the core a frame is made of: single cycles on synthetic instruction mixes,
+// sprite drawing, whole frames of pong and taking and restoring snapshots
+//
+//     cargo bench --bench core
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use chip8::config::Config;
+use chip8::drivers::keyboard::create_key_state;
+use chip8::headless::{run_frames, InputScript};
+use chip8::rom_builder::{chip8_with_rom, RomBuilder};
+
+const CYCLES: u64 = 1000;
+const PONG: &[u8] = include_bytes!("../pong.chp8");
+const PONG_INPUT: &str = include_str!("../tests/golden/pong.input");
+
+// loops of one kind of instruction, each ending in a jump back to the start
+fn mixes() -> Vec<(&'static str, Vec<u8>)> {
+    let alu = RomBuilder::new()
+        .add(0, 3).add_reg(1, 0).xor(2, 1).and(3, 2).or(4, 3).shr(5, 4).sub(6, 5).shl(7, 6)
+        .jp(0x200)
+        .build();
+    let memory = RomBuilder::new()
+        .ld_i(0x300).store(7).ld_i(0x310).load(7).ld(0, 123).bcd(0).add_i(0)
+        .jp(0x200)
+        .build();
+    let branches = RomBuilder::new()
+        .se(0, 0).jp(0x200).sne(0, 1).jp(0x200).call(0x20C).jp(0x200)
+        .ret()
+        .build();
+    let timers_and_rng = RomBuilder::new()
+        .ld_dt(0).ld_from_dt(1).ld_st(0).rnd(2, 0x0F).ld_font(2)
+        .jp(0x200)
+        .build();
+
+    vec![("alu", alu), ("memory", memory), ("branches", branches), ("timers_and_rng", timers_and_rng)]
+}
+
+fn bench_exec_cycle(c: &mut Criterion) {
+    let key_state = create_key_state();
+    let mut group = c.benchmark_group("exec_cycle");
+    group.throughput(Throughput::Elements(CYCLES));
+
+    for (name, rom) in mixes() {
+        let mut chip8 = chip8_with_rom(&rom);
+        group.bench_function(name, |b| b.iter(|| {
+            for _ in 0..CYCLES {
+                chip8.exec_cycle(black_box(&key_state)).unwrap();
+            }
+        }));
+    }
+
+    group.finish();
+}
+
+// a single DXYN drawing rows of the font from address 0, past the edges the quirks decide between
+// clipping and wrapping
+fn bench_draw_sprite(c: &mut Criterion) {
+    let key_state = create_key_state();
+    let mut group = c.benchmark_group("draw_sprite");
+
+    let positions = [("aligned", 8, 8), ("unaligned", 13, 9), ("right_edge", 60, 9), ("bottom_edge", 13, 28)];
+    for height in [1, 5, 15] {
+        for (position, x, y) in positions {
+            let rom = RomBuilder::new().drw(0, 1, height).build();
+            let mut chip8 = chip8_with_rom(&rom);
+            chip8.set_register(0, x);
+            chip8.set_register(1, y);
+            chip8.set_index_register(0x000);
+
+            let id = BenchmarkId::new(format!("{}_rows", height), position);
+            group.bench_function(id, |b| b.iter(|| {
+                chip8.set_pc(0x200);
+                chip8.step(black_box(&key_state)).unwrap();
+            }));
+        }
+    }
+
+    group.finish();
+}
+
+// a second of pong with the golden test's input, at the default speed
+fn bench_frame(c: &mut Criterion) {
+    let input = InputScript::parse(PONG_INPUT).unwrap();
+    let cycles_per_frame = Config::default().cycles_per_frame;
+    let mut group = c.benchmark_group("frame");
+    group.throughput(Throughput::Elements(60));
+
+    group.bench_function("pong", |b| b.iter(|| {
+        let mut chip8 = chip8_with_rom(PONG);
+        run_frames(&mut chip8, 60, cycles_per_frame, black_box(&input)).unwrap();
+    }));
+
+    group.finish();
+}
+
+fn bench_snapshot(c: &mut Criterion) {
+    let input = InputScript::parse(PONG_INPUT).unwrap();
+    let cycles_per_frame = Config::default().cycles_per_frame;
+    let mut chip8 = chip8_with_rom(PONG);
+    run_frames(&mut chip8, 60, cycles_per_frame, &input).unwrap();
+    let mut group = c.benchmark_group("snapshot");
+
+    group.bench_function("save", |b| b.iter(|| black_box(chip8.snapshot())));
+
+    let snapshot = chip8.snapshot();
+    group.bench_function("restore", |b| b.iter(|| chip8.restore(black_box(&snapshot))));
+
+    // after a frame has run, so the decoded instructions the frame wrote over are dropped
+    let key_state = create_key_state();
+    group.bench_function("restore_after_frame", |b| b.iter(|| {
+        chip8.run_frame(&key_state, cycles_per_frame).unwrap();
+        chip8.restore(black_box(&snapshot));
+    }));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_exec_cycle, bench_draw_sprite, bench_frame, bench_snapshot);
+criterion_main!(benches);
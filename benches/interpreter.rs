@@ -0,0 +1,74 @@
+// instructions per second with and without the decoded instruction cache, and with the JIT
+//
+//     cargo bench --bench interpreter [--features jit]
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use chip8::chip8::Chip8;
+use chip8::drivers::keyboard::create_key_state;
+use chip8::rom_builder::RomBuilder;
+
+const CYCLES_PER_FRAME: u32 = 1000;
+
+fn roms() -> Vec<(&'static str, Vec<u8>)> {
+    // arithmetic in a tight loop, what XO-CHIP games at high speeds spend their time on
+    let tight_loop = RomBuilder::new()
+        .ld(0, 1).ld(1, 2)
+        .add(0, 3).add_reg(1, 0).xor(2, 1).shr(3, 2).se(0, 0xFF).jp(0x204)
+        .jp(0x200)
+        .build();
+
+    vec![
+        ("pong", include_bytes!("../pong.chp8").to_vec()),
+        ("tight_loop", tight_loop),
+    ]
+}
+
+fn bench_decode_cache(c: &mut Criterion) {
+    let key_state = create_key_state();
+    let mut group = c.benchmark_group("run_frame");
+    group.throughput(Throughput::Elements(CYCLES_PER_FRAME as u64));
+
+    for (name, rom) in roms() {
+        for cache in [false, true] {
+            let mut chip8 = Chip8::new();
+            chip8.seed_rng(1);
+            chip8.decode_cache = cache;
+            chip8.load_rom(rom.clone()).unwrap();
+
+            let id = BenchmarkId::new(name, if cache {"cached"} else {"uncached"});
+            group.bench_function(id, |b| b.iter(|| {
+                chip8.run_frame(black_box(&key_state), CYCLES_PER_FRAME).unwrap();
+            }));
+        }
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "jit")]
+fn bench_jit(c: &mut Criterion) {
+    use chip8::jit::Jit;
+
+    let key_state = create_key_state();
+    let mut group = c.benchmark_group("run_frame");
+    group.throughput(Throughput::Elements(CYCLES_PER_FRAME as u64));
+
+    for (name, rom) in roms() {
+        let mut chip8 = Chip8::new();
+        chip8.seed_rng(1);
+        chip8.load_rom(rom).unwrap();
+        let mut jit = Jit::new().unwrap();
+
+        group.bench_function(BenchmarkId::new(name, "jit"), |b| b.iter(|| {
+            jit.run_frame(&mut chip8, black_box(&key_state), CYCLES_PER_FRAME).unwrap();
+        }));
+    }
+
+    group.finish();
+}
+
+#[cfg(not(feature = "jit"))]
+criterion_group!(benches, bench_decode_cache);
+#[cfg(feature = "jit")]
+criterion_group!(benches, bench_decode_cache, bench_jit);
+criterion_main!(benches);
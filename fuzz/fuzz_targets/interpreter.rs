@@ -0,0 +1,56 @@
+#![no_main]
+
+// feeds arbitrary ROMs and key presses to the interpreter, any panic is a bug.
+// every way a ROM can misbehave should come back as a Chip8Error instead
+//
+//     cargo +nightly fuzz run interpreter
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use chip8::chip8::Chip8;
+use chip8::drivers::keyboard::{KeyboardDriver, create_key_state};
+use chip8::quirks::Quirks;
+
+const MAX_FRAMES: usize = 64;
+const MAX_CYCLES_PER_FRAME: u32 = 64;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    quirks: u8,
+    cycles_per_frame: u8,
+    seed: u64,
+    // bit n set means hex key n is held down for that frame
+    keys: Vec<u16>,
+    rom: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut chip8 = Chip8::new();
+    chip8.seed_rng(input.seed);
+    chip8.quirks = Quirks {
+        vf_reset: input.quirks & 1 != 0,
+        memory_increment: input.quirks & 2 != 0,
+        shift_uses_vy: input.quirks & 4 != 0,
+        jump_uses_vx: input.quirks & 8 != 0,
+        wrap_sprites: input.quirks & 16 != 0,
+        display_wait: input.quirks & 32 != 0,
+    };
+
+    if chip8.load_rom(input.rom).is_err() {
+        return;
+    }
+
+    let cycles = input.cycles_per_frame as u32 % MAX_CYCLES_PER_FRAME + 1;
+    let mut key_state = create_key_state();
+
+    for frame in 0..MAX_FRAMES {
+        let keys = input.keys.get(frame).copied().unwrap_or(0);
+        for k in 0..16 {
+            key_state.insert(KeyboardDriver::int_to_key(k).unwrap(), keys & (1 << k) != 0);
+        }
+
+        if chip8.run_frame(&key_state, cycles).is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,505 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::Serialize;
+use serde_json::json;
+use crate::chip8::{MAX_ROM_SIZE, MEMORY_SIZE, STACK_SIZE};
+use crate::instruction::{decode, decode_for, Instruction, Platform};
+
+#[cfg(test)]
+#[path = "./analysis_tests.rs"]
+mod analysis_tests;
+
+const PROGRAM_START: u16 = 0x200;
+
+// instruction sets beyond the original CHIP-8
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Extension {
+    Schip,
+    XoChip,
+}
+
+impl Extension {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Extension::Schip => "SCHIP",
+            Extension::XoChip => "XO-CHIP",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    Code,
+    Data,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningKind {
+    // an opcode the interpreter can't decode
+    InvalidOpcode,
+    // a jump, call or fall through that leaves memory or the ROM
+    OutOfBounds,
+    OddJump,
+    // FX55 or FX33 writing over reachable instructions
+    SelfModifying,
+    // recursion, or calls nested deeper than the stack
+    StackOverflow,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Warning {
+    pub addr: u16,
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+// straight line run of instructions, entered at start and left from end
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub start: u16,
+    // address of the last instruction
+    pub end: u16,
+    // blocks execution can continue in, for a call that's the return address
+    pub successors: Vec<u16>,
+    // subroutine called at the end of the block
+    pub call: Option<u16>,
+}
+
+// what can be found out about a ROM without running it, by following every
+// path from 0x200 through jumps, calls and skips
+#[derive(Clone, Debug, Default)]
+pub struct Analysis {
+    pub size: usize,
+    // reachable instructions, address to opcode
+    pub code: BTreeMap<u16, u16>,
+    // control flow graph over the reachable instructions, keyed by start address
+    pub blocks: BTreeMap<u16, Block>,
+    // reachable instructions that aren't plain CHIP-8
+    pub extensions: Vec<(u16, u16, Extension)>,
+    // keys checked by EX9E/EXA1 with a known VX
+    pub keys: BTreeSet<u8>,
+    // EX9E/EXA1 with a VX that depends on the program's input
+    pub dynamic_keys: bool,
+    // FX0A, any key can be pressed
+    pub waits_for_key: bool,
+    // BNNN instructions, where the jump target isn't known
+    pub indirect_jumps: Vec<u16>,
+    pub warnings: Vec<Warning>,
+}
+
+// known register values, None when a register could hold more than one value
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct State {
+    v: [Option<u8>; 16],
+    i: Option<u16>,
+}
+
+impl State {
+    fn merge(&self, other: &State) -> State {
+        let mut merged = *self;
+        for (m, v) in merged.v.iter_mut().zip(other.v.iter()) {
+            if *m != *v {
+                *m = None;
+            }
+        }
+        if merged.i != other.i {
+            merged.i = None;
+        }
+        merged
+    }
+}
+
+// where execution can continue after an instruction
+enum Flow {
+    Next,
+    Skip,
+    Jump(u16),
+    Call(u16),
+    // 00EE, 00FD and BNNN, nothing to follow from here
+    Stop,
+}
+
+impl Analysis {
+    pub fn fits(&self) -> bool {
+        self.size <= MAX_ROM_SIZE
+    }
+
+    pub fn extensions_used(&self) -> BTreeSet<Extension> {
+        self.extensions.iter().map(|(_, _, ext)| *ext).collect()
+    }
+
+    // the ROM split into runs of code and data, as (region, first address, last address)
+    pub fn regions(&self) -> Vec<(Region, u16, u16)> {
+        let mut is_code = vec![false; self.size];
+        for (addr, op) in self.code.iter() {
+            let start = (addr - PROGRAM_START) as usize;
+            for byte in is_code.iter_mut().skip(start).take(instruction_len(*op)) {
+                *byte = true;
+            }
+        }
+
+        let mut regions: Vec<(Region, u16, u16)> = Vec::new();
+        for (i, code) in is_code.iter().enumerate() {
+            let region = if *code {Region::Code} else {Region::Data};
+            let addr = PROGRAM_START + i as u16;
+
+            match regions.last_mut() {
+                Some((r, _, end)) if *r == region => *end = addr,
+                _ => regions.push((region, addr, addr)),
+            }
+        }
+
+        regions
+    }
+
+    // everything above as one JSON object, addresses are plain numbers
+    pub fn to_json(&self) -> serde_json::Value {
+        let regions: Vec<_> = self.regions().iter().map(|(region, start, end)| json!({
+            "kind": match region {Region::Code => "code", Region::Data => "data"},
+            "start": start,
+            "end": end,
+        })).collect();
+        let extensions: Vec<_> = self.extensions.iter().map(|(addr, op, ext)| json!({
+            "addr": addr,
+            "opcode": format!("{:04X}", op),
+            "extension": ext.name(),
+        })).collect();
+        let blocks: Vec<_> = self.blocks.values().map(|b| json!({
+            "start": b.start,
+            "end": b.end,
+            "successors": b.successors,
+            "call": b.call,
+        })).collect();
+
+        json!({
+            "size": self.size,
+            "fits": self.fits(),
+            "regions": regions,
+            "extensions": extensions,
+            "keys": self.keys,
+            "dynamic_keys": self.dynamic_keys,
+            "waits_for_key": self.waits_for_key,
+            "indirect_jumps": self.indirect_jumps,
+            "blocks": blocks,
+            "warnings": self.warnings,
+        })
+    }
+
+    fn warn(&mut self, addr: u16, kind: WarningKind, message: String) {
+        let warning = Warning {addr, kind, message};
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+}
+
+pub fn analyze(rom: &[u8]) -> Analysis {
+    let mut analysis = Analysis {size: rom.len(), ..Analysis::default()};
+    let mut states: BTreeMap<u16, State> = BTreeMap::new();
+    // instruction to the instructions it can continue at, with the call target for 2NNN
+    let mut edges: BTreeMap<u16, (Vec<u16>, Option<u16>)> = BTreeMap::new();
+    // FX55 and FX33 with a known I, as (instruction, first byte written, last byte written)
+    let mut writes = Vec::new();
+    let mut work = vec![(PROGRAM_START, State::default())];
+    let rom_end = PROGRAM_START as usize + rom.len();
+
+    while let Some((addr, state)) = work.pop() {
+        // revisit an instruction only when what's known about the registers shrinks
+        let state = match states.get(&addr) {
+            Some(prev) => {
+                let merged = prev.merge(&state);
+                if merged == *prev {
+                    continue;
+                }
+                merged
+            },
+            None => state,
+        };
+        states.insert(addr, state);
+
+        let op = match read_op(rom, addr) {
+            Some(op) => op,
+            None => continue,
+        };
+        analysis.code.insert(addr, op);
+
+        let ext = extension(op);
+        if let Some(ext) = ext {
+            if !analysis.extensions.iter().any(|(a, _, _)| *a == addr) {
+                analysis.extensions.push((addr, op, ext));
+            }
+        }
+
+        // extension opcodes are followed anyway, the ROM is probably meant for another interpreter
+        let instr = decode_any(op);
+        match instr {
+            Some(Instruction::Skp {x}) | Some(Instruction::Sknp {x}) => match state.v[x as usize] {
+                Some(key) if key < 16 => {analysis.keys.insert(key);},
+                Some(_) => (),
+                None => analysis.dynamic_keys = true,
+            },
+            Some(Instruction::LdVxK {..}) => analysis.waits_for_key = true,
+            Some(Instruction::Store {x}) => writes.extend(state.i.map(|i| (addr, i as usize, i as usize + x as usize))),
+            Some(Instruction::Bcd {..}) => writes.extend(state.i.map(|i| (addr, i as usize, i as usize + 2))),
+            Some(Instruction::JpV0(_)) if !analysis.indirect_jumps.contains(&addr) => analysis.indirect_jumps.push(addr),
+            _ => (),
+        }
+
+        if let Err(e) = decode(op) {
+            let message = match ext {
+                Some(ext) => format!("{:04X} is a {} instruction", op, ext.name()),
+                None => e.to_string(),
+            };
+            analysis.warn(addr, WarningKind::InvalidOpcode, message);
+        }
+
+        let next = addr.wrapping_add(instruction_len(op) as u16);
+        let after = instr.map_or(state, |instr| transfer(instr, state));
+
+        let (successors, call) = match instr.map(flow) {
+            None => (vec![], None),
+            Some(Flow::Next) => (vec![next], None),
+            Some(Flow::Skip) => {
+                // a skipped F000 NNNN is 4 bytes long
+                let skipped = read_op(rom, next).map_or(2, instruction_len) as u16;
+                (vec![next, next.wrapping_add(skipped)], None)
+            },
+            Some(Flow::Jump(target)) => (vec![target], None),
+            Some(Flow::Call(target)) => (vec![next], Some(target)),
+            Some(Flow::Stop) => (vec![], None),
+        };
+
+        if let Some(target) = op_target(op) {
+            if target as usize + 1 >= MEMORY_SIZE {
+                analysis.warn(addr, WarningKind::OutOfBounds, format!("{:04X} jumps past the end of memory", op));
+            } else if target < PROGRAM_START || target as usize >= rom_end {
+                analysis.warn(addr, WarningKind::OutOfBounds, format!("{:04X} jumps outside the ROM", op));
+            }
+            if target % 2 == 1 {
+                analysis.warn(addr, WarningKind::OddJump, format!("{:04X} jumps to an odd address", op));
+            }
+        }
+        for succ in successors.iter().filter(|s| op_target(op) != Some(**s)) {
+            if *succ as usize >= rom_end {
+                analysis.warn(addr, WarningKind::OutOfBounds, String::from("execution runs past the end of the ROM"));
+            }
+        }
+
+        match call {
+            // the subroutine could have changed anything, carry on after it with nothing known
+            Some(target) => {
+                work.push((target, after));
+                work.push((next, State::default()));
+            },
+            None => work.extend(successors.iter().map(|s| (*s, after))),
+        }
+
+        edges.insert(addr, (successors, call));
+    }
+
+    for (addr, first, last) in writes {
+        let overwritten: Vec<u16> = analysis.code.iter()
+            .filter(|(a, op)| (**a as usize) <= last && **a as usize + instruction_len(**op) > first)
+            .map(|(a, _)| *a)
+            .collect();
+        if let Some(code) = overwritten.first() {
+            let op = analysis.code[&addr];
+            analysis.warn(addr, WarningKind::SelfModifying, format!(
+                "{:04X} writes {:#05X}-{:#05X}, over the instruction at {:#05X}", op, first, last, code
+            ));
+        }
+    }
+
+    analysis.blocks = build_blocks(&analysis.code, &edges);
+    check_stack_depth(&mut analysis, &edges);
+
+    analysis.extensions.sort();
+    analysis.indirect_jumps.sort();
+    analysis.warnings.sort();
+    analysis
+}
+
+// a block starts at the entry point, at branch and call targets, and wherever paths join
+fn build_blocks(code: &BTreeMap<u16, u16>, edges: &BTreeMap<u16, (Vec<u16>, Option<u16>)>) -> BTreeMap<u16, Block> {
+    let next = |addr: u16| addr.wrapping_add(instruction_len(code[&addr]) as u16);
+
+    let mut leaders = BTreeSet::from([PROGRAM_START]);
+    let mut preds: BTreeMap<u16, usize> = BTreeMap::new();
+    for (addr, (successors, call)) in edges.iter() {
+        if call.is_some() || successors.as_slice() != [next(*addr)] {
+            leaders.extend(successors.iter().copied());
+            leaders.extend(*call);
+        }
+        for succ in successors.iter().chain(call.iter()) {
+            *preds.entry(*succ).or_default() += 1;
+        }
+    }
+    // joins, e.g. the instruction after a skip that's also a jump target
+    leaders.extend(preds.iter().filter(|(_, n)| **n > 1).map(|(addr, _)| *addr));
+
+    let mut blocks = BTreeMap::new();
+    for start in leaders.iter().filter(|l| code.contains_key(l)) {
+        let mut end = *start;
+        loop {
+            let (successors, call) = &edges[&end];
+            let following = next(end);
+            if call.is_some() || successors.as_slice() != [following] || leaders.contains(&following) || !code.contains_key(&following) {
+                blocks.insert(*start, Block {start: *start, end, successors: successors.clone(), call: *call});
+                break;
+            }
+            end = following;
+        }
+    }
+
+    blocks
+}
+
+// walks the call graph from 0x200, every level of calls uses a stack entry
+fn check_stack_depth(analysis: &mut Analysis, edges: &BTreeMap<u16, (Vec<u16>, Option<u16>)>) {
+    // calls made by each subroutine without following the calls themselves, as (call site, target)
+    let calls_from = |entry: u16| {
+        let mut seen = BTreeSet::new();
+        let mut work = vec![entry];
+        let mut calls = Vec::new();
+
+        while let Some(addr) = work.pop() {
+            if !seen.insert(addr) {
+                continue;
+            }
+            if let Some((successors, call)) = edges.get(&addr) {
+                calls.extend(call.map(|c| (addr, c)));
+                work.extend(successors.iter().copied());
+            }
+        }
+        calls
+    };
+
+    let mut entries = vec![PROGRAM_START];
+    entries.extend(edges.values().filter_map(|(_, call)| *call));
+    let graph: BTreeMap<u16, Vec<(u16, u16)>> = entries.iter().map(|e| (*e, calls_from(*e))).collect();
+
+    // depth first, a call back into a subroutine that's still on the path is recursion
+    fn depth(entry: u16, graph: &BTreeMap<u16, Vec<(u16, u16)>>, path: &mut Vec<u16>, memo: &mut BTreeMap<u16, usize>, warnings: &mut Vec<(u16, u16)>) -> usize {
+        if let Some(d) = memo.get(&entry) {
+            return *d;
+        }
+        path.push(entry);
+
+        let mut deepest = 0;
+        for (site, target) in graph.get(&entry).into_iter().flatten() {
+            if path.contains(target) {
+                warnings.push((*site, *target));
+                continue;
+            }
+            deepest = deepest.max(1 + depth(*target, graph, path, memo, warnings));
+        }
+
+        path.pop();
+        memo.insert(entry, deepest);
+        deepest
+    }
+
+    let mut recursive = Vec::new();
+    let max_depth = depth(PROGRAM_START, &graph, &mut Vec::new(), &mut BTreeMap::new(), &mut recursive);
+
+    for (site, target) in recursive {
+        analysis.warn(site, WarningKind::StackOverflow, format!(
+            "recursive call to {:#05X}, more than {} levels overflow the stack", target, STACK_SIZE
+        ));
+    }
+    if max_depth > STACK_SIZE {
+        analysis.warn(PROGRAM_START, WarningKind::StackOverflow, format!(
+            "calls nest {} deep, the stack holds {}", max_depth, STACK_SIZE
+        ));
+    }
+}
+
+fn read_op(rom: &[u8], addr: u16) -> Option<u16> {
+    if addr < PROGRAM_START || addr as usize + 1 >= MEMORY_SIZE {
+        return None;
+    }
+
+    let i = (addr - PROGRAM_START) as usize;
+    let b1 = *rom.get(i)?;
+    let b2 = *rom.get(i + 1)?;
+    Some(u16::from_be_bytes([b1, b2]))
+}
+
+// the instruction on whichever platform has it, CHIP-8 decodes 5XYN for any N
+fn decode_any(op: u16) -> Option<Instruction> {
+    decode_for(Platform::XoChip, op).or_else(|_| decode(op)).ok()
+}
+
+// F000 NNNN carries a 16 bit address in the following word
+fn instruction_len(op: u16) -> usize {
+    decode_any(op).map_or(2, |instr| instr.size())
+}
+
+// destination of 1NNN and 2NNN
+fn op_target(op: u16) -> Option<u16> {
+    match decode_any(op)? {
+        Instruction::Jp(nnn) | Instruction::Call(nnn) => Some(nnn),
+        _ => None,
+    }
+}
+
+fn flow(instr: Instruction) -> Flow {
+    match instr {
+        Instruction::Ret | Instruction::Exit | Instruction::JpV0(_) => Flow::Stop,
+        Instruction::Jp(nnn) => Flow::Jump(nnn),
+        Instruction::Call(nnn) => Flow::Call(nnn),
+        _ if instr.is_skip() => Flow::Skip,
+        _ => Flow::Next,
+    }
+}
+
+// what's known after instr, only constants loaded with 6XNN/ANNN and adjusted with 7XNN, 8XY0 or FX1E are tracked
+fn transfer(instr: Instruction, mut state: State) -> State {
+    let regs = &mut state.v;
+
+    match instr {
+        Instruction::LdImm {x, nn} => regs[x as usize] = Some(nn),
+        Instruction::AddImm {x, nn} => regs[x as usize] = regs[x as usize].map(|v| v.wrapping_add(nn)),
+        Instruction::LdReg {x, y} => regs[x as usize] = regs[y as usize],
+        Instruction::Or {x, ..} | Instruction::And {x, ..} | Instruction::Xor {x, ..} | Instruction::AddReg {x, ..}
+            | Instruction::Sub {x, ..} | Instruction::Shr {x, ..} | Instruction::Subn {x, ..} | Instruction::Shl {x, ..} => {
+            regs[x as usize] = None;
+            regs[0xF] = None;
+        },
+        Instruction::LdI(nnn) => state.i = Some(nnn),
+        Instruction::Rnd {x, ..} | Instruction::LdVxDt {x} | Instruction::LdVxK {x} => regs[x as usize] = None,
+        Instruction::Drw {..} | Instruction::DrwLarge {..} => regs[0xF] = None,
+        Instruction::AddI {x} => state.i = state.i.zip(regs[x as usize]).map(|(i, v)| i.wrapping_add(v as u16)),
+        Instruction::LdF {x} => state.i = regs[x as usize].map(|v| (v & 0xF) as u16 * 5),
+        // I may or may not move depending on the memory_increment quirk
+        Instruction::Store {..} => state.i = None,
+        Instruction::Load {x} => {
+            for reg in regs.iter_mut().take(x as usize + 1) {
+                *reg = None;
+            }
+            state.i = None;
+        },
+        Instruction::LoadFlags {x} => {
+            for reg in regs.iter_mut().take(x as usize + 1) {
+                *reg = None;
+            }
+        },
+        Instruction::LoadRange {x, y} => {
+            for reg in regs.iter_mut().take(x.max(y) as usize + 1).skip(x.min(y) as usize) {
+                *reg = None;
+            }
+        },
+        Instruction::LdILong => state.i = None,
+        _ => (),
+    }
+
+    state
+}
+
+pub fn extension(op: u16) -> Option<Extension> {
+    match decode_for(Platform::XoChip, op).ok()?.platform() {
+        Platform::Chip8 => None,
+        Platform::Schip => Some(Extension::Schip),
+        Platform::XoChip => Some(Extension::XoChip),
+    }
+}
@@ -0,0 +1,172 @@
+use super::*;
+use crate::rom_builder::RomBuilder;
+
+#[test]
+fn test_code_and_data_regions() {
+	// jumps over 4 bytes of sprite data
+	let rom = RomBuilder::new().ld_i(0x204).jp(0x208).bytes(&[0xF0, 0x90, 0x90, 0xF0]).drw(0, 0, 4).jp(0x20A).build();
+	let analysis = analyze(&rom);
+
+	assert_eq!(analysis.code.keys().copied().collect::<Vec<_>>(), [0x200, 0x202, 0x208, 0x20A]);
+	assert_eq!(analysis.regions(), [
+		(Region::Code, 0x200, 0x203),
+		(Region::Data, 0x204, 0x207),
+		(Region::Code, 0x208, 0x20B),
+	]);
+	assert!(analysis.fits());
+}
+
+#[test]
+fn test_skips_and_calls_are_followed() {
+	// 0x200: se v0, 1 / 0x202: jp 0x208 / 0x204: call 0x20A / 0x206: jp 0x206 / 0x208: jp 0x208 / 0x20A: ret
+	let rom = RomBuilder::new().se(0, 1).jp(0x208).call(0x20A).jp(0x206).jp(0x208).ret().build();
+	let analysis = analyze(&rom);
+
+	assert_eq!(analysis.code.len(), 6);
+	assert!(analysis.regions().iter().all(|(r, _, _)| *r == Region::Code));
+}
+
+#[test]
+fn test_used_keys() {
+	let rom = RomBuilder::new()
+		.ld(1, 5)
+		.ld(2, 7).add(2, 1)
+		.rnd(3, 0xF)
+		.skp(1).cls()     // 5
+		.sknp(2).cls()    // 8
+		.skp(3).cls()     // unknown
+		.ld_key(4)
+		.build();
+	let analysis = analyze(&rom);
+
+	assert_eq!(analysis.keys.iter().copied().collect::<Vec<_>>(), [5, 8]);
+	assert!(analysis.dynamic_keys);
+	assert!(analysis.waits_for_key);
+}
+
+#[test]
+fn test_keys_on_converging_paths() {
+	// v0 is 1 or 2 depending on the skip, so the key checked at 0x206 isn't known
+	let rom = RomBuilder::new().ld(0, 1).se(1, 0).ld(0, 2).skp(0).build();
+	let analysis = analyze(&rom);
+
+	assert!(analysis.dynamic_keys);
+}
+
+#[test]
+fn test_extensions() {
+	let rom = RomBuilder::new()
+		.op(0x00FF)             // SCHIP hires
+		.drw(0, 1, 0)           // SCHIP 16x16 sprite
+		.op(0xF000).op(0x1234)  // XO-CHIP long I
+		.op(0x5122)             // XO-CHIP save range
+		.op(0x00FD)             // SCHIP exit
+		.op(0x00FE)             // never reached
+		.build();
+	let analysis = analyze(&rom);
+
+	assert_eq!(analysis.extensions, [
+		(0x200, 0x00FF, Extension::Schip),
+		(0x202, 0xD010, Extension::Schip),
+		(0x204, 0xF000, Extension::XoChip),
+		(0x208, 0x5122, Extension::XoChip),
+		(0x20A, 0x00FD, Extension::Schip),
+	]);
+	assert_eq!(analysis.extensions_used().into_iter().collect::<Vec<_>>(), [Extension::Schip, Extension::XoChip]);
+	assert_eq!(analysis.regions().last(), Some(&(Region::Data, 0x20C, 0x20D)));
+}
+
+#[test]
+fn test_indirect_jumps_stop_analysis() {
+	let rom = RomBuilder::new().jp_v0(0x204).cls().cls().build();
+	let analysis = analyze(&rom);
+
+	assert_eq!(analysis.indirect_jumps, [0x200]);
+	assert_eq!(analysis.code.len(), 1);
+}
+
+#[test]
+fn test_too_large() {
+	assert!(!analyze(&vec![0; MAX_ROM_SIZE + 1]).fits());
+}
+
+fn warnings(rom: RomBuilder) -> Vec<(u16, WarningKind)> {
+	analyze(&rom.build()).warnings.iter().map(|w| (w.addr, w.kind)).collect()
+}
+
+#[test]
+fn test_blocks() {
+	// 0x200: ld v0 / 0x202: se v0 / 0x204: jp 0x200 / 0x206: call 0x20C / 0x208: cls / 0x20A: jp 0x20A / 0x20C: ret
+	let rom = RomBuilder::new().ld(0, 1).se(0, 1).jp(0x200).call(0x20C).cls().jp(0x20A).ret().build();
+	let analysis = analyze(&rom);
+
+	let block = |start: u16, end: u16, successors: &[u16], call: Option<u16>| Block {start, end, successors: successors.to_vec(), call};
+	assert_eq!(analysis.blocks.values().cloned().collect::<Vec<_>>(), [
+		block(0x200, 0x202, &[0x204, 0x206], None),
+		block(0x204, 0x204, &[0x200], None),
+		block(0x206, 0x206, &[0x208], Some(0x20C)),
+		block(0x208, 0x208, &[0x20A], None),
+		block(0x20A, 0x20A, &[0x20A], None),
+		block(0x20C, 0x20C, &[], None),
+	]);
+	assert!(analysis.warnings.is_empty());
+}
+
+#[test]
+fn test_invalid_opcodes() {
+	assert_eq!(warnings(RomBuilder::new().cls().op(0x8008).cls()), [(0x202, WarningKind::InvalidOpcode)]);
+	// the SCHIP instruction is reported but the code after it is still followed
+	let analysis = analyze(&RomBuilder::new().op(0x00FF).op(0xFFFF).build());
+	assert_eq!(analysis.warnings.iter().map(|w| w.addr).collect::<Vec<_>>(), [0x200, 0x202]);
+}
+
+#[test]
+fn test_out_of_bounds_and_odd_jumps() {
+	assert_eq!(warnings(RomBuilder::new().jp(0xFFF)), [(0x200, WarningKind::OutOfBounds), (0x200, WarningKind::OddJump)]);
+	assert_eq!(warnings(RomBuilder::new().call(0x100).jp(0x202)), [(0x200, WarningKind::OutOfBounds)]);
+	assert_eq!(warnings(RomBuilder::new().cls().jp(0x203).op(0x1203)), [(0x202, WarningKind::OddJump), (0x203, WarningKind::InvalidOpcode)]);
+	// falls off the end of the ROM
+	assert_eq!(warnings(RomBuilder::new().cls()), [(0x200, WarningKind::OutOfBounds)]);
+}
+
+#[test]
+fn test_self_modifying_code() {
+	// stores v0-v1 over the jump at 0x206
+	let rom = RomBuilder::new().ld_i(0x206).store(1).cls().jp(0x206);
+	assert_eq!(warnings(rom), [(0x202, WarningKind::SelfModifying)]);
+
+	// writes to data past the code are fine
+	let rom = RomBuilder::new().ld_i(0x208).bcd(0).jp(0x204).bytes(&[0, 0, 0]);
+	assert!(warnings(rom).is_empty());
+}
+
+#[test]
+fn test_recursion() {
+	// 0x202 calls itself
+	let rom = RomBuilder::new().call(0x202).call(0x202).ret();
+	assert_eq!(warnings(rom), [(0x202, WarningKind::StackOverflow)]);
+}
+
+#[test]
+fn test_nested_calls() {
+	// each subroutine calls the next, 17 deep
+	let mut rom = RomBuilder::new();
+	for i in 0..17u16 {
+		rom = rom.call(0x200 + 4 * (i + 1)).ret();
+	}
+	let rom = rom.ret();
+	assert_eq!(warnings(rom.clone()), [(0x200, WarningKind::StackOverflow)]);
+
+	let json = analyze(&rom.build()).to_json();
+	assert_eq!(json["warnings"][0]["kind"], "stack-overflow");
+	assert_eq!(json["warnings"][0]["addr"], 0x200);
+}
+
+#[test]
+fn test_real_roms_are_clean() {
+	for name in ["pong.chp8", "ibm_logo.chp8"] {
+		let rom = std::fs::read(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(name)).unwrap();
+		let analysis = analyze(&rom);
+		assert!(analysis.warnings.is_empty(), "{}: {:?}", name, analysis.warnings);
+	}
+}
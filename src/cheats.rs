@@ -0,0 +1,303 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use crate::chip8::{Chip8, MEMORY_SIZE};
+
+#[cfg(test)]
+#[path = "./cheats_tests.rs"]
+mod cheats_tests;
+
+// candidates shown by `list`, a search usually needs narrowing a few times before it's useful
+const MAX_LISTED: usize = 32;
+
+const HELP: &str = "\
+search                    start a RAM search, every address is a candidate
+equal|changed             keep candidates that stayed the same or changed since the last step
+increased|decreased       keep candidates that went up or down since the last step
+value VAL                 keep candidates holding VAL
+list                      show the candidates and their values
+poke ADDR VAL             write a byte once
+freeze ADDR VAL [IF]      hold ADDR at VAL, only while it reads IF when given
+unfreeze ADDR             stop holding ADDR
+cheats                    show the held addresses
+numbers are hex, cheats are saved per ROM as they change";
+
+// hold a byte of memory at a value, like a Game Genie code. with `compare` the byte is only
+// written while it holds that value, for addresses shared by more than one thing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cheat {
+    pub addr: u16,
+    pub value: u8,
+    pub compare: Option<u8>,
+}
+
+impl Cheat {
+    // "ADDR VALUE [COMPARE]" in hex
+    pub fn parse(text: &str) -> Result<Cheat, String> {
+        let err = || format!("expected 'ADDR VALUE [COMPARE]' in hex, got '{}'", text);
+        let parts: Vec<&str> = text.split_whitespace().collect();
+
+        match parts[..] {
+            [addr, value] | [addr, value, _] => Ok(Cheat {
+                addr: parse_addr(addr).ok_or_else(err)?,
+                value: parse_hex(value).ok_or_else(err)?,
+                compare: match parts.get(2) {
+                    Some(c) => Some(parse_hex(c).ok_or_else(err)?),
+                    None => None,
+                },
+            }),
+            _ => Err(err()),
+        }
+    }
+
+    pub fn apply(&self, chip8: &mut Chip8) {
+        let current = chip8.memory()[self.addr as usize];
+        if current != self.value && self.compare.is_none_or(|c| c == current) {
+            // checked by parse, can't fail
+            chip8.write_memory(self.addr, &[self.value]).unwrap();
+        }
+    }
+}
+
+impl fmt::Display for Cheat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:03X} {:02X}", self.addr, self.value)?;
+        if let Some(c) = self.compare {
+            write!(f, " {:02X}", c)?;
+        }
+        Ok(())
+    }
+}
+
+// a ROM's cheats, one per line in its cheat file, '#' starts a comment
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheatList {
+    cheats: Vec<Cheat>,
+}
+
+impl CheatList {
+    // no file yet is no cheats
+    pub fn load(path: &Path) -> Result<CheatList, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => CheatList::parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(CheatList::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<CheatList, String> {
+        let mut cheats = Vec::new();
+
+        for (num, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            cheats.push(Cheat::parse(line).map_err(|e| format!("line {}: {}", num + 1, e))?);
+        }
+
+        Ok(CheatList {cheats})
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut text = String::from("# ADDR VALUE [COMPARE] in hex\n");
+        for cheat in self.cheats.iter() {
+            text += &format!("{}\n", cheat);
+        }
+        fs::write(path, text)
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    // replaces a cheat for the same address
+    pub fn add(&mut self, cheat: Cheat) {
+        self.remove(cheat.addr);
+        self.cheats.push(cheat);
+    }
+
+    pub fn remove(&mut self, addr: u16) -> bool {
+        let len = self.cheats.len();
+        self.cheats.retain(|c| c.addr != addr);
+        self.cheats.len() != len
+    }
+
+    // called after every frame
+    pub fn apply(&self, chip8: &mut Chip8) {
+        for cheat in self.cheats.iter() {
+            cheat.apply(chip8);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    Value(u8),
+}
+
+impl Comparison {
+    fn matches(self, before: u8, now: u8) -> bool {
+        match self {
+            Comparison::Equal => now == before,
+            Comparison::Changed => now != before,
+            Comparison::Increased => now > before,
+            Comparison::Decreased => now < before,
+            Comparison::Value(val) => now == val,
+        }
+    }
+}
+
+// find where a game keeps something by how it changes: start a search, play, narrow the
+// candidates down with what happened to the value, repeat
+#[derive(Clone, Debug)]
+pub struct RamSearch {
+    previous: Vec<u8>,
+    candidates: Vec<u16>,
+}
+
+impl RamSearch {
+    pub fn new(memory: &[u8]) -> RamSearch {
+        RamSearch {previous: memory.to_vec(), candidates: (0..memory.len() as u16).collect()}
+    }
+
+    // keep the candidates whose value compares to the last step's, memory becomes the last step
+    pub fn narrow(&mut self, memory: &[u8], comparison: Comparison) {
+        let previous = &self.previous;
+        self.candidates.retain(|&a| comparison.matches(previous[a as usize], memory[a as usize]));
+        self.previous = memory.to_vec();
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+}
+
+// the --cheats console: commands typed on stdin, run between frames
+pub struct CheatConsole {
+    pub cheats: CheatList,
+    path: PathBuf,
+    search: Option<RamSearch>,
+    commands: Option<Receiver<String>>,
+}
+
+impl CheatConsole {
+    // changes to the cheats are saved to path
+    pub fn new(cheats: CheatList, path: PathBuf) -> CheatConsole {
+        CheatConsole {cheats, path, search: None, commands: None}
+    }
+
+    // <dir>/<sha1 of the ROM>.txt
+    pub fn path_for(dir: &Path, sha1: &str) -> PathBuf {
+        dir.join(format!("{}.txt", sha1))
+    }
+
+    // read commands from stdin on a thread of its own so frames keep running while it waits
+    pub fn read_stdin(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        self.commands = Some(rx);
+        println!("cheat console ready, type help for commands");
+    }
+
+    // run the commands typed since the last call, the cheats are held separately with apply
+    pub fn poll(&mut self, chip8: &mut Chip8) {
+        let lines: Vec<String> = self.commands.as_ref().map(|rx| rx.try_iter().collect()).unwrap_or_default();
+        for line in lines {
+            match self.run_command(&line, chip8) {
+                Ok(out) if out.is_empty() => (),
+                Ok(out) => println!("{}", out),
+                Err(e) => println!("error: {}", e),
+            }
+        }
+    }
+
+    // returns what to print
+    pub fn run_command(&mut self, line: &str, chip8: &mut Chip8) -> Result<String, String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let memory = chip8.memory();
+
+        let comparison = match parts[..] {
+            ["equal"] => Some(Comparison::Equal),
+            ["changed"] => Some(Comparison::Changed),
+            ["increased"] => Some(Comparison::Increased),
+            ["decreased"] => Some(Comparison::Decreased),
+            ["value", val] => Some(Comparison::Value(parse_hex(val).ok_or_else(|| format!("invalid value '{}'", val))?)),
+            _ => None,
+        };
+        if let Some(comparison) = comparison {
+            let search = self.search.as_mut().ok_or("no search running, start one with search")?;
+            search.narrow(memory, comparison);
+            return Ok(format!("candidates: {}", search.candidates().len()));
+        }
+
+        match parts[..] {
+            [] => Ok(String::new()),
+            ["help"] => Ok(HELP.to_string()),
+            ["search"] => {
+                self.search = Some(RamSearch::new(memory));
+                Ok(format!("candidates: {}", MEMORY_SIZE))
+            },
+            ["list"] => {
+                let search = self.search.as_ref().ok_or("no search running, start one with search")?;
+                let mut lines: Vec<String> = search.candidates().iter().take(MAX_LISTED)
+                    .map(|&a| format!("{:03X}  {:02X}", a, memory[a as usize]))
+                    .collect();
+                if search.candidates().len() > MAX_LISTED {
+                    lines.push(format!("and {} more", search.candidates().len() - MAX_LISTED));
+                }
+                Ok(lines.join("\n"))
+            },
+            ["poke", addr, val] => {
+                let cheat = Cheat::parse(&format!("{} {}", addr, val))?;
+                chip8.write_memory(cheat.addr, &[cheat.value]).map_err(|e| e.to_string())?;
+                Ok(String::new())
+            },
+            ["freeze", ..] => {
+                let cheat = Cheat::parse(&parts[1..].join(" "))?;
+                self.cheats.add(cheat);
+                self.save()?;
+                Ok(format!("holding {}", cheat))
+            },
+            ["unfreeze", addr] => {
+                let addr = parse_addr(addr).ok_or_else(|| format!("invalid address '{}'", addr))?;
+                if !self.cheats.remove(addr) {
+                    return Err(format!("{:03X} isn't held", addr));
+                }
+                self.save()?;
+                Ok(String::new())
+            },
+            ["cheats"] => Ok(self.cheats.cheats().iter().map(|c| c.to_string()).collect::<Vec<_>>().join("\n")),
+            _ => Err(format!("unknown command '{}', type help for the list", line.trim())),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        self.cheats.save(&self.path).map_err(|e| format!("could not save cheats to {}: {}", self.path.display(), e))
+    }
+}
+
+fn parse_hex(text: &str) -> Option<u8> {
+    u8::from_str_radix(text.trim_start_matches("0x"), 16).ok()
+}
+
+fn parse_addr(text: &str) -> Option<u16> {
+    u16::from_str_radix(text.trim_start_matches("0x"), 16).ok().filter(|a| (*a as usize) < MEMORY_SIZE)
+}
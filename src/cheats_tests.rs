@@ -0,0 +1,108 @@
+use super::*;
+use crate::drivers::keyboard::create_key_state;
+use crate::rom_builder::{chip8_with_rom, RomBuilder};
+
+// saving to a directory of the test's own, cleared first in case an earlier run failed part way
+fn console(name: &str) -> CheatConsole {
+	let dir = std::env::temp_dir().join(name);
+	let _ = std::fs::remove_dir_all(&dir);
+	CheatConsole::new(CheatList::default(), dir.join("cheats.txt"))
+}
+
+#[test]
+fn test_parse_cheats() {
+	let list = CheatList::parse("# lives\n3F0 09\n\n0x3F1 FF 05\n").unwrap();
+
+	assert_eq!(list.cheats(), &[
+		Cheat {addr: 0x3F0, value: 0x09, compare: None},
+		Cheat {addr: 0x3F1, value: 0xFF, compare: Some(0x05)},
+	]);
+	assert_eq!(list.cheats()[1].to_string(), "3F1 FF 05");
+	assert_eq!(CheatList::parse("3F0 09\n1000 01").unwrap_err(), "line 2: expected 'ADDR VALUE [COMPARE]' in hex, got '1000 01'");
+	assert!(CheatList::parse("3F0").is_err());
+}
+
+#[test]
+fn test_cheats_are_held() {
+	// counts V0 up and stores it at 0x300 every loop
+	let rom = RomBuilder::new().add(0, 1).ld_i(0x300).store(0).jp(0x200).build();
+	let mut chip8 = chip8_with_rom(&rom);
+	let mut list = CheatList::default();
+	list.add(Cheat {addr: 0x300, value: 0x42, compare: None});
+	// only while the byte reads 0x00, it never does again once written
+	list.add(Cheat {addr: 0x301, value: 0x07, compare: Some(0x00)});
+	list.add(Cheat {addr: 0x302, value: 0x07, compare: Some(0x01)});
+
+	for _ in 0..3 {
+		chip8.run_frame(&create_key_state(), 8).unwrap();
+		list.apply(&mut chip8);
+		assert_eq!(chip8.memory()[0x300..0x303], [0x42, 0x07, 0x00]);
+	}
+
+	// code is a byte like any other, and runs as written
+	list.add(Cheat {addr: 0x201, value: 0x10, compare: None});
+	list.apply(&mut chip8);
+	chip8.set_pc(0x200);
+	chip8.step(&create_key_state()).unwrap();
+	assert_eq!(chip8.register(0), 6 + 0x10);
+}
+
+#[test]
+fn test_ram_search_narrows_candidates() {
+	let mut memory = vec![0u8; 8];
+	let mut search = RamSearch::new(&memory);
+
+	memory[1] = 1;
+	memory[2] = 1;
+	memory[5] = 9;
+	search.narrow(&memory, Comparison::Changed);
+	assert_eq!(search.candidates(), &[1, 2, 5]);
+
+	memory[2] = 0;
+	search.narrow(&memory, Comparison::Decreased);
+	assert_eq!(search.candidates(), &[2]);
+
+	let mut search = RamSearch::new(&memory);
+	memory[1] = 3;
+	search.narrow(&memory, Comparison::Increased);
+	assert_eq!(search.candidates(), &[1]);
+	search.narrow(&memory, Comparison::Equal);
+	search.narrow(&memory, Comparison::Value(3));
+	assert_eq!(search.candidates(), &[1]);
+	search.narrow(&memory, Comparison::Value(4));
+	assert!(search.candidates().is_empty());
+}
+
+#[test]
+fn test_console_finds_and_freezes_a_counter() {
+	let rom = RomBuilder::new().add(0, 1).ld_i(0x300).store(0).jp(0x200).build();
+	let mut chip8 = chip8_with_rom(&rom);
+	let mut console = console("chip8_cheat_console_test");
+	let key_state = create_key_state();
+
+	assert!(console.run_command("list", &mut chip8).is_err());
+	assert_eq!(console.run_command("search", &mut chip8).unwrap(), "candidates: 4096");
+	for _ in 0..3 {
+		chip8.run_frame(&key_state, 8).unwrap();
+		console.run_command("increased", &mut chip8).unwrap();
+	}
+	// V0 itself isn't in memory, the copy at 0x300 is
+	assert_eq!(console.run_command("value 6", &mut chip8).unwrap(), "candidates: 1");
+	assert_eq!(console.run_command("list", &mut chip8).unwrap(), "300  06");
+
+	assert_eq!(console.run_command("freeze 300 63", &mut chip8).unwrap(), "holding 300 63");
+	console.cheats.apply(&mut chip8);
+	assert_eq!(chip8.memory()[0x300], 0x63);
+
+	// saved as they change
+	assert_eq!(CheatList::load(&console.path).unwrap(), console.cheats);
+	console.run_command("unfreeze 300", &mut chip8).unwrap();
+	assert!(CheatList::load(&console.path).unwrap().cheats().is_empty());
+	assert!(console.run_command("unfreeze 300", &mut chip8).is_err());
+
+	console.run_command("poke 301 AA", &mut chip8).unwrap();
+	assert_eq!(chip8.memory()[0x301], 0xAA);
+	assert!(console.run_command("jump", &mut chip8).is_err());
+
+	std::fs::remove_dir_all(console.path.parent().unwrap()).unwrap();
+}
@@ -1,21 +1,42 @@
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::error::Error;
+use std::fmt;
 use std::thread::sleep;
 use std::time::Duration;
 use crate::utils::*;
+use crate::image::Image;
+use crate::palette::Palette;
+use crate::quirks::Quirks;
+use crate::framebuffer::Framebuffer;
+use crate::instruction::{decode, Instruction};
 use crate::drivers::keyboard::{KeyState, KeyboardDriver};
 
 #[cfg(test)]
 #[path = "./chip8_tests.rs"]
 mod chip8_tests;
 
+#[cfg(test)]
+#[path = "./golden_tests.rs"]
+mod golden_tests;
+
+#[cfg(test)]
+#[path = "./chip8_prop_tests.rs"]
+mod chip8_prop_tests;
+
 type OpCode = u16;
 
 pub const PIXEL_WIDTH: usize = 64;
 pub const PIXEL_HEIGHT: usize = 32;
+pub const MEMORY_SIZE: usize = 4096;
+pub const MAX_ROM_SIZE: usize = MEMORY_SIZE - ROM_START_ADDRESS as usize;
 const ROM_START_ADDRESS: u16 = 0x200;
+pub const STACK_SIZE: usize = 16;
+// largest pixel size to_image draws at, 4096x2048
+pub const MAX_IMAGE_SCALE: u32 = 64;
 
 
-const FONT_SET: [u8; 80] = [
+pub const FONT_SET: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -35,33 +56,119 @@ const FONT_SET: [u8; 80] = [
 ];
 
 
+// everything that can go wrong while running a ROM. when an instruction fails
+// the machine is left as it was before the instruction, with pc pointing at it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    RomTooLarge {size: usize},
+    PcOutOfBounds {pc: u16},
+    InvalidOpcode {pc: u16, op: u16},
+    StackOverflow {pc: u16},
+    StackUnderflow {pc: u16},
+    MemoryOutOfBounds {pc: u16, addr: usize},
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Chip8Error::RomTooLarge {size} => write!(f, "ROM is {} bytes, at most {} fit in memory", size, MAX_ROM_SIZE),
+            Chip8Error::PcOutOfBounds {pc} => write!(f, "program counter {:#05x} is past the end of memory", pc),
+            Chip8Error::InvalidOpcode {pc, op} => write!(f, "invalid opcode {:04X} at {:#05x}", op, pc),
+            Chip8Error::StackOverflow {pc} => write!(f, "stack overflow, more than {} nested calls at {:#05x}", STACK_SIZE, pc),
+            Chip8Error::StackUnderflow {pc} => write!(f, "return without a call at {:#05x}", pc),
+            Chip8Error::MemoryOutOfBounds {pc, addr} => write!(f, "access to address {:#x} past the end of memory at {:#05x}", addr, pc),
+        }
+    }
+}
+
+impl Error for Chip8Error {}
+
+// what a FrameHook wants done with the next instruction of a frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cycle<S> {
+    // let the interpreter execute it
+    Step,
+    // the hook ran this many cycles itself, e.g. a compiled block
+    Ran(u32),
+    // end the frame here without ticking the timers
+    Stop(S),
+}
+
+// lets the debugger, scripts and the jit share run_frame's loop, called around each instruction
+pub trait FrameHook {
+    type Stop;
+
+    // before the instruction at pc, with `left` of the frame's cycles to go
+    fn before_step(&mut self, _chip8: &mut Chip8, _left: u32) -> Cycle<Self::Stop> {
+        Cycle::Step
+    }
+
+    // after the interpreter executed an instruction
+    fn after_step(&mut self, _chip8: &mut Chip8) {}
+
+    // once the frame's instructions have run, before the timers tick
+    fn end_frame(&mut self, _chip8: &mut Chip8) {}
+}
+
+// plain run_frame
+impl FrameHook for () {
+    type Stop = ();
+}
+
+// everything a running program can change, to go back to later. quirks and the decode cache
+// setting belong to the machine and aren't included
+#[derive(Clone)]
+pub struct Snapshot {
+    memory: [u8; MEMORY_SIZE],
+    registers: [u8; 16],
+    stack: [u16; STACK_SIZE],
+    stack_pointer: usize,
+    pc: u16,
+    index_register: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+    framebuffer: Framebuffer,
+    draw_flag: bool,
+    rng: StdRng,
+}
+
 pub struct Chip8 {
-    memory: [u8; 4096], 
+    memory: [u8; MEMORY_SIZE], 
     registers: [u8; 16],
-    stack: [u16; 16],       // holds PC for when CALL was executed
+    stack: [u16; STACK_SIZE],       // holds PC for when CALL was executed
     stack_pointer: usize,
     pc: u16,
     index_register: u16,
     delay_timer: u8,
     pub sound_timer: u8,
-    pub display_memory: [[u32; PIXEL_WIDTH]; PIXEL_HEIGHT],
+    pub framebuffer: Framebuffer,
     pub draw_flag: bool,
+    pub quirks: Quirks,
+    // look instructions up in `decoded` instead of decoding them every time
+    pub decode_cache: bool,
+    // instruction decoded at each address, cleared whenever memory under it is written
+    decoded: Vec<Option<Instruction>>,
+    rng: StdRng,
 }
 
 
 impl Chip8 {
     pub fn new() -> Chip8{
         let mut chip8 = Chip8 {
-            memory: [0; 4096], 
+            memory: [0; MEMORY_SIZE], 
             registers: [0; 16],
-            stack: [0; 16],
+            stack: [0; STACK_SIZE],
             stack_pointer: 0,
             pc: ROM_START_ADDRESS,
             index_register: 0,
             delay_timer: 0,
             sound_timer: 0,
-            display_memory: [[0; 64]; 32],  // (row, col)
+            framebuffer: Framebuffer::new(),
             draw_flag: false,
+            quirks: Quirks::default(),
+            decode_cache: true,
+            decoded: vec![None; MEMORY_SIZE],
+            rng: StdRng::from_entropy(),
         };
 
         chip8.load_fontset();
@@ -69,11 +176,157 @@ impl Chip8 {
         chip8
     }
 
-    pub fn load_rom(&mut self, rom: Vec<u8>) {
+    // make CXNN reproducible, for tests and recordings
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn load_rom(&mut self, rom: Vec<u8>) -> Result<(), Chip8Error> {
+        if rom.len() > MAX_ROM_SIZE {
+            return Err(Chip8Error::RomTooLarge {size: rom.len()});
+        }
+
         // load into memory
         for (pos, e) in rom.iter().enumerate() {
             self.memory[pos+ROM_START_ADDRESS as usize] = *e;
         }
+        self.invalidate_decoded(ROM_START_ADDRESS as usize, rom.len());
+
+        Ok(())
+    }
+
+    // current display memory as an image, each chip8 pixel becomes a scale x scale block.
+    // the scale is kept within 1..=MAX_IMAGE_SCALE
+    pub fn to_image(&self, scale: u32, palette: &Palette) -> Image {
+        let scale = scale.clamp(1, MAX_IMAGE_SCALE);
+        let mut image = Image::new(PIXEL_WIDTH as u32 * scale, PIXEL_HEIGHT as u32 * scale);
+
+        for (y, row) in self.framebuffer.rows().enumerate() {
+            for (x, val) in row.enumerate() {
+                image.fill_block(x as u32, y as u32, scale, palette.color(val));
+            }
+        }
+
+        image
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            memory: self.memory,
+            registers: self.registers,
+            stack: self.stack,
+            stack_pointer: self.stack_pointer,
+            pc: self.pc,
+            index_register: self.index_register,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            framebuffer: self.framebuffer,
+            draw_flag: self.draw_flag,
+            rng: self.rng.clone(),
+        }
+    }
+
+    // put the machine back as it was when the snapshot was taken, CXNN included
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        // only decoded instructions over bytes that differ are dropped, the rest are still right
+        for addr in 0..MEMORY_SIZE {
+            if self.memory[addr] != snapshot.memory[addr] {
+                self.invalidate_decoded(addr, 1);
+            }
+        }
+
+        self.memory = snapshot.memory;
+        self.registers = snapshot.registers;
+        self.stack = snapshot.stack;
+        self.stack_pointer = snapshot.stack_pointer;
+        self.pc = snapshot.pc;
+        self.index_register = snapshot.index_register;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.framebuffer = snapshot.framebuffer;
+        self.draw_flag = snapshot.draw_flag;
+        self.rng = snapshot.rng.clone();
+    }
+
+    // read only views of the machine state for tests and tools
+    pub fn register(&self, x: usize) -> u8 {
+        self.registers[x]
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    // return addresses of the subroutines currently being executed, innermost last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer]
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    // write access for debuggers
+    pub fn set_register(&mut self, x: usize, val: u8) {
+        self.registers[x] = val;
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    pub fn set_index_register(&mut self, i: u16) {
+        self.index_register = i;
+    }
+
+    pub fn set_delay_timer(&mut self, val: u8) {
+        self.delay_timer = val;
+    }
+
+    // V0-VF at once, for recompiled code that keeps them in a local
+    pub fn registers(&self) -> [u8; 16] {
+        self.registers
+    }
+
+    pub fn set_registers(&mut self, registers: [u8; 16]) {
+        self.registers = registers;
+    }
+
+    // keeps the return addresses below sp, new entries are 0
+    pub fn set_stack_pointer(&mut self, sp: usize) -> Result<(), Chip8Error> {
+        if sp > STACK_SIZE {
+            return Err(Chip8Error::StackOverflow {pc: self.pc});
+        }
+
+        for entry in self.stack.iter_mut().take(sp).skip(self.stack_pointer) {
+            *entry = 0;
+        }
+        self.stack_pointer = sp;
+        Ok(())
+    }
+
+    // patch memory from outside the program, e.g. the platform byte test ROMs read from 0x1FF
+    pub fn write_memory(&mut self, addr: u16, bytes: &[u8]) -> Result<(), Chip8Error> {
+        let end = addr as usize + bytes.len();
+        if end > MEMORY_SIZE {
+            return Err(Chip8Error::MemoryOutOfBounds {pc: self.pc, addr: end - 1});
+        }
+
+        self.memory[addr as usize..end].copy_from_slice(bytes);
+        self.invalidate_decoded(addr as usize, bytes.len());
+        Ok(())
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.framebuffer.pixel(x, y)
     }
 
     // load fontset into memory at predefined location
@@ -81,16 +334,104 @@ impl Chip8 {
         self.memory[..80].copy_from_slice(&FONT_SET);
     }
 
-    pub fn exec_cycle(&mut self, key_state: &KeyState) {
+    pub fn exec_cycle(&mut self, key_state: &KeyState) -> Result<(), Chip8Error> {
         self.draw_flag = false;
 
-        // fetch next opcode at PC
-        let opcode = self.get_next_opcode();
+        self.step(key_state)?;
+        self.tick_timers();
 
-        // decode instruction
-        self.handle_opcode(opcode, key_state);
-        
-        // handle timers
+        // let exec cycle run at ~60 Hz
+        sleep(Duration::new(1/60, 0));
+        Ok(())
+    }
+
+    // run one 60 Hz frame: `cycles` instructions followed by a single timer tick.
+    // draw_flag is set if any instruction in the frame drew to the screen
+    pub fn run_frame(&mut self, key_state: &KeyState, cycles: u32) -> Result<(), Chip8Error> {
+        self.run_frame_with(key_state, cycles, &mut ()).map(|_| ())
+    }
+
+    // run_frame with a hook around each instruction. returns what the hook stopped the frame
+    // with, None if it ran to the end
+    pub fn run_frame_with<H: FrameHook>(&mut self, key_state: &KeyState, cycles: u32, hook: &mut H) -> Result<Option<H::Stop>, Chip8Error> {
+        self.draw_flag = false;
+
+        let mut left = cycles;
+        while left > 0 {
+            match hook.before_step(self, left) {
+                Cycle::Step => {
+                    self.step(key_state)?;
+                    hook.after_step(self);
+                    left -= 1;
+                },
+                Cycle::Ran(n) => left -= n.clamp(1, left),
+                Cycle::Stop(stop) => return Ok(Some(stop)),
+            }
+
+            // the original interpreter waited for the vertical blank before drawing
+            if self.draw_flag && self.quirks.display_wait {
+                break;
+            }
+        }
+
+        hook.end_frame(self);
+        self.tick_timers();
+        Ok(None)
+    }
+
+    // execute a single instruction without touching the timers
+    pub fn step(&mut self, key_state: &KeyState) -> Result<(), Chip8Error> {
+        let pc = self.pc;
+
+        let res = self.fetch().and_then(|instr| self.execute(instr, key_state));
+        if res.is_err() {
+            self.pc = pc;
+        }
+
+        res
+    }
+
+    // run an instruction decoded ahead of time as if it had been fetched from addr, for
+    // recompiled code. like step, pc is left at addr if it fails
+    pub fn step_decoded(&mut self, addr: u16, instr: Instruction, key_state: &KeyState) -> Result<(), Chip8Error> {
+        self.pc = addr.wrapping_add(instr.size() as u16);
+
+        let res = self.execute(instr, key_state);
+        if res.is_err() {
+            self.pc = addr;
+        }
+
+        res
+    }
+
+    // decoded instruction at PC, from the cache when it's been seen before, and move PC past it
+    fn fetch(&mut self) -> Result<Instruction, Chip8Error> {
+        let pc = self.pc as usize;
+        if self.decode_cache {
+            if let Some(instr) = self.decoded.get(pc).copied().flatten() {
+                self.pc += 2;
+                return Ok(instr);
+            }
+        }
+
+        let op = self.get_next_opcode()?;
+        let instr = decode(op).map_err(|_| Chip8Error::InvalidOpcode {pc: pc as u16, op})?;
+        if self.decode_cache {
+            self.decoded[pc] = Some(instr);
+        }
+
+        Ok(instr)
+    }
+
+    // forget instructions overlapping len bytes written at addr, including one starting the byte before
+    fn invalidate_decoded(&mut self, addr: usize, len: usize) {
+        let end = (addr + len).min(MEMORY_SIZE);
+        for entry in self.decoded[addr.saturating_sub(1)..end].iter_mut() {
+            *entry = None;
+        }
+    }
+
+    pub fn tick_timers(&mut self) {
         if self.delay_timer > 0 {
             self.delay_timer -= 1
         }
@@ -98,187 +439,226 @@ impl Chip8 {
         if self.sound_timer > 0 {
             self.sound_timer -= 1
         }
-
-        // let exec cycle run at ~60 Hz
-        sleep(Duration::new(1/60, 0));
     }
 
     // get 2 byte opcode and update program counter
-    fn get_next_opcode(&mut self) -> OpCode {
+    fn get_next_opcode(&mut self) -> Result<OpCode, Chip8Error> {
+        if self.pc as usize + 1 >= MEMORY_SIZE {
+            return Err(Chip8Error::PcOutOfBounds {pc: self.pc});
+        }
+
         let b1: u8 = self.memory[self.pc as usize];
         self.pc += 1;
         let b2: u8 = self.memory[self.pc as usize];
         self.pc += 1;
 
-        concat_bytes(b1, b2)
+        Ok(concat_bytes(b1, b2))
     }
 
-    fn handle_opcode(&mut self, op: OpCode, key_state: &KeyState) {
-        let mut nibs: [u16; 4] = [0; 4];
-        for n in 0..4 {
-            nibs[3-n] = get_nth_nibble(op, n as u8);
+    // checks that `len` bytes starting at I are inside memory
+    fn check_index_range(&self, len: usize) -> Result<(), Chip8Error> {
+        let end = self.index_register as usize + len;
+        if end > MEMORY_SIZE {
+            return Err(Chip8Error::MemoryOutOfBounds {pc: self.pc - 2, addr: end - 1});
         }
-        
-        let nn = get_first_n_nibbles(op, 2) as u8;
-        let nnn = get_first_n_nibbles(op, 3);
 
-        match nibs {
-            [0, 0, 0xE, 0]      => self.clear_screen(),
+        Ok(())
+    }
+
+    // run an instruction, PC already points past it
+    fn execute(&mut self, instr: Instruction, key_state: &KeyState) -> Result<(), Chip8Error> {
+        match instr {
+            Instruction::Cls                => self.clear_screen(),
 
             // return from subroutine
-            [0, 0, 0xE, 0xE]    => {self.stack_pointer -= 1; self.pc = self.stack[self.stack_pointer];},
+            Instruction::Ret                => {
+                if self.stack_pointer == 0 {
+                    return Err(Chip8Error::StackUnderflow {pc: self.pc - 2});
+                }
+                self.stack_pointer -= 1; self.pc = self.stack[self.stack_pointer];
+            },
 
             // Jump
-            [1, _, _, _]        => self.pc = nnn,
+            Instruction::Jp(nnn)            => self.pc = nnn,
 
             // Call Subroutine
-            [2, _, _, _]        => {self.stack[self.stack_pointer] = self.pc; self.stack_pointer += 1; self.pc = nnn},
+            Instruction::Call(nnn)          => {
+                if self.stack_pointer == STACK_SIZE {
+                    return Err(Chip8Error::StackOverflow {pc: self.pc - 2});
+                }
+                self.stack[self.stack_pointer] = self.pc; self.stack_pointer += 1; self.pc = nnn
+            },
 
             //Skips the next instruction if VX equals NN
-            [3, x, _, _]        => {if self.registers[x as usize] == nn as u8 {self.pc += 2}},
+            Instruction::SeImm {x, nn}      => {if self.registers[x as usize] == nn {self.pc += 2}},
 
             // Skips the next instruction if VX does not equal NN
-            [4, x, _, _]        => {if self.registers[x as usize] != nn as u8 {self.pc += 2}},
+            Instruction::SneImm {x, nn}     => {if self.registers[x as usize] != nn {self.pc += 2}},
 
-            // Skips the next instruction if VX equals VY            
-            [5, x, y, _]        => {if self.registers[x as usize] == self.registers[y as usize] {self.pc += 2}},
+            // Skips the next instruction if VX equals VY
+            Instruction::SeReg {x, y}       => {if self.registers[x as usize] == self.registers[y as usize] {self.pc += 2}},
 
             // Sets VX to NN
-            [6, x, _, _]        => {self.registers[x as usize] = nn},
+            Instruction::LdImm {x, nn}      => {self.registers[x as usize] = nn},
 
             // Adds NN to VX. (Carry flag is not changed);
-            [7, x, _, _]        => { 
-                let overflow_res = addition_with_overflow(self.registers[x as usize], nn as u8);
+            Instruction::AddImm {x, nn}     => {
+                let overflow_res = addition_with_overflow(self.registers[x as usize], nn);
                 self.registers[x as usize] = overflow_res.val;
             },
-                
+
             // Sets VX to the value of VY.
-            [8, x, y, 0]        => {self.registers[x as usize] = self.registers[y as usize]},
+            Instruction::LdReg {x, y}       => {self.registers[x as usize] = self.registers[y as usize]},
 
             // bitwise or
-            [8, x, y, 1]        => {self.registers[x as usize] |= self.registers[y as usize]}
+            Instruction::Or {x, y}          => {self.registers[x as usize] |= self.registers[y as usize]; self.logic_vf_reset();}
 
             // bitwise and
-            [8, x, y, 2]        => {self.registers[x as usize] &= self.registers[y as usize]}
+            Instruction::And {x, y}         => {self.registers[x as usize] &= self.registers[y as usize]; self.logic_vf_reset();}
 
             // bitwise xor
-            [8, x, y, 3]        => {self.registers[x as usize] ^= self.registers[y as usize]},
+            Instruction::Xor {x, y}         => {self.registers[x as usize] ^= self.registers[y as usize]; self.logic_vf_reset();},
 
             // Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there is not.
-            [8, x, y, 4]        => { 
+            Instruction::AddReg {x, y}      => {
                 let overflow_res = addition_with_overflow(self.registers[x as usize], self.registers[y as usize]);
                 self.registers[x as usize] = overflow_res.val;
                 if overflow_res.overflowed {self.registers[0xF] = 1} else {self.registers[0xF] = 0};
             }
 
             // VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1 when there is not.
-            [8, x, y, 5]        => {
+            Instruction::Sub {x, y}         => {
                 let overflow_res = subtract_with_overflow(self.registers[x as usize], self.registers[y as usize]);
-                if overflow_res.overflowed {self.registers[0xF] = 0} else {self.registers[0xF] = 1}
                 self.registers[x as usize] = overflow_res.val;
+                if overflow_res.overflowed {self.registers[0xF] = 0} else {self.registers[0xF] = 1}
             },
 
             // Stores the least significant bit of VX in VF and then shifts VX to the right by 1.[b]
-            [8, x, _, 6]        => {self.registers[0xF] = self.registers[x as usize] & 1; self.registers[x as usize] >>= 1;},
+            Instruction::Shr {x, y}         => {
+                let val = if self.quirks.shift_uses_vy {self.registers[y as usize]} else {self.registers[x as usize]};
+                self.registers[x as usize] = val >> 1;
+                self.registers[0xF] = val & 1;
+            },
 
             // Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when there is not.
-            [8, x, y, 7]        => {
+            Instruction::Subn {x, y}        => {
                 let overflow_res = subtract_with_overflow(self.registers[y as usize], self.registers[x as usize]);
-                if overflow_res.overflowed {self.registers[0xF] = 0} else {self.registers[0xF] = 1}
                 self.registers[x as usize] = overflow_res.val;
+                if overflow_res.overflowed {self.registers[0xF] = 0} else {self.registers[0xF] = 1}
             },
-            
+
             // Stores the most significant bit of VX in VF and then shifts VX to the left by 1
-            [8, x, _, 0xE]      => {self.registers[0xF] = self.registers[x as usize] & 0x80; self.registers[x as usize] <<= 1;},
+            Instruction::Shl {x, y}         => {
+                let val = if self.quirks.shift_uses_vy {self.registers[y as usize]} else {self.registers[x as usize]};
+                self.registers[x as usize] = val << 1;
+                self.registers[0xF] = val >> 7;
+            },
 
             // Skips the next instruction if VX does not equal VY
-            [9, x, y, 0]        => {if self.registers[x as usize] != self.registers[y as usize] {self.pc += 2}},
+            Instruction::SneReg {x, y}      => {if self.registers[x as usize] != self.registers[y as usize] {self.pc += 2}},
 
             // Sets I to the address NNN.
-            [0xA, _, _, _]      => self.index_register = nnn,
+            Instruction::LdI(nnn)           => self.index_register = nnn,
 
             // Jumps to the address NNN plus V0.
-            [0xB, _, _, _]      => self.pc = self.registers[0] as u16 + nnn,
+            Instruction::JpV0(nnn)          => {
+                let x = (nnn >> 8) as usize;
+                let offset = if self.quirks.jump_uses_vx {self.registers[x]} else {self.registers[0]};
+                self.pc = offset as u16 + nnn;
+            },
 
             // Sets VX to the result of a bitwise and operation on a random number and NN
-            [0xC, x, _, _]      => self.registers[x as usize] = rand::thread_rng().gen_range(0..255) & nn,
+            Instruction::Rnd {x, nn}        => self.registers[x as usize] = self.rng.gen_range(0..255) & nn,
 
             // Draw sprites
-            [0xD, x, y, n]      => self.draw_sprite(x as u8, y as u8, n as u8),
+            Instruction::Drw {x, y, n}      => {
+                self.check_index_range(n as usize)?;
+                self.draw_sprite(x, y, n)
+            },
 
             // Skips the next instruction if the key stored in VX is pressed.
-            [0xE, x, 9, 0xE]    => {
+            Instruction::Skp {x}            => {
                 match KeyboardDriver::int_to_key(self.registers[x as usize]) {
-                    Some(kc) => if key_state[&kc] {self.pc += 2;},
+                    Some(kc) => if key_state.get(&kc).copied().unwrap_or(false) {self.pc += 2;},
                     None => (),
                 }
             }
 
             // Skips the next instruction if the key stored in VX is not pressed
-            [0xE, x, 0xA, 1]    => {
+            Instruction::Sknp {x}           => {
                 match KeyboardDriver::int_to_key(self.registers[x as usize]) {
-                    Some(kc) => {if !key_state[&kc] {self.pc += 2;}},
+                    Some(kc) => {if !key_state.get(&kc).copied().unwrap_or(false) {self.pc += 2;}},
                     None => (),
                 }
             }
 
             // Sets VX to the value of the delay timer.
-            [0xF, x, 0, 7]      => {self.registers[x as usize] = self.delay_timer},
+            Instruction::LdVxDt {x}         => {self.registers[x as usize] = self.delay_timer},
 
             // Sets the delay timer to VX.
-            [0xF, x, 1, 5]      => {self.delay_timer = self.registers[x as usize]},
+            Instruction::LdDtVx {x}         => {self.delay_timer = self.registers[x as usize]},
 
             // Sets the sound timer to VX.
-            [0xF, x, 1, 8]      => {self.sound_timer = self.registers[x as usize]},
+            Instruction::LdStVx {x}         => {self.sound_timer = self.registers[x as usize]},
 
             // Adds VX to I. VF is not affected
-            [0xF, x, 1, 0xE]    => self.index_register += self.registers[x as usize] as u16,
+            Instruction::AddI {x}           => self.index_register = self.index_register.wrapping_add(self.registers[x as usize] as u16),
+
+            // A key press is awaited, and then stored in VX. Blocking Operation.
+            Instruction::LdVxK {x}          => self.wait_for_keypress(x as usize, key_state),
 
-            // A key press is awaited, and then stored in VX. Blocking Operation. 
-            [0xF, x, 0, 0xA]    => self.wait_for_keypress(x as usize, key_state),
-            
             // Sets I to the location of the sprite for the character in VX.
-            [0xF, x, 2, 9]      => {
-                self.index_register = (self.registers[x as usize] * 5) as u16; 
+            Instruction::LdF {x}            => {
+                self.index_register = (self.registers[x as usize] & 0xF) as u16 * 5;
             },
 
             // Stores the binary-coded decimal representation of VX,
-            [0xF, x, 3, 3]      => {
+            Instruction::Bcd {x}            => {
+                self.check_index_range(3)?;
 
                 let dec = to_binary_encoded_decimal(self.registers[x as usize], 3);
 
                 for (i, dig) in dec.into_iter().enumerate() {
                     self.memory[(self.index_register as usize) + i] = dig;
                 }
+                self.invalidate_decoded(self.index_register as usize, 3);
             },
 
-            // Stores from V0 to VX (including VX) in memory, starting at address I. 
+            // Stores from V0 to VX (including VX) in memory, starting at address I.
             // The offset from I is increased by 1 for each value written, but I itself is left unmodified.
-            [0xF, x, 5, 5]      => {
-                for i in 0..x+1 { 
-                    self.memory[(self.index_register as usize) + i as usize] = self.registers[i as usize];
+            Instruction::Store {x}          => {
+                self.check_index_range(x as usize + 1)?;
+                for i in 0..x as usize + 1 {
+                    self.memory[(self.index_register as usize) + i] = self.registers[i];
                 }
+                self.invalidate_decoded(self.index_register as usize, x as usize + 1);
+                if self.quirks.memory_increment {self.index_register = self.index_register.wrapping_add(x as u16 + 1)}
             },
 
             // Fills from V0 to VX (including VX) with values from memory, starting at address I.
             // The offset from I is increased by 1 for each value written, but I itself is left unmodified.
-            [0xF, x, 6, 5]      => {
-                for i in 0..x+1 { 
-                    self.registers[i as usize] = self.memory[self.index_register as usize + i as usize];
+            Instruction::Load {x}           => {
+                self.check_index_range(x as usize + 1)?;
+                for i in 0..x as usize + 1 {
+                    self.registers[i] = self.memory[self.index_register as usize + i];
                 }
+                if self.quirks.memory_increment {self.index_register = self.index_register.wrapping_add(x as u16 + 1)}
             },
 
-            [_, _, _, _] => panic!("Invalid OpCode as Hex: '{:#04x}' and as Decimal: '{}'", op, op)
+            // SCHIP and XO-CHIP instructions aren't implemented, decode never returns them
+            _ => {
+                let pc = self.pc - 2;
+                let op = concat_bytes(self.memory[pc as usize], self.memory[pc as usize + 1]);
+                return Err(Chip8Error::InvalidOpcode {pc, op});
+            },
         };
+
+        Ok(())
     }
 
     // Opcode Methods
     fn clear_screen(&mut self) {
-        for row in self.display_memory.iter_mut() {
-            for col in row.iter_mut() {
-                *col = 0;
-            }
-        }
+        self.framebuffer.clear();
     }
 
     fn draw_sprite(&mut self, vx: u8, vy: u8, h: u8) {
@@ -291,39 +671,35 @@ impl Chip8 {
         for irow in 0..(h as usize) {
 
             // don't wrap draw position
-            if irow + y_pos >= PIXEL_HEIGHT {
+            if irow + y_pos >= PIXEL_HEIGHT && !self.quirks.wrap_sprites {
                 continue
             }
+            let y = (y_pos + irow) % PIXEL_HEIGHT;
 
-            let pixel = self.memory[self.index_register as usize + irow ];
-
-            for icol in 0..8 {
-                
-                // don't wrap draw position
-                if icol + x_pos >= PIXEL_WIDTH {
-                    continue
-                }
+            let sprite = self.memory[self.index_register as usize + irow];
 
-                if pixel & (0x80 >> (icol as u8)) != 0 {
-                    self.display_memory[y_pos+irow][x_pos+icol] ^= 1;
-
-                    if self.display_memory[y_pos+irow][x_pos+icol] == 0 {
-                        self.registers[0xF] = 1;
-                    }
-                }
+            // columns past the right edge are clipped unless sprites wrap
+            if self.framebuffer.draw_sprite_row(0, x_pos, y, sprite, self.quirks.wrap_sprites) {
+                self.registers[0xF] = 1;
             }
         }
     }
 
+    fn logic_vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            self.registers[0xF] = 0;
+        }
+    }
+
     fn wait_for_keypress(&mut self, reg_index: usize, key_state: &KeyState) {
-        let mut block = true;
-        for (key, pressed) in key_state.iter() {
-            if *pressed {
-                self.registers[reg_index] = KeyboardDriver::key_to_int(key).unwrap(); 
-                block = false; 
-                break
-            }
+        // lowest pressed key wins so runs are reproducible, hashmap order isn't
+        let pressed = (0..16).find(|k| {
+            KeyboardDriver::int_to_key(*k).is_some_and(|kc| key_state.get(&kc).copied().unwrap_or(false))
+        });
+
+        match pressed {
+            Some(k) => self.registers[reg_index] = k,
+            None => self.pc -= 2,
         }
-        if block {self.pc -= 2};
     }
 }
\ No newline at end of file
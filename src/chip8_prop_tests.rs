@@ -0,0 +1,87 @@
+// test names follow the opcode spelling, e.g. prop_8XYN
+#![allow(non_snake_case)]
+
+use super::*;
+use crate::drivers::keyboard::create_key_state;
+use proptest::prelude::*;
+
+// the 8XYN instructions as described by the spec, written independently of execute.
+// VF is written after VX, so when X is F the flag wins
+fn reference_8xyn(n: u8, regs: [u8; 16], x: usize, y: usize, quirks: Quirks) -> [u8; 16] {
+	let (vx, vy) = (regs[x] as u16, regs[y] as u16);
+	let shift_src = if quirks.shift_uses_vy {vy} else {vx};
+	let logic_flag = if quirks.vf_reset {Some(0)} else {None};
+
+	let (result, flag) = match n {
+		0x0 => (vy, None),
+		0x1 => (vx | vy, logic_flag),
+		0x2 => (vx & vy, logic_flag),
+		0x3 => (vx ^ vy, logic_flag),
+		0x4 => (vx + vy, Some((vx + vy > 0xFF) as u16)),
+		0x5 => (vx + 0x100 - vy, Some((vx >= vy) as u16)),
+		0x6 => (shift_src >> 1, Some(shift_src & 1)),
+		0x7 => (vy + 0x100 - vx, Some((vy >= vx) as u16)),
+		0xE => (shift_src << 1, Some(shift_src >> 7)),
+		_ => unreachable!(),
+	};
+
+	let mut out = regs;
+	out[x] = (result & 0xFF) as u8;
+	if let Some(f) = flag {
+		out[0xF] = f as u8;
+	}
+	out
+}
+
+fn chip8_with_registers(regs: [u8; 16], quirks: Quirks) -> Chip8 {
+	let mut chip8 = Chip8::new();
+	chip8.registers = regs;
+	chip8.quirks = quirks;
+	chip8
+}
+
+fn quirks_strategy() -> impl Strategy<Value = Quirks> {
+	(any::<bool>(), any::<bool>()).prop_map(|(vf_reset, shift_uses_vy)| Quirks {vf_reset, shift_uses_vy, ..Quirks::default()})
+}
+
+proptest! {
+	#[test]
+	fn prop_8XYN_matches_reference(
+		regs in any::<[u8; 16]>(),
+		x in 0..16usize,
+		y in 0..16usize,
+		n in prop::sample::select(vec![0x0u8, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE]),
+		quirks in quirks_strategy(),
+	) {
+		let mut chip8 = chip8_with_registers(regs, quirks);
+		let op = 0x8000 | (x as u16) << 8 | (y as u16) << 4 | n as u16;
+
+		chip8.execute(decode(op).unwrap(), &create_key_state()).unwrap();
+
+		prop_assert_eq!(chip8.registers, reference_8xyn(n, regs, x, y, quirks), "opcode {:04X}", op);
+	}
+
+	#[test]
+	fn prop_7XNN_wraps_and_leaves_VF(regs in any::<[u8; 16]>(), x in 0..16usize, nn in any::<u8>()) {
+		let mut chip8 = chip8_with_registers(regs, Quirks::default());
+
+		chip8.execute(decode(0x7000 | (x as u16) << 8 | nn as u16).unwrap(), &create_key_state()).unwrap();
+
+		let mut expected = regs;
+		expected[x] = ((regs[x] as u16 + nn as u16) % 256) as u8;
+		prop_assert_eq!(chip8.registers, expected);
+	}
+
+	#[test]
+	fn prop_FX33_stores_decimal_digits(val in any::<u8>(), i in 0x200u16..0xFFD) {
+		let mut regs = [0; 16];
+		regs[5] = val;
+		let mut chip8 = chip8_with_registers(regs, Quirks::default());
+		chip8.index_register = i;
+
+		chip8.execute(decode(0xF533).unwrap(), &create_key_state()).unwrap();
+
+		let i = i as usize;
+		prop_assert_eq!(&chip8.memory[i..i+3], &[val / 100, val / 10 % 10, val % 10]);
+	}
+}
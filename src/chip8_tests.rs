@@ -1,53 +1,582 @@
+// test names follow the opcode spelling, e.g. test_8XY4
+#![allow(non_snake_case)]
+
 use super::*;
-use crate::drivers::create_key_state;
+use crate::drivers::keyboard::create_key_state;
+use crate::rom_builder::RomBuilder;
+use crate::palette::Rgb;
+use sdl2::keyboard::Keycode;
+
+// load the rom and execute `steps` instructions
+fn run(rom: RomBuilder, steps: usize) -> Chip8 {
+	run_with_quirks(rom, steps, Quirks::default())
+}
+
+fn run_with_quirks(rom: RomBuilder, steps: usize, quirks: Quirks) -> Chip8 {
+	let mut chip8 = Chip8::new();
+	chip8.quirks = quirks;
+	chip8.load_rom(rom.build()).unwrap();
+
+	let key_state = create_key_state();
+	for _ in 0..steps {
+		chip8.step(&key_state).unwrap();
+	}
+
+	chip8
+}
+
+fn assert_registers(chip8: &Chip8, expected: &[(usize, u8)]) {
+	for (x, val) in expected.iter() {
+		assert_eq!(chip8.register(*x), *val, "V{:X}", x);
+	}
+}
+
+// text art of the top left corner of the screen, for readable sprite assertions
+fn screen_corner(chip8: &Chip8, width: usize, height: usize) -> Vec<String> {
+	(0..height).map(|y| (0..width).map(|x| if chip8.pixel(x, y) {'#'} else {'.'}).collect()).collect()
+}
 
 #[test]
 fn test_00E0() {
-	let rom: Vec<u8> = vec![0x00, 0xE0];	
+	let rom: Vec<u8> = vec![0x00, 0xE0];
 
 	let mut chip8 = Chip8::new();
 	let key_state = create_key_state();
-	chip8.load_rom(rom);
-	chip8.display_memory[10][10] = 1; 
+	chip8.load_rom(rom).unwrap();
+	chip8.framebuffer.set_pixel(10, 10, 1);
 
-	chip8.exec_cycle(&key_state);
+	chip8.exec_cycle(&key_state).unwrap();
 
-	assert_eq!(chip8.display_memory[10][10], 0);
+	assert!(!chip8.pixel(10, 10));
 }
 
 #[test]
 fn test_1NNN() {
-	let rom: Vec<u8> = vec![0x1A, 0xBC];	
+	let chip8 = run(RomBuilder::new().jp(0xABC), 1);
+
+	assert_eq!(chip8.pc(), 0xABC);
+}
+
+#[test]
+fn test_2NNN_and_00EE() {
+	let rom = RomBuilder::new().call(0x204).cls().ret();
+
+	let mut chip8 = run(rom, 0);
+	let key_state = create_key_state();
+
+	assert!(chip8.stack().is_empty());
+	let pc_at_start = chip8.pc();
+
+	// enter subroutine call
+	chip8.exec_cycle(&key_state).unwrap();
+
+	assert_eq!(chip8.stack(), &[pc_at_start+2]);
+	assert_eq!(chip8.pc(), 0x204);
+
+	// call immediately returns
+	chip8.exec_cycle(&key_state).unwrap();
+	assert!(chip8.stack().is_empty());
+	assert_eq!(chip8.pc(), 0x202);
+}
+
+#[test]
+fn test_3XNN() {
+	let skip = run(RomBuilder::new().ld(1, 0x42).se(1, 0x42), 2);
+	assert_eq!(skip.pc(), 0x206);
+
+	let no_skip = run(RomBuilder::new().ld(1, 0x42).se(1, 0x43), 2);
+	assert_eq!(no_skip.pc(), 0x204);
+}
+
+#[test]
+fn test_4XNN() {
+	let skip = run(RomBuilder::new().ld(1, 0x42).sne(1, 0x43), 2);
+	assert_eq!(skip.pc(), 0x206);
+
+	let no_skip = run(RomBuilder::new().ld(1, 0x42).sne(1, 0x42), 2);
+	assert_eq!(no_skip.pc(), 0x204);
+}
+
+#[test]
+fn test_5XY0() {
+	let skip = run(RomBuilder::new().ld(1, 7).ld(2, 7).se_reg(1, 2), 3);
+	assert_eq!(skip.pc(), 0x208);
+
+	let no_skip = run(RomBuilder::new().ld(1, 7).ld(2, 8).se_reg(1, 2), 3);
+	assert_eq!(no_skip.pc(), 0x206);
+}
+
+#[test]
+fn test_6XNN() {
+	let chip8 = run(RomBuilder::new().ld(0xA, 0x5C), 1);
+
+	assert_registers(&chip8, &[(0xA, 0x5C)]);
+}
+
+#[test]
+fn test_7XNN_wraps_without_touching_VF() {
+	let chip8 = run(RomBuilder::new().ld(0xF, 0x11).ld(3, 0xFF).add(3, 2), 3);
+
+	assert_registers(&chip8, &[(3, 0x01), (0xF, 0x11)]);
+}
+
+#[test]
+fn test_8XY0() {
+	let chip8 = run(RomBuilder::new().ld(2, 0x99).ld_reg(1, 2), 2);
+
+	assert_registers(&chip8, &[(1, 0x99), (2, 0x99)]);
+}
+
+#[test]
+fn test_8XY1_8XY2_8XY3() {
+	let setup = || RomBuilder::new().ld(1, 0b1100).ld(2, 0b1010).ld(0xF, 5);
+
+	assert_registers(&run(setup().or(1, 2), 4), &[(1, 0b1110), (0xF, 5)]);
+	assert_registers(&run(setup().and(1, 2), 4), &[(1, 0b1000), (0xF, 5)]);
+	assert_registers(&run(setup().xor(1, 2), 4), &[(1, 0b0110), (0xF, 5)]);
+}
+
+#[test]
+fn test_8XY1_resets_VF_with_quirk() {
+	let quirks = Quirks {vf_reset: true, ..Quirks::default()};
+	let chip8 = run_with_quirks(RomBuilder::new().ld(1, 1).ld(0xF, 5).or(1, 1), 3, quirks);
+
+	assert_registers(&chip8, &[(1, 1), (0xF, 0)]);
+}
+
+#[test]
+fn test_8XY4() {
+	let carry = run(RomBuilder::new().ld(1, 0xFF).ld(2, 0x02).add_reg(1, 2), 3);
+	assert_registers(&carry, &[(1, 0x01), (0xF, 1)]);
+
+	let exact = run(RomBuilder::new().ld(1, 0xFE).ld(2, 0x01).ld(0xF, 1).add_reg(1, 2), 4);
+	assert_registers(&exact, &[(1, 0xFF), (0xF, 0)]);
+}
+
+#[test]
+fn test_8XY5() {
+	let no_borrow = run(RomBuilder::new().ld(1, 10).ld(2, 3).sub(1, 2), 3);
+	assert_registers(&no_borrow, &[(1, 7), (0xF, 1)]);
+
+	// equal values don't borrow
+	let equal = run(RomBuilder::new().ld(1, 3).ld(2, 3).sub(1, 2), 3);
+	assert_registers(&equal, &[(1, 0), (0xF, 1)]);
+
+	let borrow = run(RomBuilder::new().ld(1, 3).ld(2, 10).sub(1, 2), 3);
+	assert_registers(&borrow, &[(1, 0xF9), (0xF, 0)]);
+}
+
+#[test]
+fn test_8XY6() {
+	let odd = run(RomBuilder::new().ld(1, 0b101).shr(1, 2), 2);
+	assert_registers(&odd, &[(1, 0b10), (0xF, 1)]);
+
+	let even = run(RomBuilder::new().ld(1, 0b100).ld(0xF, 1).shr(1, 2), 3);
+	assert_registers(&even, &[(1, 0b10), (0xF, 0)]);
+}
+
+#[test]
+fn test_8XY7() {
+	let no_borrow = run(RomBuilder::new().ld(1, 3).ld(2, 10).subn(1, 2), 3);
+	assert_registers(&no_borrow, &[(1, 7), (0xF, 1)]);
+
+	let borrow = run(RomBuilder::new().ld(1, 10).ld(2, 3).subn(1, 2), 3);
+	assert_registers(&borrow, &[(1, 0xF9), (0xF, 0)]);
+}
+
+#[test]
+fn test_8XYE() {
+	// VF holds the shifted out bit, not the masked value
+	let msb = run(RomBuilder::new().ld(1, 0x81).shl(1, 2), 2);
+	assert_registers(&msb, &[(1, 0x02), (0xF, 1)]);
+
+	let no_msb = run(RomBuilder::new().ld(1, 0x41).ld(0xF, 1).shl(1, 2), 3);
+	assert_registers(&no_msb, &[(1, 0x82), (0xF, 0)]);
+}
+
+#[test]
+fn test_shift_uses_VY_with_quirk() {
+	let quirks = Quirks {shift_uses_vy: true, ..Quirks::default()};
+
+	let shr = run_with_quirks(RomBuilder::new().ld(1, 0xFF).ld(2, 0b11).shr(1, 2), 3, quirks);
+	assert_registers(&shr, &[(1, 0b1), (2, 0b11), (0xF, 1)]);
+
+	let shl = run_with_quirks(RomBuilder::new().ld(1, 0xFF).ld(2, 0x40).shl(1, 2), 3, quirks);
+	assert_registers(&shl, &[(1, 0x80), (2, 0x40), (0xF, 0)]);
+}
+
+#[test]
+fn test_9XY0() {
+	let skip = run(RomBuilder::new().ld(1, 7).ld(2, 8).sne_reg(1, 2), 3);
+	assert_eq!(skip.pc(), 0x208);
+
+	let no_skip = run(RomBuilder::new().ld(1, 7).ld(2, 7).sne_reg(1, 2), 3);
+	assert_eq!(no_skip.pc(), 0x206);
+}
+
+#[test]
+fn test_ANNN() {
+	let chip8 = run(RomBuilder::new().ld_i(0x123), 1);
+
+	assert_eq!(chip8.index_register(), 0x123);
+}
+
+#[test]
+fn test_BNNN() {
+	let chip8 = run(RomBuilder::new().ld(0, 0x10).ld(3, 0x20).jp_v0(0x300), 3);
+	assert_eq!(chip8.pc(), 0x310);
+
+	// BXNN with the jump quirk uses VX where X is the top nibble of the address
+	let quirks = Quirks {jump_uses_vx: true, ..Quirks::default()};
+	let chip8 = run_with_quirks(RomBuilder::new().ld(0, 0x10).ld(3, 0x20).jp_v0(0x300), 3, quirks);
+	assert_eq!(chip8.pc(), 0x320);
+}
+
+#[test]
+fn test_CXNN_is_masked() {
+	let mut chip8 = Chip8::new();
+	chip8.seed_rng(7);
+	chip8.load_rom(RomBuilder::new().rnd(1, 0x0F).rnd(2, 0x00).build()).unwrap();
+
+	let key_state = create_key_state();
+	chip8.step(&key_state).unwrap();
+	chip8.step(&key_state).unwrap();
+
+	assert!(chip8.register(1) <= 0x0F);
+	assert_eq!(chip8.register(2), 0);
+}
+
+#[test]
+fn test_DXYN_draws_and_detects_collision() {
+	// font sprite for 0 at (1, 2), then drawn again on top of itself
+	let rom = RomBuilder::new().ld(0, 1).ld(1, 2).ld(2, 0).ld_font(2).drw(0, 1, 5).drw(0, 1, 5);
+
+	let chip8 = run(rom.clone(), 5);
+	assert!(chip8.draw_flag);
+	assert_registers(&chip8, &[(0xF, 0)]);
+	assert_eq!(screen_corner(&chip8, 6, 7), vec![
+		"......",
+		"......",
+		".####.",
+		".#..#.",
+		".#..#.",
+		".#..#.",
+		".####.",
+	]);
 
+	let chip8 = run(rom, 6);
+	assert_registers(&chip8, &[(0xF, 1)]);
+	assert!(screen_corner(&chip8, 6, 7).iter().all(|row| row == "......"));
+}
+
+#[test]
+fn test_DXYN_clips_at_screen_edge() {
+	// 8 wide sprite at the bottom right corner
+	let rom = RomBuilder::new().ld(0, 60).ld(1, 30).ld_i(0x300).drw(0, 1, 4).jp(0x208);
 	let mut chip8 = Chip8::new();
+	chip8.load_rom(rom.bytes(&[0; 0x300 - 0x20A]).bytes(&[0xFF; 4]).build()).unwrap();
+
 	let key_state = create_key_state();
-	chip8.load_rom(rom);
+	for _ in 0..4 {
+		chip8.step(&key_state).unwrap();
+	}
 
-	chip8.exec_cycle(&key_state);
+	let lit: Vec<(usize, usize)> = (0..PIXEL_HEIGHT)
+		.flat_map(|y| (0..PIXEL_WIDTH).map(move |x| (x, y)))
+		.filter(|(x, y)| chip8.pixel(*x, *y))
+		.collect();
 
-	assert_eq!(chip8.pc, 0xABC);
+	assert_eq!(lit.len(), 8);
+	assert!(lit.iter().all(|(x, y)| *x >= 60 && *y >= 30));
 }
 
 #[test]
-fn test_2NNN_and_00EE() {
-	let rom: Vec<u8> = vec![0x22, 0x04, 0x00, 0xE0, 0x00, 0xEE];	
+fn test_DXYN_start_position_wraps() {
+	// (65, 34) wraps to (1, 2), the font sprite for 1 has a single pixel in its top row
+	let chip8 = run(RomBuilder::new().ld(0, 65).ld(1, 34).ld(2, 1).ld_font(2).drw(0, 1, 1), 5);
+
+	assert!(chip8.pixel(3, 2));
+	assert_eq!(chip8.framebuffer.rows().flatten().filter(|p| *p != 0).count(), 1);
+}
+
+#[test]
+fn test_DXYN_wraps_with_quirk() {
+	let quirks = Quirks {wrap_sprites: true, ..Quirks::default()};
+	// top row of the font sprite for 0 (####) drawn two pixels from the right edge
+	let chip8 = run_with_quirks(RomBuilder::new().ld(0, 62).ld(1, 0).ld(2, 0).ld_font(2).drw(0, 1, 1), 5, quirks);
+
+	assert!(chip8.pixel(62, 0) && chip8.pixel(63, 0));
+	assert!(chip8.pixel(0, 0) && chip8.pixel(1, 0));
+	assert!(!chip8.pixel(2, 0));
+}
+
+#[test]
+fn test_EX9E_and_EXA1() {
+	let mut key_state = create_key_state();
+	key_state.insert(Keycode::A, true);
+
+	let rom = RomBuilder::new().ld(1, 0xA).ld(2, 0xB).skp(1).cls().sknp(2).cls().skp(2).cls().sknp(1);
 
 	let mut chip8 = Chip8::new();
+	chip8.load_rom(rom.build()).unwrap();
+	let mut pcs = Vec::new();
+	for _ in 0..7 {
+		chip8.step(&key_state).unwrap();
+		pcs.push(chip8.pc());
+	}
+
+	// skp A skips, sknp B skips, skp B and sknp A fall through
+	assert_eq!(pcs, vec![0x202, 0x204, 0x208, 0x20C, 0x20E, 0x210, 0x212]);
+}
+
+#[test]
+fn test_FX07_FX15_FX18_timers_tick_once_per_frame() {
+	let mut chip8 = Chip8::new();
+	chip8.load_rom(RomBuilder::new().ld(1, 10).ld_dt(1).ld_st(1).ld_from_dt(2).build()).unwrap();
+
 	let key_state = create_key_state();
-	chip8.load_rom(rom);
+	chip8.run_frame(&key_state, 3).unwrap();
+	assert_eq!(chip8.delay_timer(), 9);
+	assert_eq!(chip8.sound_timer, 9);
 
-	assert_eq!(chip8.stack_pointer, 0);
-	let pc_at_start = chip8.pc;
+	chip8.run_frame(&key_state, 1).unwrap();
+	assert_registers(&chip8, &[(2, 9)]);
+	assert_eq!(chip8.delay_timer(), 8);
+	assert_eq!(chip8.sound_timer, 8);
+}
 
-	// enter subroutine call
-	chip8.exec_cycle(&key_state);
+#[test]
+fn test_FX0A_blocks_until_key_pressed() {
+	let mut chip8 = Chip8::new();
+	chip8.load_rom(RomBuilder::new().ld_key(3).build()).unwrap();
 
-	assert_eq!(chip8.stack_pointer, 1);
-	assert_eq!(chip8.stack[0], pc_at_start+2);
-	assert_eq!(chip8.pc, 0x204);
+	let mut key_state = create_key_state();
+	chip8.step(&key_state).unwrap();
+	chip8.step(&key_state).unwrap();
+	assert_eq!(chip8.pc(), 0x200);
 
-	// call immediately returns
-	chip8.exec_cycle(&key_state);
-	assert_eq!(chip8.stack_pointer, 0);
-	assert_eq!(chip8.pc, 0x202);
+	key_state.insert(Keycode::Num7, true);
+	chip8.step(&key_state).unwrap();
+	assert_eq!(chip8.pc(), 0x202);
+	assert_registers(&chip8, &[(3, 7)]);
+}
+
+#[test]
+fn test_FX1E() {
+	let chip8 = run(RomBuilder::new().ld_i(0x100).ld(1, 0x20).add_i(1).ld(0xF, 9), 4);
+
+	assert_eq!(chip8.index_register(), 0x120);
+	assert_registers(&chip8, &[(0xF, 9)]);
+}
+
+#[test]
+fn test_FX29() {
+	let chip8 = run(RomBuilder::new().ld(1, 0xB).ld_font(1), 2);
+
+	assert_eq!(chip8.index_register(), 0xB * 5);
+	assert_eq!(&chip8.memory()[55..60], &[0xE0, 0x90, 0xE0, 0x90, 0xE0]);
+}
+
+#[test]
+fn test_FX33() {
+	let bcd = |val: u8| {
+		let chip8 = run(RomBuilder::new().ld(1, val).ld_i(0x300).bcd(1), 3);
+		chip8.memory()[0x300..0x303].to_vec()
+	};
+
+	assert_eq!(bcd(254), vec![2, 5, 4]);
+	assert_eq!(bcd(90), vec![0, 9, 0]);
+	assert_eq!(bcd(7), vec![0, 0, 7]);
+	assert_eq!(bcd(0), vec![0, 0, 0]);
+}
+
+#[test]
+fn test_FX55_and_FX65() {
+	let rom = RomBuilder::new().ld(0, 1).ld(1, 2).ld(2, 3).ld_i(0x300).store(1).ld(0, 0).ld(1, 0).ld(2, 0).load(2);
+
+	let chip8 = run(rom.clone(), 9);
+	assert_eq!(&chip8.memory()[0x300..0x303], &[1, 2, 0]);
+	assert_registers(&chip8, &[(0, 1), (1, 2), (2, 0)]);
+	assert_eq!(chip8.index_register(), 0x300);
+
+	let quirks = Quirks {memory_increment: true, ..Quirks::default()};
+	let chip8 = run_with_quirks(rom, 5, quirks);
+	assert_eq!(chip8.index_register(), 0x302);
+}
+
+#[test]
+fn test_display_wait_quirk_draws_once_per_frame() {
+	let rom = RomBuilder::new().ld(0, 0).ld_font(0).drw(0, 0, 5).add(1, 1).jp(0x204);
+	let quirks = Quirks {display_wait: true, ..Quirks::default()};
+
+	let mut chip8 = Chip8::new();
+	chip8.quirks = quirks;
+	chip8.load_rom(rom.build()).unwrap();
+	chip8.run_frame(&create_key_state(), 20).unwrap();
+
+	// the frame ends right after the first draw
+	assert_eq!(chip8.pc(), 0x206);
+	assert_registers(&chip8, &[(1, 0)]);
+}
+
+#[test]
+fn test_rom_too_large() {
+	let mut chip8 = Chip8::new();
+
+	assert!(chip8.load_rom(vec![0; MAX_ROM_SIZE]).is_ok());
+	assert_eq!(chip8.load_rom(vec![0; MAX_ROM_SIZE + 1]), Err(Chip8Error::RomTooLarge {size: MAX_ROM_SIZE + 1}));
+}
+
+#[test]
+fn test_errors_leave_pc_at_faulting_instruction() {
+	let key_state = create_key_state();
+	let step_err = |rom: RomBuilder, steps: usize| {
+		let mut chip8 = run(rom, steps);
+		let err = chip8.step(&key_state).unwrap_err();
+		(err, chip8.pc())
+	};
+
+	assert_eq!(step_err(RomBuilder::new().op(0xFFFF), 0), (Chip8Error::InvalidOpcode {pc: 0x200, op: 0xFFFF}, 0x200));
+	assert_eq!(step_err(RomBuilder::new().ret(), 0), (Chip8Error::StackUnderflow {pc: 0x200}, 0x200));
+	assert_eq!(step_err(RomBuilder::new().call(0x200), 16), (Chip8Error::StackOverflow {pc: 0x200}, 0x200));
+	assert_eq!(step_err(RomBuilder::new().jp(0xFFF), 1), (Chip8Error::PcOutOfBounds {pc: 0xFFF}, 0xFFF));
+
+	let oob = |addr| Chip8Error::MemoryOutOfBounds {pc: 0x202, addr};
+	assert_eq!(step_err(RomBuilder::new().ld_i(0xFFE).bcd(0), 1), (oob(0x1000), 0x202));
+	assert_eq!(step_err(RomBuilder::new().ld_i(0xFF8).store(0xF), 1), (oob(0x1007), 0x202));
+	assert_eq!(step_err(RomBuilder::new().ld_i(0xFFF).load(1), 1), (oob(0x1000), 0x202));
+	assert_eq!(step_err(RomBuilder::new().ld_i(0xFFC).drw(0, 0, 5), 1), (oob(0x1000), 0x202));
+}
+
+#[test]
+fn test_failed_store_writes_nothing() {
+	let mut chip8 = run(RomBuilder::new().ld(0, 0xAA).ld_i(0xFFF).store(1), 2);
+
+	assert!(chip8.step(&create_key_state()).is_err());
+	assert_eq!(chip8.memory()[0xFFF], 0);
+}
+
+#[test]
+fn test_decode_cache_sees_self_modifying_code() {
+	// 0x206 runs once as "add v2, 1", then FX55 stores v0-v1 over it as "add v2, 5" and it runs again
+	let rom = RomBuilder::new()
+		.ld(0, 0x72).ld(1, 0x05).ld_i(0x206)
+		.add(2, 1)
+		.store(1)
+		.jp(0x206)
+		.build();
+
+	for cache in [true, false] {
+		let mut chip8 = Chip8::new();
+		chip8.decode_cache = cache;
+		chip8.load_rom(rom.clone()).unwrap();
+		let key_state = create_key_state();
+
+		for _ in 0..7 {
+			chip8.step(&key_state).unwrap();
+		}
+		assert_eq!(chip8.register(2), 6, "cache {}", cache);
+
+		chip8.write_memory(0x206, &[0x72, 0x10]).unwrap();
+		chip8.set_pc(0x206);
+		chip8.step(&key_state).unwrap();
+		assert_eq!(chip8.register(2), 0x16, "cache {}", cache);
+
+		// FX33 writing 0, 0, 1 from 0x205 leaves 0001 at 0x206
+		chip8.write_memory(0x300, &[0xF3, 0x33, 0x12, 0x06]).unwrap();
+		chip8.set_register(3, 1);
+		chip8.set_index_register(0x205);
+		chip8.set_pc(0x300);
+		chip8.step(&key_state).unwrap();
+		chip8.step(&key_state).unwrap();
+		assert_eq!(chip8.step(&key_state), Err(Chip8Error::InvalidOpcode {pc: 0x206, op: 0x0001}), "cache {}", cache);
+	}
+}
+
+#[test]
+fn test_restore_returns_to_snapshot() {
+	let rom = RomBuilder::new()
+		.rnd(0, 0xFF).ld_i(0x300).store(0)
+		.ld_font(0).drw(1, 1, 5)
+		.call(0x20E)
+		.jp(0x200)
+		.add(2, 1).ret()
+		.build();
+
+	let mut chip8 = Chip8::new();
+	chip8.seed_rng(3);
+	chip8.load_rom(rom).unwrap();
+	let key_state = create_key_state();
+
+	let snapshot = chip8.snapshot();
+	chip8.run_frame(&key_state, 8).unwrap();
+	let first = (chip8.registers(), chip8.memory().to_vec(), chip8.framebuffer, chip8.pc());
+
+	// overwrite code the cache has decoded, restoring must put it back
+	chip8.write_memory(0x200, &[0x00, 0xE0]).unwrap();
+	chip8.run_frame(&key_state, 8).unwrap();
+
+	chip8.restore(&snapshot);
+	assert_eq!(chip8.pc(), 0x200);
+	assert!(chip8.stack().is_empty());
+	chip8.run_frame(&key_state, 8).unwrap();
+	// CXNN draws the same number again
+	assert_eq!((chip8.registers(), chip8.memory().to_vec(), chip8.framebuffer, chip8.pc()), first);
+}
+
+#[test]
+fn test_to_image_scales_pixels_to_blocks() {
+	let mut chip8 = Chip8::new();
+	chip8.framebuffer.set_pixel(2, 1, 1);
+	let palette = Palette::from_hex_list("#000011,#EEFF00").unwrap();
+
+	let image = chip8.to_image(3, &palette);
+
+	assert_eq!((image.width, image.height), (64 * 3, 32 * 3));
+	for y in 0..image.height {
+		for x in 0..image.width {
+			let lit = (6..9).contains(&x) && (3..6).contains(&y);
+			assert_eq!(image.get(x, y), if lit {Rgb(0xEE, 0xFF, 0)} else {Rgb(0, 0, 0x11)}, "({}, {})", x, y);
+		}
+	}
+
+	// 0 is treated as 1, and huge scales are capped instead of overflowing the size
+	assert_eq!(chip8.to_image(0, &palette).width, 64);
+	assert_eq!(chip8.to_image(u32::MAX, &palette).width, 64 * MAX_IMAGE_SCALE);
+}
+
+// stops at an address, counting the instructions the interpreter ran
+struct StopAt {
+	addr: u16,
+	steps: u32,
+}
+
+impl FrameHook for StopAt {
+	type Stop = u16;
+
+	fn before_step(&mut self, chip8: &mut Chip8, _left: u32) -> Cycle<u16> {
+		if chip8.pc() == self.addr {Cycle::Stop(chip8.pc())} else {Cycle::Step}
+	}
+
+	fn after_step(&mut self, _chip8: &mut Chip8) {
+		self.steps += 1;
+	}
+}
+
+#[test]
+fn test_run_frame_with_hook() {
+	let mut chip8 = Chip8::new();
+	chip8.load_rom(RomBuilder::new().ld(0, 5).ld_dt(0).add(1, 1).jp(0x204).build()).unwrap();
+	let key_state = create_key_state();
+
+	let mut hook = StopAt {addr: 0x206, steps: 0};
+	assert_eq!(chip8.run_frame_with(&key_state, 10, &mut hook), Ok(Some(0x206)));
+	assert_eq!(hook.steps, 3);
+	// stopped frames don't tick the timers
+	assert_eq!(chip8.delay_timer(), 5);
+
+	let mut hook = StopAt {addr: 0x300, steps: 0};
+	assert_eq!(chip8.run_frame_with(&key_state, 10, &mut hook), Ok(None));
+	assert_eq!(hook.steps, 10);
+	assert_eq!(chip8.delay_timer(), 4);
 }
@@ -0,0 +1,341 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::chip8::MAX_IMAGE_SCALE;
+use crate::palette::Palette;
+use crate::drivers::display::{PixelStyle, ScaleMode};
+use crate::screen_render::RenderMode;
+use crate::recorder::RecordFormat;
+use crate::quirks::Quirks;
+use crate::romdb::RomEntry;
+use crate::drivers::keyboard::button_keycode;
+
+#[cfg(test)]
+#[path = "./config_tests.rs"]
+mod config_tests;
+
+const DEFAULT_CONFIG_FILE: &str = "chip8.cfg";
+
+pub struct Config {
+    pub rom_path: Option<PathBuf>,
+    pub palette: Palette,
+    pub pixel_style: PixelStyle,
+    pub scale_mode: ScaleMode,
+    pub cycles_per_frame: u32,
+    pub screenshot_dir: PathBuf,
+    pub screenshot_scale: u32,
+    pub record_format: RecordFormat,
+    pub record_on_start: bool,
+    pub recording_dir: PathBuf,
+    pub record_scale: u32,
+    pub quirks: Quirks,
+    // extra key bindings, button name from the ROM database to chip8 key
+    pub keys: Vec<(String, u8)>,
+    pub rom_database: Option<PathBuf>,
+    // port for a gdb remote serial protocol server on localhost
+    pub gdb_port: Option<u16>,
+    // Rhai script run alongside the ROM, see src/script.rs
+    pub script: Option<PathBuf>,
+    // cheats are kept in <cheat_dir>/<sha1 of the ROM>.txt, cheat_console reads commands from stdin
+    pub cheat_dir: PathBuf,
+    pub cheat_console: bool,
+    // frames shown ahead of the machine to hide input latency, see src/run_ahead.rs
+    pub run_ahead: u32,
+    // speed multipliers for the fast forward and slow motion hotkeys, see src/scheduler.rs
+    pub fast_forward: f32,
+    pub slow_motion: f32,
+    pub mute_fast_forward: bool,
+    pub start_paused: bool,
+    render: String,
+    phosphor_frames: usize,
+    phosphor_decay: f32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            rom_path: None,
+            palette: Palette::default(),
+            pixel_style: PixelStyle::default(),
+            scale_mode: ScaleMode::default(),
+            cycles_per_frame: 10,
+            screenshot_dir: PathBuf::from("screenshots"),
+            screenshot_scale: 10,
+            record_format: RecordFormat::Gif,
+            record_on_start: false,
+            recording_dir: PathBuf::from("recordings"),
+            record_scale: 4,
+            quirks: Quirks::default(),
+            keys: Vec::new(),
+            rom_database: None,
+            gdb_port: None,
+            script: None,
+            cheat_dir: PathBuf::from("cheats"),
+            cheat_console: false,
+            run_ahead: 0,
+            fast_forward: 4.0,
+            slow_motion: 0.25,
+            mute_fast_forward: true,
+            start_paused: false,
+            render: String::from("direct"),
+            phosphor_frames: 4,
+            phosphor_decay: 0.5,
+        }
+    }
+}
+
+impl Config {
+    // settings are applied in order: defaults, config file, command line
+    pub fn from_args(args: &[String]) -> Result<Config, String> {
+        Config::build(args, Config::default(), &[])
+    }
+
+    // settings for a particular ROM: defaults, its ROM database entry, the config file,
+    // the config file's [<sha1>] or [<file name>] section for the ROM, command line.
+    // also returns warnings for database settings that were ignored
+    pub fn for_rom(args: &[String], sha1: &str, entry: Option<&RomEntry>) -> Result<(Config, Vec<String>), String> {
+        let mut config = Config::default();
+        let mut warnings = Vec::new();
+
+        for (key, val) in entry.map(|e| e.settings()).unwrap_or_default() {
+            // a bad database entry shouldn't stop the ROM from running
+            if let Err(e) = config.set(&key, &val) {
+                warnings.push(format!("ignoring ROM database setting: {}", e));
+            }
+        }
+
+        let cli = parse_args(args)?;
+        let file_name = cli.iter().rev().find(|(k, _)| k == "rom")
+            .and_then(|(_, v)| Path::new(v).file_name().map(|n| n.to_string_lossy().to_string()));
+
+        let mut sections = vec![sha1.to_string()];
+        sections.extend(file_name);
+        Ok((Config::build(args, config, &sections)?, warnings))
+    }
+
+    fn build(args: &[String], mut config: Config, sections: &[String]) -> Result<Config, String> {
+        let cli = parse_args(args)?;
+
+        let explicit_file = cli.iter().find(|(k, _)| k == "config").map(|(_, v)| PathBuf::from(v));
+        let config_file = explicit_file.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE));
+
+        if config_file.exists() || explicit_file.is_some() {
+            config.load_file(&config_file, sections)?;
+        }
+
+        for (key, val) in cli.iter() {
+            if key != "config" {
+                config.set(key, val)?;
+            }
+        }
+
+        Ok(config)
+    }
+
+    // settings outside any section, then those in the listed sections
+    pub fn load_file(&mut self, path: &Path, sections: &[String]) -> Result<(), String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read config file '{}': {}", path.display(), e))?;
+        let settings = parse_sections(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        for (_, key, val) in settings.iter().filter(|(section, _, _)| section.is_none()) {
+            self.set(key, val)?;
+        }
+        for (_, key, val) in settings.iter().filter(|(section, _, _)| section.as_ref().is_some_and(|s| sections.contains(s))) {
+            self.set(key, val)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set(&mut self, key: &str, val: &str) -> Result<(), String> {
+        match key {
+            "rom" => self.rom_path = Some(PathBuf::from(val)),
+            "palette" => {
+                self.palette = Palette::by_name(val).ok_or_else(|| format!(
+                    "unknown palette '{}', expected one of: {}", val, Palette::preset_names().join(", ")
+                ))?;
+            },
+            "colors" => {
+                self.palette = Palette::from_hex_list(val)
+                    .ok_or_else(|| format!("invalid colour list '{}', expected e.g. #000000,#FFFFFF", val))?;
+            },
+            "grid" => self.pixel_style.grid = parse_bool(key, val)?,
+            "rounded" => self.pixel_style.rounded = parse_bool(key, val)?,
+            "scale" => {
+                self.scale_mode = match val {
+                    "integer" => ScaleMode::Integer,
+                    "aspect" => ScaleMode::Aspect,
+                    _ => return Err(format!("unknown scale mode '{}', expected integer or aspect", val)),
+                };
+            },
+            "screenshot-dir" => self.screenshot_dir = PathBuf::from(val),
+            "screenshot-scale" => self.screenshot_scale = parse_scale(key, val)?,
+            "record-format" => {
+                self.record_format = match val {
+                    "gif" => RecordFormat::Gif,
+                    "raw" => RecordFormat::Raw,
+                    _ => return Err(format!("unknown record format '{}', expected gif or raw", val)),
+                };
+            },
+            "record-gif" => {
+                self.record_on_start = parse_bool(key, val)?;
+                self.record_format = RecordFormat::Gif;
+            },
+            "recording-dir" => self.recording_dir = PathBuf::from(val),
+            "record-scale" => self.record_scale = parse_scale(key, val)?,
+            "cycles-per-frame" => self.cycles_per_frame = parse_num(key, val)?,
+            "quirks" => {
+                self.quirks = Quirks::by_name(val).ok_or_else(|| format!(
+                    "unknown quirk profile '{}', expected one of: {}", val, Quirks::profile_names().join(", ")
+                ))?;
+            },
+            "vf-reset" => self.quirks.vf_reset = parse_bool(key, val)?,
+            "memory-increment" => self.quirks.memory_increment = parse_bool(key, val)?,
+            "shift-uses-vy" => self.quirks.shift_uses_vy = parse_bool(key, val)?,
+            "jump-uses-vx" => self.quirks.jump_uses_vx = parse_bool(key, val)?,
+            "wrap-sprites" => self.quirks.wrap_sprites = parse_bool(key, val)?,
+            "display-wait" => self.quirks.display_wait = parse_bool(key, val)?,
+            "keys" => self.keys = parse_keys(val)?,
+            "rom-database" => self.rom_database = Some(PathBuf::from(val)),
+            "gdb" => self.gdb_port = Some(parse_num(key, val)?),
+            "script" => self.script = Some(PathBuf::from(val)),
+            "cheat-dir" => self.cheat_dir = PathBuf::from(val),
+            "cheats" => self.cheat_console = parse_bool(key, val)?,
+            "run-ahead" => self.run_ahead = parse_num(key, val)?,
+            "fast-forward" => self.fast_forward = parse_speed(key, val)?,
+            "slow-motion" => self.slow_motion = parse_speed(key, val)?,
+            "fast-forward-audio" => {
+                self.mute_fast_forward = match val {
+                    "mute" => true,
+                    "keep" => false,
+                    _ => return Err(format!("unknown fast forward audio '{}', expected mute or keep", val)),
+                };
+            },
+            "paused" => self.start_paused = parse_bool(key, val)?,
+            "render" => {
+                RenderMode::from_name(val, 0, 0.0)
+                    .ok_or_else(|| format!("unknown render mode '{}', expected direct, phosphor or frame-or", val))?;
+                self.render = val.to_string();
+            },
+            "phosphor-frames" => self.phosphor_frames = parse_num(key, val)?,
+            "phosphor-decay" => {
+                self.phosphor_decay = parse_num(key, val)?;
+                if !(0.0..=1.0).contains(&self.phosphor_decay) {
+                    return Err(format!("'{}' must be between 0 and 1", key));
+                }
+            },
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+
+        Ok(())
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        RenderMode::from_name(&self.render, self.phosphor_frames, self.phosphor_decay).unwrap()
+    }
+}
+
+// turn "--key value" / "--flag" / positional rom path into key value pairs
+fn parse_args(args: &[String]) -> Result<Vec<(String, String)>, String> {
+    let mut pairs = Vec::new();
+    let mut iter = args.iter().skip(1).peekable();
+
+    while let Some(arg) = iter.next() {
+        match arg.strip_prefix("--") {
+            Some(key) => {
+                let val = match iter.peek() {
+                    Some(next) if !next.starts_with("--") && !is_flag(key) => iter.next().unwrap().clone(),
+                    _ if is_flag(key) => String::from("true"),
+                    _ => return Err(format!("missing value for '--{}'", key)),
+                };
+                pairs.push((key.to_string(), val));
+            },
+            None => pairs.push((String::from("rom"), arg.clone())),
+        }
+    }
+
+    Ok(pairs)
+}
+
+fn is_flag(key: &str) -> bool {
+    matches!(key, "grid" | "rounded" | "record-gif" | "cheats" | "paused")
+}
+
+fn parse_bool(key: &str, val: &str) -> Result<bool, String> {
+    match val {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(format!("invalid value '{}' for '{}', expected true or false", val, key)),
+    }
+}
+
+fn parse_num<T: std::str::FromStr>(key: &str, val: &str) -> Result<T, String> {
+    val.parse().map_err(|_| format!("invalid number '{}' for '{}'", val, key))
+}
+
+fn parse_scale(key: &str, val: &str) -> Result<u32, String> {
+    let scale: u32 = parse_num(key, val)?;
+    if !(1..=MAX_IMAGE_SCALE).contains(&scale) {
+        return Err(format!("'{}' must be between 1 and {}", key, MAX_IMAGE_SCALE));
+    }
+    Ok(scale)
+}
+
+fn parse_speed(key: &str, val: &str) -> Result<f32, String> {
+    let speed: f32 = parse_num(key, val)?;
+    if !(speed.is_finite() && speed > 0.0) {
+        return Err(format!("'{}' must be a multiple of normal speed above 0", key));
+    }
+    Ok(speed)
+}
+
+// "up:5, down:8", button names as in the ROM database and hex chip8 keys
+fn parse_keys(val: &str) -> Result<Vec<(String, u8)>, String> {
+    val.split(',')
+        .map(|binding| {
+            let (button, key) = binding.split_once(':').unwrap_or((binding, ""));
+            let button = button.trim();
+            let key = u8::from_str_radix(key.trim(), 16).ok().filter(|k| *k < 16);
+
+            match key {
+                Some(key) if button_keycode(button).is_some() => Ok((button.to_string(), key)),
+                _ => Err(format!("invalid key binding '{}', expected e.g. up:5", binding.trim())),
+            }
+        })
+        .collect()
+}
+
+// "key = value" lines, '#' starts a comment
+pub fn parse_key_values(text: &str) -> Result<Vec<(String, String)>, String> {
+    parse_sections(text)?.into_iter()
+        .map(|(section, key, val)| match section {
+            None => Ok((key, val)),
+            Some(s) => Err(format!("unexpected section '[{}]'", s)),
+        })
+        .collect()
+}
+
+// like parse_key_values, but a "[name]" line puts the settings after it in section `name`
+pub fn parse_sections(text: &str) -> Result<Vec<(Option<String>, String, String)>, String> {
+    let mut settings = Vec::new();
+    let mut section = None;
+
+    for (num, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = Some(name.trim().to_string());
+            continue;
+        }
+
+        match line.split_once('=') {
+            Some((key, val)) => settings.push((section.clone(), key.trim().to_string(), val.trim().to_string())),
+            None => return Err(format!("line {}: expected 'key = value', got '{}'", num + 1, line)),
+        }
+    }
+
+    Ok(settings)
+}
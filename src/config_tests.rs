@@ -0,0 +1,77 @@
+use super::*;
+use std::env;
+use crate::romdb::RomDb;
+
+fn args(list: &[&str]) -> Vec<String> {
+	std::iter::once("chip8").chain(list.iter().copied()).map(String::from).collect()
+}
+
+#[test]
+fn test_parse_sections() {
+	let text = "palette = amber\n# comment\n[pong.ch8]\ngrid = true\n[ abc ]\nrounded = true\n";
+	let settings = parse_sections(text).unwrap();
+
+	assert_eq!(settings, [
+		(None, String::from("palette"), String::from("amber")),
+		(Some(String::from("pong.ch8")), String::from("grid"), String::from("true")),
+		(Some(String::from("abc")), String::from("rounded"), String::from("true")),
+	]);
+	assert!(parse_key_values(text).is_err());
+}
+
+#[test]
+fn test_parse_keys() {
+	assert_eq!(parse_keys("up:5, player2A:f").unwrap(), [(String::from("up"), 5), (String::from("player2A"), 0xF)]);
+	assert!(parse_keys("up:10").is_err());
+	assert!(parse_keys("jump:1").is_err());
+}
+
+#[test]
+fn test_image_scales_are_bounded() {
+	let mut config = Config::default();
+	config.set("screenshot-scale", "64").unwrap();
+	assert_eq!(config.screenshot_scale, 64);
+
+	// 1000 would overflow the image size
+	assert!(config.set("screenshot-scale", "1000").is_err());
+	assert!(config.set("screenshot-scale", "0").is_err());
+	assert_eq!(config.screenshot_scale, 64);
+	assert!(config.set("record-scale", "65").is_err());
+	assert_eq!(config.record_scale, 4);
+}
+
+// database entry < config file < the ROM's section < command line
+#[test]
+fn test_rom_settings_precedence() {
+	let dir = env::temp_dir().join("chip8_config_test");
+	fs::create_dir_all(&dir).unwrap();
+	let file = dir.join("chip8.cfg");
+	fs::write(&file, "cycles-per-frame = 12\n[pong.chp8]\ndisplay-wait = false\n[0123]\npalette = amber\n").unwrap();
+
+	let pong = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("pong.chp8")).unwrap();
+	let entry = RomDb::embedded().lookup(&pong).unwrap();
+	let config_file = file.to_str().unwrap();
+
+	let (config, warnings) = Config::for_rom(&args(&["roms/pong.chp8", "--config", config_file, "--grid"]), &entry.sha1, Some(&entry)).unwrap();
+	assert!(warnings.is_empty());
+	assert_eq!(config.quirks, Quirks {display_wait: false, ..Quirks::by_name("chip8").unwrap()});
+	assert_eq!(config.cycles_per_frame, 12);
+	assert_eq!(config.keys.len(), 4);
+	assert_eq!(config.palette, Palette::default());
+	assert!(config.pixel_style.grid);
+
+	// sections also match by hash, and only apply to that ROM
+	let (config, _) = Config::for_rom(&args(&["other.ch8", "--config", config_file, "--cycles-per-frame", "7"]), "0123", None).unwrap();
+	assert_eq!(config.quirks, Quirks::default());
+	assert_eq!(config.cycles_per_frame, 7);
+	assert_eq!(config.palette.name, "amber");
+
+	// a bad database setting is skipped with a warning, the rest still apply
+	let bad = RomEntry {colors: vec![String::from("not a colour")], ..entry};
+	let (config, warnings) = Config::for_rom(&args(&["roms/pong.chp8", "--config", config_file]), &bad.sha1, Some(&bad)).unwrap();
+	assert!(!config.quirks.display_wait);
+	assert_eq!(warnings.len(), 1);
+	assert!(warnings[0].starts_with("ignoring ROM database setting: "), "{}", warnings[0]);
+
+	fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use crate::headless::{Manifest, framebuffer_to_text, text_diff};
+use crate::quirks::Quirks;
+
+#[cfg(test)]
+#[path = "./conformance_tests.rs"]
+mod conformance_tests;
+
+// result of running one test ROM under one quirk profile
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    // the final screen differs from the stored one, holds the diff
+    Fail(String),
+    // the interpreter returned an error, e.g. an opcode we don't implement
+    Error(String),
+    // the ROM file isn't in the suite directory
+    MissingRom,
+    // nothing stored to compare against yet
+    Unblessed,
+    // the manifest doesn't list this profile
+    Skipped,
+}
+
+impl Outcome {
+    fn label(&self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Fail(_) => "FAIL",
+            Outcome::Error(_) => "ERROR",
+            Outcome::MissingRom => "no rom",
+            Outcome::Unblessed => "unblessed",
+            Outcome::Skipped => "-",
+        }
+    }
+}
+
+// outcomes for every ROM in the suite, one column per quirk profile
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub profiles: Vec<String>,
+    pub rows: Vec<(String, Vec<Outcome>)>,
+}
+
+impl Report {
+    pub fn outcomes(&self) -> impl Iterator<Item = (&str, &str, &Outcome)> {
+        self.rows.iter().flat_map(move |(rom, outcomes)| {
+            self.profiles.iter().zip(outcomes.iter()).map(move |(profile, o)| (rom.as_str(), profile.as_str(), o))
+        })
+    }
+
+    // everything that didn't pass, with diffs and error messages. a missing ROM or expected
+    // screen is a failure too, otherwise an empty checkout would pass
+    pub fn failures(&self) -> Vec<String> {
+        self.outcomes()
+            .filter_map(|(rom, profile, o)| match o {
+                Outcome::Fail(msg) | Outcome::Error(msg) => Some(format!("{} ({}): {}", rom, profile, msg)),
+                Outcome::MissingRom => Some(format!("{} ({}): rom not found, see tests/roms/README.md", rom, profile)),
+                Outcome::Unblessed => Some(format!("{} ({}): no expected screen stored", rom, profile)),
+                Outcome::Pass | Outcome::Skipped => None,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name_width = self.rows.iter().map(|(rom, _)| rom.len()).max().unwrap_or(0).max(8);
+        let col_width = self.profiles.iter().map(|p| p.len()).max().unwrap_or(0).max(9);
+        let line = |first: &str, cells: Vec<String>| {
+            let cells: String = cells.iter().map(|c| format!("  {:<w$}", c, w = col_width)).collect();
+            format!("{:<w$}{}", first, cells, w = name_width).trim_end().to_string()
+        };
+
+        writeln!(f, "{}", line("rom", self.profiles.clone()))?;
+        for (rom, outcomes) in self.rows.iter() {
+            writeln!(f, "{}", line(rom, outcomes.iter().map(|o| o.label().to_string()).collect()))?;
+        }
+
+        // passes out of the ROMs that could actually be checked
+        let totals = (0..self.profiles.len()).map(|i| {
+            let column = self.rows.iter().map(|(_, outcomes)| &outcomes[i]);
+            let passed = column.clone().filter(|o| **o == Outcome::Pass).count();
+            let checked = column.filter(|o| matches!(o, Outcome::Pass | Outcome::Fail(_) | Outcome::Error(_))).count();
+            format!("{}/{}", passed, checked)
+        });
+        writeln!(f, "{}", line("passed", totals.collect()))
+    }
+}
+
+// runs every *.manifest in dir under each quirk profile and compares the final screen
+// against <name>.<profile>.txt. with bless set the current screens are stored instead
+pub fn run_suite(dir: &Path, bless: bool) -> Result<Report, String> {
+    let mut manifests: Vec<_> = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "manifest"))
+        .collect();
+    manifests.sort();
+
+    let mut report = Report {
+        profiles: Quirks::profile_names().iter().map(|p| p.to_string()).collect(),
+        rows: Vec::new(),
+    };
+
+    for path in manifests.iter() {
+        let manifest = Manifest::load(path)?;
+        let mut outcomes = Vec::new();
+
+        for profile in report.profiles.iter() {
+            let outcome = if !manifest.profiles.is_empty() && !manifest.profiles.contains(profile) {
+                Outcome::Skipped
+            } else if !manifest.rom.exists() {
+                Outcome::MissingRom
+            } else {
+                run_profile(&manifest.with_profile(profile)?, bless)?
+            };
+            outcomes.push(outcome);
+        }
+
+        report.rows.push((manifest.name.clone(), outcomes));
+    }
+
+    Ok(report)
+}
+
+fn run_profile(manifest: &Manifest, bless: bool) -> Result<Outcome, String> {
+    let chip8 = match manifest.run() {
+        Ok(chip8) => chip8,
+        Err(e) => return Ok(Outcome::Error(e)),
+    };
+
+    if bless {
+        manifest.bless(&chip8)?;
+        return Ok(Outcome::Pass);
+    }
+
+    if !manifest.expected.exists() {
+        return Ok(Outcome::Unblessed);
+    }
+
+    let expected = manifest.expected_text()?;
+    match text_diff(&expected, &framebuffer_to_text(&chip8.framebuffer)) {
+        Some(diff) => Ok(Outcome::Fail(diff)),
+        None => Ok(Outcome::Pass),
+    }
+}
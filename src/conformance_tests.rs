@@ -0,0 +1,85 @@
+use super::*;
+use std::env;
+use crate::rom_builder::RomBuilder;
+use crate::romdb::sha1_hex;
+
+// runs the community test ROMs fetched into tests/roms, see tests/roms/README.md.
+// `cargo test conformance -- --ignored --nocapture` prints the table
+#[test]
+#[ignore = "needs the ROMs downloaded by tests/roms/fetch.sh"]
+fn test_conformance_suite() {
+	let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms");
+	let report = run_suite(&dir, env::var("CHIP8_BLESS").is_ok()).unwrap();
+	println!("{}", report);
+
+	let failures = report.failures();
+	assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_suite_report() {
+	let dir = env::temp_dir().join("chip8_conformance_test");
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+
+	// draws the font digit stored at 0x1FF, so pokes show up on screen
+	let rom = RomBuilder::new().ld_i(0x1FF).load(0).ld_font(0).ld(1, 0).drw(1, 1, 5).jp(0x20A).build();
+	fs::write(dir.join("digit.ch8"), rom).unwrap();
+	fs::write(dir.join("a-digit.manifest"), "rom = digit.ch8\nframes = 2\npoke = 0x1FF:1\npoke.schip = 1FF:2\nprofiles = default, schip\n").unwrap();
+	fs::write(dir.join("b-missing.manifest"), "rom = missing.ch8\n").unwrap();
+	fs::write(dir.join("c-new.manifest"), "rom = digit.ch8\nprofiles = default\n").unwrap();
+
+	run_suite(&dir, true).unwrap();
+	fs::remove_file(dir.join("c-new.default.txt")).unwrap();
+	assert!(dir.join("a-digit.default.txt").exists());
+	assert!(!dir.join("a-digit.chip8.txt").exists());
+
+	// the schip screen shows a 2, swap it for the 1 the other profile drew
+	fs::copy(dir.join("a-digit.default.txt"), dir.join("a-digit.schip.txt")).unwrap();
+
+	let report = run_suite(&dir, false).unwrap();
+	let outcome = |rom: &str, profile: &str| report.outcomes().find(|(r, p, _)| *r == rom && *p == profile).unwrap().2.clone();
+
+	assert_eq!(outcome("a-digit", "default"), Outcome::Pass);
+	assert_eq!(outcome("a-digit", "chip8"), Outcome::Skipped);
+	assert!(matches!(outcome("a-digit", "schip"), Outcome::Fail(_)));
+	assert_eq!(outcome("b-missing", "default"), Outcome::MissingRom);
+	assert_eq!(outcome("c-new", "default"), Outcome::Unblessed);
+	// the schip diff, b-missing under every profile and c-new
+	assert_eq!(report.failures().len(), 7);
+	assert!(report.failures().contains(&"c-new (default): no expected screen stored".to_string()));
+
+	let table = report.to_string();
+	let cells = |row: usize| table.lines().nth(row).unwrap().split_whitespace().map(String::from).collect::<Vec<_>>();
+	assert_eq!(cells(0), ["rom", "default", "chip8", "modern", "schip", "xochip"]);
+	assert_eq!(cells(1), ["a-digit", "pass", "-", "-", "FAIL", "-"]);
+	assert_eq!(cells(2), ["b-missing", "no", "rom", "no", "rom", "no", "rom", "no", "rom", "no", "rom"]);
+	assert_eq!(cells(3), ["c-new", "unblessed", "-", "-", "-", "-"]);
+	assert_eq!(cells(4), ["passed", "1/1", "0/0", "0/0", "0/1", "0/0"]);
+
+	fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_pinned_rom_must_match() {
+	let dir = env::temp_dir().join("chip8_conformance_sha1_test");
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+
+	let rom = RomBuilder::new().jp(0x200).build();
+	fs::write(dir.join("loop.ch8"), &rom).unwrap();
+	fs::write(dir.join("loop.manifest"), format!("rom = loop.ch8\nsha1 = {}\nprofiles = default\n", sha1_hex(&rom).to_uppercase())).unwrap();
+	fs::write(dir.join("other.manifest"), format!("rom = loop.ch8\nsha1 = {}\nprofiles = default\n", "0".repeat(40))).unwrap();
+	fs::write(dir.join("short.manifest"), "rom = loop.ch8\nsha1 = 1234\n").unwrap();
+	assert!(run_suite(&dir, true).is_err());
+	fs::remove_file(dir.join("short.manifest")).unwrap();
+
+	// blessing doesn't store a screen for the wrong ROM either
+	let report = run_suite(&dir, true).unwrap();
+	let outcome = |rom: &str| report.outcomes().find(|(r, p, _)| *r == rom && *p == "default").unwrap().2.clone();
+	assert_eq!(outcome("loop"), Outcome::Pass);
+	assert!(matches!(outcome("other"), Outcome::Error(e) if e.contains("the manifest pins 0000")));
+	assert!(!dir.join("other.default.txt").exists());
+
+	fs::remove_dir_all(&dir).unwrap();
+}
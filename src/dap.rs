@@ -0,0 +1,517 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use serde_json::{json, Value};
+use crate::chip8::{Chip8, MEMORY_SIZE};
+use crate::debug::{Debugger, StopReason};
+use crate::instruction::decode;
+use crate::drivers::keyboard::KeyState;
+use crate::source_map::SourceMap;
+
+#[cfg(test)]
+#[path = "./dap_tests.rs"]
+mod dap_tests;
+
+// there's only the one thread of execution
+const THREAD_ID: u64 = 1;
+
+// variablesReference of each scope
+const REGISTERS: u64 = 1;
+const TIMERS: u64 = 2;
+const STACK: u64 = 3;
+
+// longest message accepted, far more than any request needs with 4 KiB of memory
+const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+// what the editor's launch configuration asks for
+#[derive(Clone, Debug, PartialEq)]
+pub struct LaunchArgs {
+    pub program: PathBuf,
+    pub source_map: Option<PathBuf>,
+    pub stop_on_entry: bool,
+}
+
+// Debug Adapter Protocol session, e.g. with VS Code over stdin/stdout. messages are read on
+// a thread so the main loop can poll for them between frames
+pub struct DapSession<W: Write> {
+    messages: Receiver<Result<Value, String>>,
+    writer: W,
+    seq: u64,
+    source_map: SourceMap,
+    stop_on_entry: bool,
+    // breakpoints set by source line, per file, and by address
+    source_breakpoints: BTreeMap<PathBuf, Vec<u16>>,
+    address_breakpoints: Vec<u16>,
+    waiting_for_stop: bool,
+    disconnected: bool,
+}
+
+impl<W: Write> DapSession<W> {
+    pub fn new<R: Read + Send + 'static>(reader: R, writer: W) -> DapSession<W> {
+        let (tx, messages) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            loop {
+                let msg = read_message(&mut reader);
+                let done = !matches!(msg, Ok(Some(_)));
+                let sent = match msg {
+                    Ok(Some(msg)) => tx.send(Ok(msg)),
+                    Ok(None) => break,
+                    Err(e) => tx.send(Err(e)),
+                };
+                if done || sent.is_err() {
+                    break;
+                }
+            }
+        });
+
+        DapSession {
+            messages,
+            writer,
+            seq: 1,
+            source_map: SourceMap::default(),
+            stop_on_entry: false,
+            source_breakpoints: BTreeMap::new(),
+            address_breakpoints: Vec::new(),
+            waiting_for_stop: false,
+            disconnected: false,
+        }
+    }
+
+    // answer requests until the editor says which ROM to launch
+    pub fn wait_for_launch(&mut self) -> io::Result<LaunchArgs> {
+        loop {
+            let request = match self.messages.recv() {
+                Ok(Ok(request)) => request,
+                Ok(Err(e)) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                Err(_) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "client went away before launch")),
+            };
+
+            match request["command"].as_str().unwrap_or("") {
+                "initialize" => self.respond(&request, Ok(capabilities()))?,
+                "launch" => match self.launch(&request["arguments"]) {
+                    Ok(args) => {
+                        self.respond(&request, Ok(Value::Null))?;
+                        // the editor sends breakpoints after this, now source lines can be mapped
+                        self.event("initialized", Value::Null)?;
+                        self.writer.flush()?;
+                        return Ok(args);
+                    },
+                    Err(e) => self.respond(&request, Err(e))?,
+                },
+                "disconnect" | "terminate" => {
+                    self.respond(&request, Ok(Value::Null))?;
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "client disconnected before launch"));
+                },
+                _ => self.respond(&request, Err(String::from("no program has been launched")))?,
+            }
+            self.writer.flush()?;
+        }
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<LaunchArgs, String> {
+        let program = args["program"].as_str().ok_or("launch needs a 'program'")?;
+        if !Path::new(program).is_file() {
+            return Err(format!("{}: no such file", program));
+        }
+        let source_map = args["sourceMap"].as_str().map(PathBuf::from);
+        if let Some(path) = source_map.as_ref() {
+            self.source_map = SourceMap::load(path)?;
+        }
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+
+        Ok(LaunchArgs {program: PathBuf::from(program), source_map, stop_on_entry: self.stop_on_entry})
+    }
+
+    // handle requests received so far and report stops, false once the editor is done
+    pub fn poll(&mut self, chip8: &mut Chip8, dbg: &mut Debugger, key_state: &KeyState) -> io::Result<bool> {
+        while !self.disconnected {
+            let request = match self.messages.try_recv() {
+                Ok(Ok(request)) => request,
+                Ok(Err(e)) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(false),
+            };
+
+            self.disconnected = !self.handle_request(&request, chip8, dbg, key_state)?;
+        }
+        if self.disconnected {
+            self.writer.flush()?;
+            return Ok(false);
+        }
+
+        if self.waiting_for_stop && !dbg.is_running() {
+            self.waiting_for_stop = false;
+            if let Some(reason) = dbg.last_stop().cloned() {
+                self.stopped(&reason)?;
+            }
+        }
+
+        self.writer.flush()?;
+        Ok(true)
+    }
+
+    // tell the editor the program has ended, e.g. when the window is closed
+    pub fn terminated(&mut self) -> io::Result<()> {
+        self.event("terminated", Value::Null)?;
+        self.writer.flush()
+    }
+
+    fn handle_request(&mut self, request: &Value, chip8: &mut Chip8, dbg: &mut Debugger, key_state: &KeyState) -> io::Result<bool> {
+        let args = &request["arguments"];
+
+        let result = match request["command"].as_str().unwrap_or("") {
+            "configurationDone" => {
+                self.respond(request, Ok(Value::Null))?;
+                if self.stop_on_entry {
+                    self.event("stopped", json!({"reason": "entry", "threadId": THREAD_ID, "allThreadsStopped": true}))?;
+                } else {
+                    dbg.resume();
+                    self.waiting_for_stop = true;
+                }
+                return Ok(true);
+            },
+            "setBreakpoints" => Ok(self.set_source_breakpoints(args, dbg)),
+            "setInstructionBreakpoints" => {
+                let refs = args["breakpoints"].as_array().cloned().unwrap_or_default();
+                let addrs = refs.iter().map(|b| {
+                    parse_address(&b["instructionReference"]).map(|a| a.wrapping_add(b["offset"].as_i64().unwrap_or(0) as u16))
+                });
+                Ok(self.set_address_breakpoints(addrs.collect(), dbg))
+            },
+            // function names are addresses, there are no symbols
+            "setFunctionBreakpoints" => {
+                let refs = args["breakpoints"].as_array().cloned().unwrap_or_default();
+                Ok(self.set_address_breakpoints(refs.iter().map(|b| parse_address(&b["name"])).collect(), dbg))
+            },
+            "threads" => Ok(json!({"threads": [{"id": THREAD_ID, "name": "chip8"}]})),
+            "stackTrace" => Ok(self.stack_trace(chip8)),
+            "scopes" => Ok(json!({"scopes": [
+                {"name": "Registers", "variablesReference": REGISTERS, "expensive": false},
+                {"name": "Timers", "variablesReference": TIMERS, "expensive": false},
+                {"name": "Stack", "variablesReference": STACK, "expensive": false},
+            ]})),
+            "variables" => Ok(json!({"variables": variables(chip8, args["variablesReference"].as_u64().unwrap_or(0))})),
+            "setVariable" => set_variable(chip8, args["name"].as_str().unwrap_or(""), args["value"].as_str().unwrap_or(""))
+                .map(|value| json!({"value": value})),
+            "readMemory" => read_memory(chip8, args),
+            "writeMemory" => write_memory(chip8, args),
+            "continue" => {
+                dbg.resume();
+                self.waiting_for_stop = true;
+                Ok(json!({"allThreadsContinued": true}))
+            },
+            "next" | "stepIn" | "stepOut" => {
+                let stopped = match request["command"].as_str() {
+                    Some("next") => dbg.step_over(chip8, key_state),
+                    Some("stepOut") => dbg.step_out(chip8, key_state),
+                    _ => Some(dbg.step(chip8, key_state)),
+                };
+                self.respond(request, Ok(Value::Null))?;
+                match stopped {
+                    Some(reason) => self.stopped(&reason)?,
+                    None => self.waiting_for_stop = true,
+                }
+                return Ok(true);
+            },
+            "pause" => {
+                dbg.pause();
+                // report the pause rather than anything that stopped it since the last poll
+                self.waiting_for_stop = false;
+                self.respond(request, Ok(Value::Null))?;
+                self.stopped(&StopReason::Pause)?;
+                return Ok(true);
+            },
+            "disconnect" | "terminate" => {
+                self.respond(request, Ok(Value::Null))?;
+                return Ok(false);
+            },
+            command => Err(format!("'{}' isn't supported", command)),
+        };
+
+        self.respond(request, result)?;
+        Ok(true)
+    }
+
+    fn set_source_breakpoints(&mut self, args: &Value, dbg: &mut Debugger) -> Value {
+        let path = PathBuf::from(args["source"]["path"].as_str().unwrap_or(""));
+        let lines = args["breakpoints"].as_array().cloned().unwrap_or_default();
+
+        let mut addrs = Vec::new();
+        let breakpoints: Vec<Value> = lines.iter().map(|b| {
+            let line = b["line"].as_u64().unwrap_or(0) as u32;
+            match self.source_map.address_at(&path, line) {
+                Some(m) => {
+                    addrs.push(m.address);
+                    json!({"verified": true, "line": m.line, "instructionReference": format!("{:#05X}", m.address)})
+                },
+                None => json!({"verified": false, "line": line, "message": "no instructions on or after this line"}),
+            }
+        }).collect();
+
+        self.source_breakpoints.insert(path, addrs);
+        self.sync_breakpoints(dbg);
+        json!({"breakpoints": breakpoints})
+    }
+
+    fn set_address_breakpoints(&mut self, addrs: Vec<Option<u16>>, dbg: &mut Debugger) -> Value {
+        let breakpoints: Vec<Value> = addrs.iter().map(|addr| match addr {
+            Some(addr) if (*addr as usize) < MEMORY_SIZE => json!({"verified": true, "instructionReference": format!("{:#05X}", addr)}),
+            _ => json!({"verified": false, "message": "expected an address such as 0x200"}),
+        }).collect();
+
+        self.address_breakpoints = addrs.into_iter().flatten().collect();
+        self.sync_breakpoints(dbg);
+        json!({"breakpoints": breakpoints})
+    }
+
+    fn sync_breakpoints(&self, dbg: &mut Debugger) {
+        dbg.clear_breakpoints();
+        for addr in self.source_breakpoints.values().flatten().chain(self.address_breakpoints.iter()) {
+            dbg.set_breakpoint(*addr);
+        }
+    }
+
+    // the current instruction, then the calls that led to it
+    fn stack_trace(&self, chip8: &Chip8) -> Value {
+        let calls = chip8.stack().iter().rev().map(|ret| ret.wrapping_sub(2));
+        let frames: Vec<Value> = std::iter::once(chip8.pc()).chain(calls).enumerate().map(|(id, addr)| {
+            let mut frame = json!({
+                "id": id,
+                "name": frame_name(chip8, addr),
+                "line": 0,
+                "column": 0,
+                "instructionPointerReference": format!("{:#05X}", addr),
+            });
+            if let Some(m) = self.source_map.location(addr) {
+                let name = m.file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                frame["source"] = json!({"name": name, "path": m.file.to_string_lossy()});
+                frame["line"] = json!(m.line);
+                frame["column"] = json!(1);
+            }
+            frame
+        }).collect();
+
+        json!({"stackFrames": frames, "totalFrames": frames.len()})
+    }
+
+    fn stopped(&mut self, reason: &StopReason) -> io::Result<()> {
+        let mut body = json!({"threadId": THREAD_ID, "allThreadsStopped": true});
+        body["reason"] = json!(match reason {
+            StopReason::Step => "step",
+            StopReason::Breakpoint(_) => "breakpoint",
+            StopReason::Pause => "pause",
+            StopReason::Error(_) => "exception",
+        });
+        if let StopReason::Error(e) = reason {
+            body["description"] = json!("interpreter error");
+            body["text"] = json!(e.to_string());
+        }
+        self.event("stopped", body)
+    }
+
+    fn respond(&mut self, request: &Value, result: Result<Value, String>) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(Value::Null) => (),
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        self.send(response)
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        let mut msg = json!({"type": "event", "event": event});
+        if !body.is_null() {
+            msg["body"] = body;
+        }
+        self.send(msg)
+    }
+
+    fn send(&mut self, mut msg: Value) -> io::Result<()> {
+        msg["seq"] = json!(self.seq);
+        self.seq += 1;
+
+        let body = msg.to_string();
+        write!(self.writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsFunctionBreakpoints": true,
+        "supportsInstructionBreakpoints": true,
+        "supportsSetVariable": true,
+        "supportsReadMemoryRequest": true,
+        "supportsWriteMemoryRequest": true,
+        "supportsTerminateRequest": true,
+    })
+}
+
+// one Content-Length framed JSON message, None at the end of the stream
+fn read_message<R: BufRead>(reader: &mut R) -> Result<Option<Value>, String> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, val)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(val.trim().parse::<usize>().map_err(|_| format!("bad Content-Length '{}'", val.trim()))?);
+            }
+        }
+    }
+
+    let length = length.ok_or("message without a Content-Length")?;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(format!("Content-Length {} is over the {} byte limit", length, MAX_MESSAGE_SIZE));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| e.to_string())
+}
+
+// the address and the instruction there, e.g. "0x208  LD V1, 0x09"
+fn frame_name(chip8: &Chip8, addr: u16) -> String {
+    let memory = chip8.memory();
+    let op = memory.get(addr as usize..addr as usize + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+
+    match op.map(decode) {
+        Some(Ok(instr)) => format!("{:#05X}  {}", addr, instr),
+        _ => format!("{:#05X}", addr),
+    }
+}
+
+fn variables(chip8: &Chip8, reference: u64) -> Vec<Value> {
+    let var = |name: String, value: String| json!({"name": name, "value": value, "variablesReference": 0});
+
+    match reference {
+        REGISTERS => {
+            let mut vars: Vec<Value> = (0..16).map(|x| var(format!("V{:X}", x), format!("{:#04X}", chip8.register(x)))).collect();
+            let mut i = var(String::from("I"), format!("{:#05X}", chip8.index_register()));
+            // lets the editor open a memory view at I
+            i["memoryReference"] = json!(format!("{:#05X}", chip8.index_register()));
+            vars.push(i);
+            vars.push(var(String::from("PC"), format!("{:#05X}", chip8.pc())));
+            vars.push(var(String::from("SP"), chip8.stack().len().to_string()));
+            vars
+        },
+        TIMERS => vec![
+            var(String::from("DT"), chip8.delay_timer().to_string()),
+            var(String::from("ST"), chip8.sound_timer.to_string()),
+        ],
+        STACK => chip8.stack().iter().enumerate().map(|(i, ret)| var(i.to_string(), format!("{:#05X}", ret))).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn set_variable(chip8: &mut Chip8, name: &str, value: &str) -> Result<String, String> {
+    let num = parse_number(value).ok_or_else(|| format!("'{}' isn't a number", value))?;
+    let byte = || u8::try_from(num).map_err(|_| format!("{} doesn't fit in a byte", value));
+    let addr = || u16::try_from(num).ok().filter(|a| (*a as usize) < MEMORY_SIZE).ok_or_else(|| format!("{} isn't an address", value));
+
+    match name {
+        "I" => chip8.set_index_register(addr()?),
+        "PC" => chip8.set_pc(addr()?),
+        "SP" => chip8.set_stack_pointer(num as usize).map_err(|e| e.to_string())?,
+        "DT" => chip8.set_delay_timer(byte()?),
+        "ST" => chip8.sound_timer = byte()?,
+        _ => {
+            let x = name.strip_prefix('V').and_then(|x| usize::from_str_radix(x, 16).ok()).filter(|x| *x < 16)
+                .ok_or_else(|| format!("can't set '{}'", name))?;
+            chip8.set_register(x, byte()?);
+            return Ok(format!("{:#04X}", chip8.register(x)));
+        },
+    }
+
+    Ok(value.to_string())
+}
+
+fn read_memory(chip8: &Chip8, args: &Value) -> Result<Value, String> {
+    let start = memory_address(args)?;
+    let count = args["count"].as_u64().unwrap_or(0) as usize;
+    let end = start.saturating_add(count).min(MEMORY_SIZE);
+
+    Ok(json!({
+        "address": format!("{:#05X}", start),
+        "data": base64_encode(&chip8.memory()[start.min(end)..end]),
+        "unreadableBytes": count - (end - start.min(end)),
+    }))
+}
+
+fn write_memory(chip8: &mut Chip8, args: &Value) -> Result<Value, String> {
+    let start = memory_address(args)?;
+    let data = base64_decode(args["data"].as_str().unwrap_or("")).ok_or("data isn't base64")?;
+    let start = u16::try_from(start).map_err(|_| String::from("address out of range"))?;
+
+    chip8.write_memory(start, &data).map_err(|e| e.to_string())?;
+    Ok(json!({"bytesWritten": data.len()}))
+}
+
+fn memory_address(args: &Value) -> Result<usize, String> {
+    let base = parse_address(&args["memoryReference"]).ok_or("bad memoryReference")?;
+    let offset = args["offset"].as_i64().unwrap_or(0);
+    (base as i64).checked_add(offset)
+        .and_then(|addr| usize::try_from(addr).ok())
+        .ok_or_else(|| String::from("address out of range"))
+}
+
+fn parse_address(val: &Value) -> Option<u16> {
+    parse_number(val.as_str()?).and_then(|n| u16::try_from(n).ok())
+}
+
+// 0x prefixed hex or decimal
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[((n >> (18 - 6 * i)) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u32> = text.trim_end_matches('=').bytes()
+        .map(|c| BASE64.iter().position(|b| *b == c).map(|d| d as u32))
+        .collect::<Option<_>>()?;
+
+    let mut out = Vec::new();
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, d)| n | (d << (18 - 6 * i)));
+        out.extend(n.to_be_bytes()[1..chunk.len()].iter());
+    }
+    Some(out)
+}
@@ -0,0 +1,283 @@
+use super::*;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+use crate::drivers::keyboard::create_key_state;
+use crate::rom_builder::RomBuilder;
+
+// the editor's end of stdin, fed a message at a time
+struct ClientInput {
+	rx: Receiver<Vec<u8>>,
+	buf: Vec<u8>,
+}
+
+impl Read for ClientInput {
+	fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+		if self.buf.is_empty() {
+			match self.rx.recv() {
+				Ok(bytes) => self.buf = bytes,
+				Err(_) => return Ok(0),
+			}
+		}
+		let n = out.len().min(self.buf.len());
+		out[..n].copy_from_slice(&self.buf[..n]);
+		self.buf.drain(..n);
+		Ok(n)
+	}
+}
+
+// the editor's end of stdout
+#[derive(Clone, Default)]
+struct ClientOutput(Arc<Mutex<Vec<u8>>>);
+
+impl Write for ClientOutput {
+	fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+		self.0.lock().unwrap().extend_from_slice(bytes);
+		Ok(bytes.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+// a scripted editor driving a session over fake stdio
+struct Client {
+	tx: Sender<Vec<u8>>,
+	output: ClientOutput,
+	seq: u64,
+	session: DapSession<ClientOutput>,
+	chip8: Chip8,
+	dbg: Debugger,
+}
+
+impl Client {
+	fn new() -> Client {
+		let (tx, rx) = mpsc::channel();
+		let output = ClientOutput::default();
+		let session = DapSession::new(ClientInput {rx, buf: Vec::new()}, output.clone());
+		Client {tx, output, seq: 1, session, chip8: Chip8::new(), dbg: Debugger::new()}
+	}
+
+	fn send(&mut self, command: &str, arguments: Value) {
+		let body = json!({"seq": self.seq, "type": "request", "command": command, "arguments": arguments}).to_string();
+		self.seq += 1;
+		self.tx.send(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes()).unwrap();
+	}
+
+	// the next message from the adapter, polling the session until one arrives
+	fn receive(&mut self) -> Value {
+		let start = Instant::now();
+		loop {
+			{
+				let mut out = self.output.0.lock().unwrap();
+				let mut reader: &[u8] = &out;
+				if let Ok(Some(msg)) = read_message(&mut reader) {
+					let used = out.len() - reader.len();
+					out.drain(..used);
+					return msg;
+				}
+			}
+			assert!(start.elapsed() < Duration::from_secs(5), "no message from the adapter");
+			self.session.poll(&mut self.chip8, &mut self.dbg, &create_key_state()).unwrap();
+		}
+	}
+
+	// sends a request and returns its response, checking it succeeded
+	fn request(&mut self, command: &str, arguments: Value) -> Value {
+		self.send(command, arguments);
+		let response = self.receive();
+		assert_eq!(response["type"], "response");
+		assert_eq!(response["command"], command);
+		assert_eq!(response["success"], true, "{}", response);
+		response["body"].clone()
+	}
+
+	fn expect_event(&mut self, event: &str) -> Value {
+		let msg = self.receive();
+		assert_eq!(msg["event"], event, "{}", msg);
+		msg["body"].clone()
+	}
+
+	// initialize and launch the ROM, written to dir as the program
+	fn launch(mut self, dir: &Path, rom: Vec<u8>, mut arguments: Value) -> Client {
+		let program = dir.join("counter.ch8");
+		std::fs::write(&program, &rom).unwrap();
+		arguments["program"] = json!(program);
+
+		self.chip8.load_rom(rom).unwrap();
+		self.send("initialize", json!({"adapterID": "chip8"}));
+		self.send("launch", arguments);
+
+		self.session.wait_for_launch().unwrap();
+		assert_eq!(self.receive()["body"]["supportsReadMemoryRequest"], true);
+		assert_eq!(self.receive()["command"], "launch");
+		self.expect_event("initialized");
+		self
+	}
+
+	fn run_frame(&mut self) -> Option<StopReason> {
+		self.dbg.run_frame(&mut self.chip8, &create_key_state(), 10)
+	}
+}
+
+fn test_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("chip8_dap_{}_test", name));
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+fn counter_rom() -> Vec<u8> {
+	// 0x200: ld v0, 1 / 0x202: call 0x208 / 0x204: add v0, 1 / 0x206: jp 0x202 / 0x208: ld v1, 9 / 0x20A: ret
+	RomBuilder::new().ld(0, 1).call(0x208).add(0, 1).jp(0x202).ld(1, 9).ret().build()
+}
+
+#[test]
+fn test_wait_for_launch() {
+	let dir = test_dir("launch");
+	let program = dir.join("pong.ch8");
+	std::fs::write(&program, counter_rom()).unwrap();
+
+	let mut client = Client::new();
+	client.send("threads", json!({}));
+	client.send("launch", json!({"program": dir.join("missing.ch8")}));
+	client.send("launch", json!({"program": program, "stopOnEntry": true}));
+
+	let args = client.session.wait_for_launch().unwrap();
+	assert_eq!(args, LaunchArgs {program, source_map: None, stop_on_entry: true});
+	assert_eq!(client.receive()["success"], false);
+	assert!(client.receive()["message"].as_str().unwrap().contains("no such file"));
+	assert_eq!(client.receive()["success"], true);
+	client.expect_event("initialized");
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_breakpoint_by_address_and_variables() {
+	let dir = test_dir("breakpoint");
+	let mut client = Client::new().launch(&dir, counter_rom(), json!({}));
+
+	let bps = client.request("setFunctionBreakpoints", json!({"breakpoints": [{"name": "0x208"}, {"name": "nope"}]}));
+	assert_eq!(bps["breakpoints"][0]["verified"], true);
+	assert_eq!(bps["breakpoints"][1]["verified"], false);
+	client.request("configurationDone", json!({}));
+
+	assert_eq!(client.run_frame(), Some(StopReason::Breakpoint(0x208)));
+	assert_eq!(client.expect_event("stopped")["reason"], "breakpoint");
+
+	let frames = client.request("stackTrace", json!({"threadId": 1}))["stackFrames"].clone();
+	let names: Vec<&str> = frames.as_array().unwrap().iter().map(|f| f["name"].as_str().unwrap()).collect();
+	assert_eq!(names, ["0x208  LD V1, 0x09", "0x202  CALL 0x208"]);
+
+	let scopes = client.request("scopes", json!({"frameId": 0}));
+	assert_eq!(scopes["scopes"].as_array().unwrap().len(), 3);
+	let registers = client.request("variables", json!({"variablesReference": REGISTERS}))["variables"].clone();
+	assert_eq!(registers[0], json!({"name": "V0", "value": "0x01", "variablesReference": 0}));
+	assert_eq!(registers[17]["value"], "0x208");
+	let stack = client.request("variables", json!({"variablesReference": STACK}))["variables"].clone();
+	assert_eq!(stack[0]["value"], "0x204");
+
+	client.request("setVariable", json!({"variablesReference": REGISTERS, "name": "VA", "value": "0x2a"}));
+	assert_eq!(client.chip8.register(0xA), 42);
+	client.send("setVariable", json!({"variablesReference": REGISTERS, "name": "V0", "value": "300"}));
+	assert_eq!(client.receive()["success"], false);
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_stepping() {
+	let dir = test_dir("stepping");
+	let mut client = Client::new().launch(&dir, counter_rom(), json!({"stopOnEntry": true}));
+	client.request("configurationDone", json!({}));
+	assert_eq!(client.expect_event("stopped")["reason"], "entry");
+
+	client.request("stepIn", json!({"threadId": 1}));
+	assert_eq!(client.expect_event("stopped")["reason"], "step");
+	assert_eq!(client.chip8.pc(), 0x202);
+
+	// over the call, which finishes on the next frame
+	client.request("next", json!({"threadId": 1}));
+	assert_eq!(client.run_frame(), Some(StopReason::Step));
+	assert_eq!(client.expect_event("stopped")["reason"], "step");
+	assert_eq!(client.chip8.pc(), 0x204);
+	assert_eq!(client.chip8.register(1), 9);
+
+	client.request("continue", json!({"threadId": 1}));
+	assert_eq!(client.run_frame(), None);
+	client.request("pause", json!({"threadId": 1}));
+	assert_eq!(client.expect_event("stopped")["reason"], "pause");
+	assert!(!client.dbg.is_running());
+
+	client.send("disconnect", json!({}));
+	assert_eq!(client.receive()["command"], "disconnect");
+	assert!(!client.session.poll(&mut client.chip8, &mut client.dbg, &create_key_state()).unwrap());
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_source_breakpoints_and_memory() {
+	let dir = test_dir("source");
+	let map = dir.join("counter.map.json");
+	std::fs::write(&map, r#"{"mappings": [
+		{"file": "counter.8o", "line": 1, "address": 512},
+		{"file": "counter.8o", "line": 2, "address": 514},
+		{"file": "counter.8o", "line": 5, "address": 520}
+	]}"#).unwrap();
+
+	let mut client = Client::new().launch(&dir, counter_rom(), json!({"sourceMap": map}));
+
+	let source = dir.join("counter.8o");
+	let bps = client.request("setBreakpoints", json!({"source": {"path": source}, "breakpoints": [{"line": 4}, {"line": 9}]}));
+	assert_eq!(bps["breakpoints"][0]["line"], 5);
+	assert_eq!(bps["breakpoints"][1]["verified"], false);
+	client.request("configurationDone", json!({}));
+
+	assert_eq!(client.run_frame(), Some(StopReason::Breakpoint(0x208)));
+	client.expect_event("stopped");
+	let frames = client.request("stackTrace", json!({"threadId": 1}))["stackFrames"].clone();
+	assert_eq!(frames[0]["line"], 5);
+	assert_eq!(frames[0]["source"]["name"], "counter.8o");
+	assert_eq!(frames[1]["line"], 2);
+
+	let memory = client.request("readMemory", json!({"memoryReference": "0x200", "count": 4}));
+	assert_eq!(memory["data"], base64_encode(&[0x60, 0x01, 0x22, 0x08]));
+	let memory = client.request("readMemory", json!({"memoryReference": "0xFFE", "count": 4}));
+	assert_eq!(memory["unreadableBytes"], 2);
+	let memory = client.request("readMemory", json!({"memoryReference": "0xFFE", "count": u64::MAX}));
+	assert_eq!(memory["data"], base64_encode(&[0, 0]));
+
+	client.request("writeMemory", json!({"memoryReference": "0x300", "offset": 1, "data": base64_encode(b"hi!")}));
+	assert_eq!(&client.chip8.memory()[0x301..0x304], b"hi!");
+
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_oversized_message_is_rejected() {
+	let mut reader = io::Cursor::new(format!("Content-Length: {}\r\n\r\n{{}}", usize::MAX).into_bytes());
+	assert!(read_message(&mut reader).unwrap_err().contains("limit"));
+
+	let mut reader = io::Cursor::new(b"Content-Length: 2\r\n\r\n{}".to_vec());
+	assert_eq!(read_message(&mut reader), Ok(Some(json!({}))));
+}
+
+#[test]
+fn test_memory_address() {
+	let args = |offset: i64| json!({"memoryReference": "0x300", "offset": offset});
+	assert_eq!(memory_address(&args(2)), Ok(0x302));
+	assert_eq!(memory_address(&args(-0x300)), Ok(0));
+	assert_eq!(memory_address(&json!({"memoryReference": "0x300"})), Ok(0x300));
+
+	for offset in [-0x301, i64::MAX, i64::MIN] {
+		assert_eq!(memory_address(&args(offset)), Err(String::from("address out of range")));
+	}
+}
+
+#[test]
+fn test_base64() {
+	for bytes in [&b""[..], b"f", b"fo", b"foo", b"foob", b"\x00\xff\x10\x80"] {
+		assert_eq!(base64_decode(&base64_encode(bytes)).unwrap(), bytes);
+	}
+	assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+	assert_eq!(base64_decode("Zm9v!"), None);
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeSet;
+use crate::chip8::{Chip8, Chip8Error, Cycle, FrameHook};
+use crate::drivers::keyboard::KeyState;
+use crate::instruction::{decode, Instruction};
+
+#[cfg(test)]
+#[path = "./debug_tests.rs"]
+mod debug_tests;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    // a single step finished
+    Step,
+    Breakpoint(u16),
+    // the debugger asked the program to stop
+    Pause,
+    // the interpreter returned an error, pc is left at the faulting instruction
+    Error(Chip8Error),
+}
+
+// breakpoints and run/pause state on top of a Chip8, shared by the gdb and DAP servers.
+// the main loop calls run_frame instead of Chip8::run_frame while a debugger is attached
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    running: bool,
+    // set by resume so continuing from a breakpoint doesn't stop on it straight away
+    leaving_breakpoint: bool,
+    last_stop: Option<StopReason>,
+    // stop with StopReason::Step at this pc once the stack is no deeper than this, for step over/out
+    run_until: Option<(u16, usize)>,
+}
+
+impl Debugger {
+    // starts paused, like a program loaded in a debugger
+    pub fn new() -> Debugger {
+        Debugger::default()
+    }
+
+    pub fn set_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    pub fn clear_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    // why the program last stopped, None if it hasn't yet
+    pub fn last_stop(&self) -> Option<&StopReason> {
+        self.last_stop.as_ref()
+    }
+
+    pub fn resume(&mut self) {
+        self.running = true;
+        self.leaving_breakpoint = true;
+        self.run_until = None;
+    }
+
+    // step, but run a whole subroutine if the instruction is a call. None if it resumed
+    pub fn step_over(&mut self, chip8: &mut Chip8, key_state: &KeyState) -> Option<StopReason> {
+        let pc = chip8.pc() as usize;
+        let op = chip8.memory().get(pc..pc + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+        if !op.is_some_and(|op| matches!(decode(op), Ok(Instruction::Call(_)))) {
+            return Some(self.step(chip8, key_state));
+        }
+
+        self.resume();
+        self.run_until = Some((chip8.pc() + 2, chip8.stack().len()));
+        None
+    }
+
+    // run until the current subroutine returns, a single step outside of one
+    pub fn step_out(&mut self, chip8: &mut Chip8, key_state: &KeyState) -> Option<StopReason> {
+        let stack = chip8.stack();
+        match stack.last() {
+            Some(ret) => {
+                self.resume();
+                self.run_until = Some((*ret, stack.len() - 1));
+                None
+            },
+            None => Some(self.step(chip8, key_state)),
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if self.running {
+            self.stop(StopReason::Pause);
+        }
+    }
+
+    // execute one instruction without touching the timers
+    pub fn step(&mut self, chip8: &mut Chip8, key_state: &KeyState) -> StopReason {
+        let reason = match chip8.step(key_state) {
+            Ok(()) => StopReason::Step,
+            Err(e) => StopReason::Error(e),
+        };
+        self.stop(reason.clone());
+        reason
+    }
+
+    // like Chip8::run_frame, but stops before an instruction with a breakpoint. returns why it
+    // stopped, or None if the frame ran to the end or the program is paused
+    pub fn run_frame(&mut self, chip8: &mut Chip8, key_state: &KeyState, cycles: u32) -> Option<StopReason> {
+        if !self.running {
+            return None;
+        }
+
+        match chip8.run_frame_with(key_state, cycles, self) {
+            Ok(stop) => stop,
+            Err(e) => Some(self.stop(StopReason::Error(e))),
+        }
+    }
+
+    fn stop(&mut self, reason: StopReason) -> StopReason {
+        self.running = false;
+        self.leaving_breakpoint = false;
+        self.run_until = None;
+        self.last_stop = Some(reason.clone());
+        reason
+    }
+}
+
+impl FrameHook for Debugger {
+    type Stop = StopReason;
+
+    fn before_step(&mut self, chip8: &mut Chip8, _left: u32) -> Cycle<StopReason> {
+        let pc = chip8.pc();
+        if self.run_until.is_some_and(|(addr, depth)| pc == addr && chip8.stack().len() <= depth) {
+            return Cycle::Stop(self.stop(StopReason::Step));
+        }
+        if self.breakpoints.contains(&pc) && !self.leaving_breakpoint {
+            return Cycle::Stop(self.stop(StopReason::Breakpoint(pc)));
+        }
+        self.leaving_breakpoint = false;
+        Cycle::Step
+    }
+}
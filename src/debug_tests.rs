@@ -0,0 +1,84 @@
+use super::*;
+use crate::drivers::keyboard::create_key_state;
+use crate::rom_builder::{chip8_with_rom, RomBuilder};
+
+#[test]
+fn test_starts_paused() {
+	let mut chip8 = chip8_with_rom(&RomBuilder::new().ld(0, 1).build());
+	let mut dbg = Debugger::new();
+
+	assert_eq!(dbg.run_frame(&mut chip8, &create_key_state(), 10), None);
+	assert_eq!(chip8.pc(), 0x200);
+	assert_eq!(dbg.last_stop(), None);
+}
+
+#[test]
+fn test_breakpoint_stops_and_resumes() {
+	let rom = RomBuilder::new().ld(0, 1).ld(1, 2).add(0, 1).jp(0x204).build();
+	let mut chip8 = chip8_with_rom(&rom);
+	let mut dbg = Debugger::new();
+	let keys = create_key_state();
+
+	dbg.set_breakpoint(0x204);
+	dbg.resume();
+	assert_eq!(dbg.run_frame(&mut chip8, &keys, 10), Some(StopReason::Breakpoint(0x204)));
+	assert_eq!(chip8.pc(), 0x204);
+	assert_eq!(chip8.register(1), 2);
+	assert!(!dbg.is_running());
+
+	// continuing runs the instruction under the breakpoint and stops when it comes round again
+	dbg.resume();
+	assert_eq!(dbg.run_frame(&mut chip8, &keys, 10), Some(StopReason::Breakpoint(0x204)));
+	assert_eq!(chip8.register(0), 2);
+
+	dbg.clear_breakpoint(0x204);
+	dbg.resume();
+	assert_eq!(dbg.run_frame(&mut chip8, &keys, 10), None);
+	assert!(dbg.is_running());
+}
+
+#[test]
+fn test_step_and_pause() {
+	let mut chip8 = chip8_with_rom(&RomBuilder::new().ld(0, 1).op(0xF0FF).build());
+	let mut dbg = Debugger::new();
+	let keys = create_key_state();
+
+	assert_eq!(dbg.step(&mut chip8, &keys), StopReason::Step);
+	assert_eq!(chip8.pc(), 0x202);
+
+	// errors leave pc on the faulting instruction
+	assert!(matches!(dbg.step(&mut chip8, &keys), StopReason::Error(Chip8Error::InvalidOpcode {..})));
+	assert_eq!(chip8.pc(), 0x202);
+
+	dbg.resume();
+	dbg.pause();
+	assert_eq!(dbg.last_stop(), Some(&StopReason::Pause));
+	assert!(!dbg.is_running());
+}
+
+#[test]
+fn test_step_over_and_out() {
+	// 0x200: call 0x206 / 0x202: ld v1, 1 / 0x204: jp 0x204 / 0x206: ld v0, 5 / 0x208: add v0, 1 / 0x20A: ret
+	let rom = RomBuilder::new().call(0x206).ld(1, 1).jp(0x204).ld(0, 5).add(0, 1).ret().build();
+	let keys = create_key_state();
+	let mut chip8 = chip8_with_rom(&rom);
+	let mut dbg = Debugger::new();
+
+	assert_eq!(dbg.step_over(&mut chip8, &keys), None);
+	assert_eq!(dbg.run_frame(&mut chip8, &keys, 10), Some(StopReason::Step));
+	assert_eq!(chip8.pc(), 0x202);
+	assert_eq!(chip8.register(0), 6);
+
+	// not a call, so just a step
+	assert_eq!(dbg.step_over(&mut chip8, &keys), Some(StopReason::Step));
+	assert_eq!(chip8.pc(), 0x204);
+
+	let mut chip8 = chip8_with_rom(&rom);
+	dbg.step(&mut chip8, &keys);
+	dbg.step(&mut chip8, &keys);
+	assert_eq!(chip8.pc(), 0x208);
+	assert_eq!(dbg.step_out(&mut chip8, &keys), None);
+	assert_eq!(dbg.run_frame(&mut chip8, &keys, 10), Some(StopReason::Step));
+	assert_eq!(chip8.pc(), 0x202);
+	assert!(chip8.stack().is_empty());
+}
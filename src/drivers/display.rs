@@ -1,20 +1,46 @@
 use sdl2::Sdl;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-use sdl2::pixels::Color;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext, FullscreenType};
+use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::Rect;
+use crate::palette::Rgb;
+
+// grid lines and rounded corners need a few texels per chip8 pixel, past this the copy to the
+// window scales them up instead
+const MAX_CELL_SIZE: u32 = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PixelStyle {
+    pub grid: bool,     // leave a 1px gap between pixels
+    pub rounded: bool,  // cut the corners off each pixel
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScaleMode {
+    // largest whole number scale that fits the window, crisp pixels
+    #[default]
+    Integer,
+    // fill as much of the window as possible while keeping the aspect ratio
+    Aspect,
+}
 
 pub struct DisplayDriver {
     // sdl_context: Sdl,
     canvas: Canvas<Window>,
-    display_scale: u32,
+    texture_creator: TextureCreator<WindowContext>,
+    texture: Texture,
+    pix_width: u32,
+    pix_height: u32,
+    cell_size: u32,     // size of one chip8 pixel in the texture
+    pub style: PixelStyle,
+    pub scale_mode: ScaleMode,
 }
 
 impl DisplayDriver {
     pub fn new(sdl_context: &Sdl, display_scale: u32, pix_width: u32, pix_height: u32) -> DisplayDriver {
         let video_subsystem = sdl_context.video().unwrap();
         let window = video_subsystem
-            .window("Game", pix_height*display_scale, pix_width*display_scale)
+            .window("Game", pix_width*display_scale, pix_height*display_scale)
             .resizable()
             .build()
             .unwrap();
@@ -23,28 +49,127 @@ impl DisplayDriver {
             .present_vsync() //< this means the screen cannot
             // render faster than your display rate (usually 60Hz or 144Hz
             .build().unwrap();
-    
-        canvas.set_draw_color(Color::WHITE);
-        canvas.clear();
+
         canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+
+        // textures don't borrow the creator (unsafe_textures), the renderer frees them when it goes
+        let texture_creator = canvas.texture_creator();
+        let texture = create_texture(&texture_creator, pix_width, pix_height);
 
         DisplayDriver {
             canvas,
-            display_scale,
+            texture_creator,
+            texture,
+            pix_width,
+            pix_height,
+            cell_size: 1,
+            style: PixelStyle::default(),
+            scale_mode: ScaleMode::default(),
         }
     }
 
-    pub fn draw_pixel(&mut self, x: i32, y: i32, color: Color) {
-        self.canvas.set_draw_color(color);
+    // upload a full frame of pixel colours (row major) and scale it to the window.
+    // gap_color fills grid lines and the cut off corners of rounded pixels
+    pub fn draw_frame(&mut self, pixels: &[Rgb], gap_color: Rgb) {
+        assert_eq!(pixels.len(), (self.pix_width * self.pix_height) as usize);
+
+        let (win_w, win_h) = self.canvas.output_size().unwrap();
+        let dest = self.dest_rect(win_w, win_h);
+
+        // plain pixels are uploaded one texel each, grid lines and rounded corners get the
+        // integer scale closest to the window, capped, and the copy below scales the rest
+        let cell_size = if self.style == PixelStyle::default() {
+            1
+        } else {
+            (dest.width() / self.pix_width).clamp(1, MAX_CELL_SIZE)
+        };
+        if cell_size != self.cell_size {
+            self.cell_size = cell_size;
+            let texture = create_texture(&self.texture_creator, self.pix_width * cell_size, self.pix_height * cell_size);
+            // the canvas is still alive
+            unsafe { std::mem::replace(&mut self.texture, texture).destroy() };
+        }
+
+        let mask = cell_mask(cell_size, self.style);
+        let cell = cell_size as usize;
+        let width = self.pix_width as usize;
+
+        self.texture.with_lock(None, |buf: &mut [u8], pitch: usize| {
+            for (py, row) in pixels.chunks_exact(width).enumerate() {
+                for (my, mask_row) in mask.chunks_exact(cell).enumerate() {
+                    let line = &mut buf[(py * cell + my) * pitch..][..width * cell * 3];
+                    for (color, out) in row.iter().zip(line.chunks_exact_mut(cell * 3)) {
+                        for (&lit, texel) in mask_row.iter().zip(out.chunks_exact_mut(3)) {
+                            let c = if lit {color} else {&gap_color};
+                            texel.copy_from_slice(&[c.0, c.1, c.2]);
+                        }
+                    }
+                }
+            }
+        }).unwrap();
 
-        let scaled_x: i32 = x * self.display_scale as i32;
-        let scaled_y: i32 = y * self.display_scale as i32;
-    
-        self.canvas.fill_rect(Rect::new(scaled_x, scaled_y, self.display_scale, self.display_scale));
+        // letterbox
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        self.canvas.copy(&self.texture, None, dest).unwrap();
     }
 
     pub fn update_display(&mut self) {
-        // self.canvas.clear();
         self.canvas.present();
     }
-}
\ No newline at end of file
+
+    pub fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let next = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+
+        window.set_fullscreen(next).unwrap();
+    }
+
+    // area of the window the chip8 screen is drawn to, centered
+    fn dest_rect(&self, win_w: u32, win_h: u32) -> Rect {
+        let (w, h) = match self.scale_mode {
+            ScaleMode::Integer => {
+                let scale = (win_w / self.pix_width).min(win_h / self.pix_height).max(1);
+                (self.pix_width * scale, self.pix_height * scale)
+            },
+            ScaleMode::Aspect => {
+                if win_w * self.pix_height > win_h * self.pix_width {
+                    (win_h * self.pix_width / self.pix_height, win_h)
+                } else {
+                    (win_w, win_w * self.pix_height / self.pix_width)
+                }
+            },
+        };
+
+        Rect::new((win_w as i32 - w as i32) / 2, (win_h as i32 - h as i32) / 2, w.max(1), h.max(1))
+    }
+}
+
+fn create_texture(creator: &TextureCreator<WindowContext>, width: u32, height: u32) -> Texture {
+    creator.create_texture_streaming(PixelFormatEnum::RGB24, width, height).unwrap()
+}
+
+// which texels of a single chip8 pixel are drawn in the pixel colour, row major
+fn cell_mask(cell_size: u32, style: PixelStyle) -> Vec<bool> {
+    let cell = cell_size as usize;
+
+    // grid lines are a gap on the right and bottom edge
+    let size = if style.grid && cell > 2 {cell - 1} else {cell};
+
+    // a rounded pixel has a square cut out of each corner
+    let inset = if style.rounded && size >= 4 {(size / 4).max(1)} else {0};
+    let edge = |v: usize| v < inset || v >= size - inset;
+
+    let mut mask = vec![false; cell * cell];
+    for y in 0..size {
+        for x in 0..size {
+            mask[y * cell + x] = !(edge(x) && edge(y));
+        }
+    }
+
+    mask
+}
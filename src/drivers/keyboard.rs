@@ -4,10 +4,26 @@ use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use std::collections::HashMap;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Hotkey {
+    CyclePalette,
+    ToggleGrid,
+    ToggleRounded,
+    ToggleFullscreen,
+    Screenshot,
+    ToggleRecording,
+    Pause,
+    FrameAdvance,
+    ToggleFastForward,
+    ToggleSlowMotion,
+}
+
 pub struct KeyboardDriver {
     event_pump: EventPump,
     key_map: KeyMap,
+    hotkey_map: HotkeyMap,
     pub key_state: KeyState,
+    pub hotkeys: Vec<Hotkey>,   // hotkeys pressed since the last update
     pub exit_requested: bool,
 }
 
@@ -15,18 +31,24 @@ impl KeyboardDriver {
     pub fn new(sdl: &Sdl) -> KeyboardDriver {
         let event_pump = sdl.event_pump().unwrap();
         let key_map = create_key_map();
+        let hotkey_map = create_hotkey_map();
         let key_state = create_key_state();
 
         let exit_requested = false;
 
-        KeyboardDriver{event_pump, key_map, key_state, exit_requested}
+        KeyboardDriver{event_pump, key_map, hotkey_map, key_state, hotkeys: Vec::new(), exit_requested}
     }
 
     pub fn update(&mut self) {
+        self.hotkeys.clear();
+
         let p = &mut self.event_pump;
         for event in p.poll_iter() {
             match event {
                 Event::Quit {..} => self.exit_requested = true,
+                Event::KeyDown {keycode: Some(code), repeat: false, ..} if self.hotkey_map.contains_key(&code) => {
+                    self.hotkeys.push(self.hotkey_map[&code]);
+                }
                 Event::KeyDown {keycode, ..} => {
                     match keycode {
                         Some(code) => {
@@ -56,6 +78,17 @@ impl KeyboardDriver {
         }
     }
 
+    // map a button from the ROM database ("up", "player2A", ..) to chip8 key `key`
+    pub fn bind(&mut self, button: &str, key: u8) -> bool {
+        match (button_keycode(button), KeyboardDriver::int_to_key(key)) {
+            (Some(code), Some(k)) => {
+                self.key_map.insert(code, k);
+                true
+            },
+            _ => false,
+        }
+    }
+
     pub fn int_to_key(val: u8) -> Option<Keycode> {
         match val {
             0 => Some(Keycode::Num0),
@@ -146,3 +179,40 @@ pub fn create_key_map() -> KeyMap {
         (Keycode::V,    Keycode::F),   
     ])
 }
+
+// physical keys for the buttons named in the ROM database, player 1 gets the arrow
+// keys and player 2 IJKL, which the default key map leaves free
+pub fn button_keycode(button: &str) -> Option<Keycode> {
+    match button {
+        "up" => Some(Keycode::Up),
+        "down" => Some(Keycode::Down),
+        "left" => Some(Keycode::Left),
+        "right" => Some(Keycode::Right),
+        "a" => Some(Keycode::Space),
+        "b" => Some(Keycode::Return),
+        "player2Up" => Some(Keycode::I),
+        "player2Down" => Some(Keycode::K),
+        "player2Left" => Some(Keycode::J),
+        "player2Right" => Some(Keycode::L),
+        "player2A" => Some(Keycode::U),
+        "player2B" => Some(Keycode::O),
+        _ => None,
+    }
+}
+
+type HotkeyMap = HashMap<Keycode, Hotkey>;
+
+pub fn create_hotkey_map() -> HotkeyMap {
+    HashMap::from([
+        (Keycode::F1, Hotkey::CyclePalette),
+        (Keycode::F2, Hotkey::ToggleGrid),
+        (Keycode::F3, Hotkey::ToggleRounded),
+        (Keycode::F5, Hotkey::Pause),
+        (Keycode::F6, Hotkey::FrameAdvance),
+        (Keycode::F7, Hotkey::ToggleFastForward),
+        (Keycode::F8, Hotkey::ToggleSlowMotion),
+        (Keycode::F10, Hotkey::ToggleRecording),
+        (Keycode::F11, Hotkey::ToggleFullscreen),
+        (Keycode::F12, Hotkey::Screenshot),
+    ])
+}
@@ -0,0 +1,105 @@
+use crate::chip8::{PIXEL_WIDTH, PIXEL_HEIGHT};
+
+#[cfg(test)]
+#[path = "./framebuffer_tests.rs"]
+mod framebuffer_tests;
+
+// bit planes, XO-CHIP draws to two. a pixel's value has bit n set when it's lit in plane n
+pub const PLANES: usize = 2;
+
+// the screen as one u64 per row per plane, the leftmost pixel in the top bit, so sprites are
+// drawn a row at a time with shifts and XOR and collisions found with AND
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Framebuffer {
+    planes: [[u64; PIXEL_HEIGHT]; PLANES],
+}
+
+impl Framebuffer {
+    pub fn new() -> Framebuffer {
+        Framebuffer::default()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Framebuffer::default();
+    }
+
+    // lit in any plane
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.value(x, y) != 0
+    }
+
+    // palette index of a pixel, see Palette::color
+    pub fn value(&self, x: usize, y: usize) -> u32 {
+        let bit = 63 - x;
+        self.planes.iter().enumerate().fold(0, |val, (n, plane)| val | ((plane[y] >> bit) as u32 & 1) << n)
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, val: u32) {
+        let bit = 1 << (63 - x);
+        for (n, plane) in self.planes.iter_mut().enumerate() {
+            if val & (1 << n) != 0 {
+                plane[y] |= bit;
+            } else {
+                plane[y] &= !bit;
+            }
+        }
+    }
+
+    pub fn row(&self, plane: usize, y: usize) -> u64 {
+        self.planes[plane][y]
+    }
+
+    // pixel values of row y, left to right
+    pub fn row_values(&self, y: usize) -> impl Iterator<Item = u32> + '_ {
+        (0..PIXEL_WIDTH).map(move |x| self.value(x, y))
+    }
+
+    // pixel values a row at a time, top to bottom
+    pub fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = u32> + '_> + '_ {
+        (0..PIXEL_HEIGHT).map(move |y| self.row_values(y))
+    }
+
+    // rows that differ from another frame, for frontends that only redraw what changed
+    pub fn changed_rows<'a>(&'a self, other: &'a Framebuffer) -> impl Iterator<Item = usize> + 'a {
+        (0..PIXEL_HEIGHT).filter(move |y| self.planes.iter().zip(other.planes.iter()).any(|(a, b)| a[*y] != b[*y]))
+    }
+
+    // XOR an 8 pixel wide sprite row onto row y with its left edge at x, pixels past the right edge
+    // are clipped or wrap around to the left. true if a lit pixel was turned off
+    pub fn draw_sprite_row(&mut self, plane: usize, x: usize, y: usize, sprite: u8, wrap: bool) -> bool {
+        let sprite = (sprite as u64) << (64 - 8);
+        let bits = if wrap {sprite.rotate_right(x as u32)} else {sprite >> x};
+
+        let row = &mut self.planes[plane][y];
+        let collision = *row & bits != 0;
+        *row ^= bits;
+        collision
+    }
+
+    // SCHIP and XO-CHIP scrolling, pixels scrolled off the edge are lost
+    pub fn scroll_down(&mut self, n: usize) {
+        for plane in self.planes.iter_mut() {
+            plane.rotate_right(n.min(PIXEL_HEIGHT));
+            plane[..n.min(PIXEL_HEIGHT)].fill(0);
+        }
+    }
+
+    pub fn scroll_up(&mut self, n: usize) {
+        for plane in self.planes.iter_mut() {
+            plane.rotate_left(n.min(PIXEL_HEIGHT));
+            plane[PIXEL_HEIGHT - n.min(PIXEL_HEIGHT)..].fill(0);
+        }
+    }
+
+    pub fn scroll_left(&mut self, n: usize) {
+        for row in self.planes.iter_mut().flatten() {
+            *row = row.checked_shl(n as u32).unwrap_or(0);
+        }
+    }
+
+    pub fn scroll_right(&mut self, n: usize) {
+        for row in self.planes.iter_mut().flatten() {
+            *row = row.checked_shr(n as u32).unwrap_or(0);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use super::*;
+use proptest::prelude::*;
+
+fn lit(fb: &Framebuffer) -> Vec<(usize, usize)> {
+	(0..PIXEL_HEIGHT).flat_map(|y| (0..PIXEL_WIDTH).map(move |x| (x, y))).filter(|(x, y)| fb.pixel(*x, *y)).collect()
+}
+
+#[test]
+fn test_set_pixel_per_plane() {
+	let mut fb = Framebuffer::new();
+
+	fb.set_pixel(0, 0, 1);
+	fb.set_pixel(63, 31, 3);
+	fb.set_pixel(5, 2, 2);
+
+	assert_eq!(fb.value(0, 0), 1);
+	assert_eq!(fb.value(63, 31), 3);
+	assert_eq!(fb.value(5, 2), 2);
+	assert_eq!(fb.row(0, 0), 1 << 63);
+	assert_eq!(fb.row(1, 31), 1);
+	assert_eq!(lit(&fb), vec![(0, 0), (5, 2), (63, 31)]);
+
+	fb.set_pixel(63, 31, 0);
+	assert!(!fb.pixel(63, 31));
+}
+
+#[test]
+fn test_draw_sprite_row_clips_or_wraps() {
+	let mut fb = Framebuffer::new();
+	assert!(!fb.draw_sprite_row(0, 60, 0, 0xFF, false));
+	assert_eq!(lit(&fb), vec![(60, 0), (61, 0), (62, 0), (63, 0)]);
+
+	let mut fb = Framebuffer::new();
+	assert!(!fb.draw_sprite_row(0, 60, 0, 0xFF, true));
+	assert_eq!(lit(&fb), vec![(0, 0), (1, 0), (2, 0), (3, 0), (60, 0), (61, 0), (62, 0), (63, 0)]);
+}
+
+#[test]
+fn test_draw_sprite_row_collides() {
+	let mut fb = Framebuffer::new();
+
+	assert!(!fb.draw_sprite_row(0, 0, 3, 0b1100_0000, false));
+	assert!(!fb.draw_sprite_row(0, 2, 3, 0b1100_0000, false));
+	assert!(fb.draw_sprite_row(0, 1, 3, 0b1000_0000, false));
+
+	assert_eq!(lit(&fb), vec![(0, 3), (2, 3), (3, 3)]);
+}
+
+#[test]
+fn test_scrolling_drops_pixels_off_the_edge() {
+	let mut fb = Framebuffer::new();
+	fb.set_pixel(0, 0, 1);
+	fb.set_pixel(63, 31, 1);
+
+	fb.scroll_down(1);
+	assert_eq!(lit(&fb), vec![(0, 1)]);
+	fb.scroll_right(4);
+	assert_eq!(lit(&fb), vec![(4, 1)]);
+	fb.scroll_left(2);
+	assert_eq!(lit(&fb), vec![(2, 1)]);
+	fb.scroll_up(2);
+	assert!(lit(&fb).is_empty());
+}
+
+#[test]
+fn test_changed_rows() {
+	let mut a = Framebuffer::new();
+	let b = a;
+	a.set_pixel(4, 7, 2);
+	a.set_pixel(9, 20, 1);
+
+	assert_eq!(a.changed_rows(&b).collect::<Vec<_>>(), vec![7, 20]);
+	assert_eq!(a.changed_rows(&a).count(), 0);
+}
+
+#[test]
+fn test_rows_iterate_values() {
+	let mut fb = Framebuffer::new();
+	fb.set_pixel(1, 0, 3);
+
+	let rows: Vec<Vec<u32>> = fb.rows().map(|row| row.collect()).collect();
+
+	assert_eq!(rows.len(), PIXEL_HEIGHT);
+	assert!(rows.iter().all(|row| row.len() == PIXEL_WIDTH));
+	assert_eq!(&rows[0][..3], &[0, 3, 0]);
+}
+
+proptest! {
+	// a row drawn at once matches drawing its pixels one at a time
+	#[test]
+	fn prop_draw_sprite_row_matches_per_pixel(row in any::<u64>(), x in 0..PIXEL_WIDTH, sprite in any::<u8>(), wrap in any::<bool>()) {
+		let mut fb = Framebuffer::new();
+		for col in 0..PIXEL_WIDTH {
+			fb.set_pixel(col, 0, ((row >> (63 - col)) & 1) as u32);
+		}
+		let mut expected = fb;
+		let mut expected_collision = false;
+		for icol in 0..8 {
+			if icol + x >= PIXEL_WIDTH && !wrap {
+				continue;
+			}
+			let col = (x + icol) % PIXEL_WIDTH;
+			if sprite & (0x80 >> icol) != 0 {
+				let was = expected.value(col, 0);
+				expected.set_pixel(col, 0, was ^ 1);
+				expected_collision |= was == 1;
+			}
+		}
+
+		let collision = fb.draw_sprite_row(0, x, 0, sprite, wrap);
+
+		prop_assert_eq!(fb, expected);
+		prop_assert_eq!(collision, expected_collision);
+	}
+}
@@ -0,0 +1,276 @@
+use std::io::Stdout;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::drivers::keyboard::{KeyboardDriver, KeyState, Hotkey};
+use crate::drivers::display::DisplayDriver;
+use crate::drivers::sound::SoundDriver;
+
+use crate::chip8::{Chip8, Chip8Error, PIXEL_WIDTH, PIXEL_HEIGHT};
+use crate::config::Config;
+use crate::palette::Rgb;
+use crate::screen_render::ScreenRenderer;
+use crate::screenshot::save_screenshot;
+use crate::recorder::Recorder;
+use crate::debug::{Debugger, StopReason};
+use crate::gdb::{GdbServer, GdbEvent};
+use crate::dap::DapSession;
+use crate::cheats::{CheatConsole, CheatList};
+use crate::romdb::sha1_hex;
+use crate::run_ahead::run_ahead;
+use crate::scheduler::{Scheduler, Speed};
+
+const DISPLAY_SCALE: u32 = 10;
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// stdout carries the debug adapter protocol in dap mode, messages go to stderr instead
+pub static STDOUT_IS_DAP: AtomicBool = AtomicBool::new(false);
+
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if STDOUT_IS_DAP.load(Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+// a debugger front end driving the emulator between frames
+pub enum DebugServer {
+    Gdb(GdbServer),
+    Dap(DapSession<Stdout>),
+}
+
+// what runs the program each frame, the interpreter or a ROM recompiled to Rust
+pub trait Runner {
+    fn run_frame(&mut self, chip8: &mut Chip8, key_state: &KeyState, cycles: u32) -> Result<(), Chip8Error>;
+
+    // pixels drawn over the screen after the frame, in chip8 coordinates
+    fn overlay(&self) -> &[(usize, usize, Rgb)] {
+        &[]
+    }
+
+    fn exit_requested(&self) -> bool {
+        false
+    }
+}
+
+pub struct Interpreter;
+
+impl Runner for Interpreter {
+    fn run_frame(&mut self, chip8: &mut Chip8, key_state: &KeyState, cycles: u32) -> Result<(), Chip8Error> {
+        chip8.run_frame(key_state, cycles)
+    }
+}
+
+// open the window and run the ROM until it's closed. with a debugger attached frames go through
+// the debugger instead of the runner
+pub fn run(rom: Vec<u8>, mut config: Config, mut debugger: Option<(DebugServer, Debugger)>, runner: &mut dyn Runner) -> Result<(), Chip8Error> {
+    let sdl = sdl2::init().unwrap();
+
+    // init drivers
+    let mut disp = DisplayDriver::new(&sdl, DISPLAY_SCALE, PIXEL_WIDTH as u32, PIXEL_HEIGHT as u32);
+    disp.style = config.pixel_style;
+    disp.scale_mode = config.scale_mode;
+    let mut kb = KeyboardDriver::new(&sdl);
+    for (button, key) in config.keys.iter() {
+        kb.bind(button, *key);
+    }
+    let mut sound = SoundDriver::new(&sdl);
+    let mut renderer = ScreenRenderer::new(config.render_mode());
+
+    // cheats saved for this ROM are held every frame, --cheats takes commands on stdin to find more
+    let cheat_path = CheatConsole::path_for(&config.cheat_dir, &sha1_hex(&rom));
+    let cheats = CheatList::load(&cheat_path).unwrap_or_else(|e| {
+        eprintln!("ignoring cheats: {}", e);
+        CheatList::default()
+    });
+    let mut cheats = CheatConsole::new(cheats, cheat_path);
+    if config.cheat_console && !STDOUT_IS_DAP.load(Ordering::Relaxed) {
+        cheats.read_stdin();
+    }
+
+    // init chip8 VM
+    let mut chip8 = Chip8::new();
+    chip8.quirks = config.quirks;
+
+    // load rom
+    chip8.load_rom(rom)?;
+
+    let mut recorder: Option<Recorder> = None;
+    if config.record_on_start {
+        toggle_recording(&mut recorder, &config);
+    }
+
+    let mut scheduler = Scheduler::new(config.fast_forward, config.slow_motion, config.mute_fast_forward);
+    if config.start_paused {
+        scheduler.toggle_pause();
+    }
+
+    'main: loop {
+        let frame_start = Instant::now();
+
+        kb.update();
+
+        if kb.exit_requested || runner.exit_requested() {
+            break 'main;
+        }
+
+        for hotkey in kb.hotkeys.iter() {
+            match hotkey {
+                Hotkey::CyclePalette => config.palette = config.palette.next(),
+                Hotkey::ToggleGrid => disp.style.grid = !disp.style.grid,
+                Hotkey::ToggleRounded => disp.style.rounded = !disp.style.rounded,
+                Hotkey::ToggleFullscreen => disp.toggle_fullscreen(),
+                Hotkey::Screenshot => {
+                    let image = chip8.to_image(config.screenshot_scale, &config.palette);
+                    match save_screenshot(&image, &config.screenshot_dir) {
+                        Ok(path) => status!("saved screenshot to {}", path.display()),
+                        Err(e) => eprintln!("could not save screenshot: {}", e),
+                    }
+                },
+                Hotkey::ToggleRecording => toggle_recording(&mut recorder, &config),
+                Hotkey::Pause => {
+                    scheduler.toggle_pause();
+                    status!("{}", if scheduler.is_paused() {"paused"} else {"running"});
+                },
+                Hotkey::FrameAdvance => scheduler.advance_frame(),
+                Hotkey::ToggleFastForward => {
+                    scheduler.toggle_fast_forward();
+                    print_speed(&scheduler);
+                },
+                Hotkey::ToggleSlowMotion => {
+                    scheduler.toggle_slow_motion();
+                    print_speed(&scheduler);
+                },
+            }
+        }
+
+        match debugger.as_mut() {
+            Some((DebugServer::Gdb(server), dbg)) => match server.poll(&mut chip8, dbg, &kb.key_state) {
+                Some(GdbEvent::Connected(addr)) => status!("gdb connected from {}", addr),
+                Some(GdbEvent::Disconnected) => status!("gdb disconnected"),
+                None => (),
+            },
+            Some((DebugServer::Dap(session), dbg)) => match session.poll(&mut chip8, dbg, &kb.key_state) {
+                Ok(true) => (),
+                Ok(false) => break 'main,
+                Err(e) => {
+                    eprintln!("debug adapter: {}", e);
+                    break 'main;
+                },
+            },
+            None => (),
+        }
+        cheats.poll(&mut chip8);
+
+        // none while paused, several while fast forwarding
+        let frames = scheduler.frames_due();
+        for _ in 0..frames {
+            match debugger.as_mut() {
+                Some((_, dbg)) => {
+                    // errors are left for gdb to look at instead of exiting
+                    if let Some(StopReason::Error(e)) = dbg.run_frame(&mut chip8, &kb.key_state, config.cycles_per_frame) {
+                        eprintln!("error: {}", e);
+                    }
+                },
+                None => {
+                    if let Err(e) = runner.run_frame(&mut chip8, &kb.key_state, config.cycles_per_frame) {
+                        eprintln!("error: {}", e);
+                        break 'main;
+                    }
+                },
+            }
+
+            cheats.cheats.apply(&mut chip8);
+
+            if let Some(rec) = recorder.as_mut() {
+                if let Err(e) = rec.capture(&chip8) {
+                    eprintln!("recording stopped: {}", e);
+                    recorder = None;
+                }
+            }
+        }
+
+        // draw the framebuffer to screen, every frame so the render filter can fade pixels out.
+        // with run-ahead it's the screen from a few frames on, not while paused or with a
+        // debugger looking at this one
+        let shown = match debugger {
+            None if config.run_ahead > 0 && frames > 0 => {
+                run_ahead(&mut chip8, &kb.key_state, config.cycles_per_frame, config.run_ahead, &cheats.cheats)
+            },
+            _ => chip8.framebuffer,
+        };
+        renderer.push_frame(&shown);
+        draw_frame(&renderer, &mut disp, &config, runner.overlay());
+
+        // handle sound
+        let beep = chip8.sound_timer > 0 && !scheduler.muted();
+        if beep && !sound.on {
+            sound.resume();
+        } else if !beep && sound.on {
+            sound.pause();
+        }
+
+        disp.update_display();
+
+        // keep frames at ~60 Hz when vsync runs faster or is unavailable
+        if let Some(remaining) = FRAME_DURATION.checked_sub(frame_start.elapsed()) {
+            sleep(remaining);
+        }
+    }
+
+    if recorder.is_some() {
+        toggle_recording(&mut recorder, &config);
+    }
+    if let Some((DebugServer::Dap(session), _)) = debugger.as_mut() {
+        // the editor may already be gone
+        let _ = session.terminated();
+    }
+
+    Ok(())
+}
+
+fn print_speed(scheduler: &Scheduler) {
+    match scheduler.speed() {
+        Speed::Normal => status!("normal speed"),
+        Speed::FastForward => status!("fast forward x{}", scheduler.multiplier()),
+        Speed::SlowMotion => status!("slow motion x{}", scheduler.multiplier()),
+    }
+}
+
+fn toggle_recording(recorder: &mut Option<Recorder>, config: &Config) {
+    match recorder.take() {
+        Some(rec) => match rec.finish() {
+            Ok((path, hint)) => {
+                status!("saved recording to {}", path.display());
+                if let Some(hint) = hint {
+                    status!("{}", hint);
+                }
+            },
+            Err(e) => eprintln!("could not save recording: {}", e),
+        },
+        None => match Recorder::start(config.record_format, &config.recording_dir, config.record_scale, &config.palette) {
+            Ok(rec) => {
+                status!("recording to {}", rec.path().display());
+                *recorder = Some(rec);
+            },
+            Err(e) => eprintln!("could not start recording: {}", e),
+        },
+    }
+}
+
+fn draw_frame(renderer: &ScreenRenderer, disp: &mut DisplayDriver, config: &Config, overlay: &[(usize, usize, Rgb)]) {
+        let palette = &config.palette;
+
+        // grid lines are drawn in a colour just off the background
+        let gap_color = if disp.style.grid {palette.background().blend(palette.foreground(), 0.15)} else {palette.background()};
+
+        let mut pixels: Vec<Rgb> = renderer.colors(palette).iter().flatten().copied().collect();
+        for (x, y, color) in overlay {
+            pixels[y * PIXEL_WIDTH + x] = *color;
+        }
+        disp.draw_frame(&pixels, gap_color);
+}
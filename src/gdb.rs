@@ -0,0 +1,412 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use crate::chip8::{Chip8, Chip8Error, MEMORY_SIZE};
+use crate::debug::{Debugger, StopReason};
+use crate::drivers::keyboard::KeyState;
+
+#[cfg(test)]
+#[path = "./gdb_tests.rs"]
+mod gdb_tests;
+
+// gdb register numbers: V0-VF are 0-15, then I, PC, SP, DT and ST
+const REG_I: usize = 16;
+const REG_PC: usize = 17;
+const REG_SP: usize = 18;
+const REG_DT: usize = 19;
+const REG_ST: usize = 20;
+const NUM_REGS: usize = 21;
+
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+// returned by GdbServer::poll for the frontend to report
+#[derive(Debug, PartialEq, Eq)]
+pub enum GdbEvent {
+    Connected(SocketAddr),
+    Disconnected,
+}
+
+// gdb remote serial protocol over TCP on localhost. polled from the main loop,
+// one client at a time
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<GdbClient<TcpStream>>,
+}
+
+impl GdbServer {
+    pub fn bind(port: u16) -> io::Result<GdbServer> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+        Ok(GdbServer {listener, client: None})
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    // accept a client, answer its packets and tell it when the program stops
+    pub fn poll(&mut self, chip8: &mut Chip8, dbg: &mut Debugger, key_state: &KeyState) -> Option<GdbEvent> {
+        let mut event = None;
+
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, addr)) if stream.set_nonblocking(true).is_ok() => {
+                    // gdb expects the target to be stopped when it attaches
+                    dbg.pause();
+                    self.client = Some(GdbClient::new(stream));
+                    event = Some(GdbEvent::Connected(addr));
+                },
+                Ok(_) => (),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => (),
+                Err(e) => eprintln!("gdb: {}", e),
+            }
+        }
+
+        if let Some(client) = self.client.as_mut() {
+            match client.poll(chip8, dbg, key_state) {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.client = None;
+                    event = Some(GdbEvent::Disconnected);
+                },
+                Err(e) => {
+                    eprintln!("gdb: {}", e);
+                    self.client = None;
+                    event = Some(GdbEvent::Disconnected);
+                },
+            }
+        }
+
+        event
+    }
+}
+
+// one connection, generic so tests can drive it over any stream
+pub struct GdbClient<S: Read + Write> {
+    stream: S,
+    input: Vec<u8>,
+    no_ack: bool,
+    // a continue is waiting for the program to stop
+    waiting_for_stop: bool,
+    open: bool,
+}
+
+impl<S: Read + Write> GdbClient<S> {
+    pub fn new(stream: S) -> GdbClient<S> {
+        GdbClient {stream, input: Vec::new(), no_ack: false, waiting_for_stop: false, open: true}
+    }
+
+    // handle everything received so far, false once the client has gone
+    pub fn poll(&mut self, chip8: &mut Chip8, dbg: &mut Debugger, key_state: &KeyState) -> io::Result<bool> {
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.input.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        while let Some(packet) = self.next_packet()? {
+            if let Some(reply) = self.handle_packet(&packet, chip8, dbg, key_state) {
+                self.send(&reply)?;
+            }
+        }
+
+        if self.waiting_for_stop && !dbg.is_running() {
+            self.waiting_for_stop = false;
+            let reply = stop_reply(dbg.last_stop());
+            self.send(&reply)?;
+        }
+
+        self.stream.flush()?;
+        Ok(self.open)
+    }
+
+    // pull the next "$data#xx" packet off the input, acking it. the ^C interrupt byte pauses the program
+    fn next_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.input.first() {
+                None => return Ok(None),
+                Some(b'$') => (),
+                Some(0x03) => {
+                    self.input.remove(0);
+                    return Ok(Some(String::from("\x03")));
+                },
+                // acks, and noise between packets
+                Some(_) => {
+                    self.input.remove(0);
+                    continue;
+                },
+            }
+
+            let end = match self.input.iter().position(|b| *b == b'#') {
+                Some(end) if end + 2 < self.input.len() => end,
+                _ => return Ok(None),
+            };
+
+            let data = unescape(&self.input[1..end]);
+            let checksum = std::str::from_utf8(&self.input[end + 1..end + 3]).ok().and_then(|c| u8::from_str_radix(c, 16).ok());
+            self.input.drain(..end + 3);
+
+            if checksum != Some(data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))) {
+                if !self.no_ack {
+                    self.stream.write_all(b"-")?;
+                }
+                continue;
+            }
+
+            if !self.no_ack {
+                self.stream.write_all(b"+")?;
+            }
+            return Ok(Some(String::from_utf8_lossy(&data).to_string()));
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let mut escaped = Vec::new();
+        for b in data.bytes() {
+            if matches!(b, b'$' | b'#' | b'}' | b'*') {
+                escaped.extend_from_slice(&[b'}', b ^ 0x20]);
+            } else {
+                escaped.push(b);
+            }
+        }
+
+        let checksum = escaped.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        self.stream.write_all(b"$")?;
+        self.stream.write_all(&escaped)?;
+        write!(self.stream, "#{:02x}", checksum)
+    }
+
+    // the reply to a packet, None when there's nothing to send yet
+    fn handle_packet(&mut self, packet: &str, chip8: &mut Chip8, dbg: &mut Debugger, key_state: &KeyState) -> Option<String> {
+        const OK: &str = "OK";
+        const ERROR: &str = "E01";
+        let unsupported = String::new();
+
+        let reply = match packet.as_bytes().first() {
+            Some(0x03) => {
+                dbg.pause();
+                return None;
+            },
+            Some(b'?') => stop_reply(dbg.last_stop().or(Some(&StopReason::Step))),
+            Some(b'g') => (0..NUM_REGS).map(|r| read_register(chip8, r)).collect(),
+            Some(b'G') => {
+                let mut rest = &packet[1..];
+                let mut ok = true;
+                for reg in 0..NUM_REGS {
+                    let len = register_size(reg) * 2;
+                    if rest.len() < len {
+                        break;
+                    }
+                    ok &= write_register(chip8, reg, &rest[..len]);
+                    rest = &rest[len..];
+                }
+                String::from(if ok {OK} else {ERROR})
+            },
+            Some(b'p') => match usize::from_str_radix(&packet[1..], 16) {
+                Ok(reg) if reg < NUM_REGS => read_register(chip8, reg),
+                _ => String::from(ERROR),
+            },
+            Some(b'P') => {
+                let written = packet[1..].split_once('=').and_then(|(reg, val)| {
+                    let reg = usize::from_str_radix(reg, 16).ok().filter(|r| *r < NUM_REGS)?;
+                    Some(write_register(chip8, reg, val))
+                });
+                String::from(if written == Some(true) {OK} else {ERROR})
+            },
+            Some(b'm') => match parse_addr_len(&packet[1..]).and_then(|(addr, len)| Some((addr, addr.checked_add(len)?))) {
+                Some((addr, end)) => {
+                    let end = end.min(MEMORY_SIZE);
+                    chip8.memory()[addr.min(end)..end].iter().map(|b| format!("{:02x}", b)).collect()
+                },
+                None => String::from(ERROR),
+            },
+            Some(b'M') => {
+                let written = packet[1..].split_once(':').and_then(|(range, data)| {
+                    let (addr, len) = parse_addr_len(range)?;
+                    let bytes = decode_hex(data)?;
+                    if bytes.len() != len {
+                        return None;
+                    }
+                    chip8.write_memory(u16::try_from(addr).ok()?, &bytes).ok()
+                });
+                String::from(if written.is_some() {OK} else {ERROR})
+            },
+            Some(b's') | Some(b'c') => {
+                if let Ok(addr) = u16::from_str_radix(&packet[1..], 16) {
+                    chip8.set_pc(addr);
+                }
+                if packet.starts_with('s') {
+                    let reason = dbg.step(chip8, key_state);
+                    stop_reply(Some(&reason))
+                } else {
+                    dbg.resume();
+                    self.waiting_for_stop = true;
+                    return None;
+                }
+            },
+            // software and hardware breakpoints are the same thing here
+            Some(b'Z') | Some(b'z') => {
+                let mut parts = packet[1..].split(',');
+                let kind = parts.next();
+                let addr = parts.next().and_then(|a| u16::from_str_radix(a, 16).ok());
+
+                match (kind, addr) {
+                    (Some("0") | Some("1"), Some(addr)) => {
+                        if packet.starts_with('Z') {
+                            dbg.set_breakpoint(addr);
+                        } else {
+                            dbg.clear_breakpoint(addr);
+                        }
+                        String::from(OK)
+                    },
+                    (Some("0") | Some("1"), None) => String::from(ERROR),
+                    _ => unsupported,
+                }
+            },
+            Some(b'H') => String::from(OK),
+            Some(b'D') | Some(b'k') => {
+                dbg.clear_breakpoints();
+                dbg.resume();
+                self.open = false;
+                if packet.starts_with('k') {
+                    return None;
+                }
+                String::from(OK)
+            },
+            Some(b'q') | Some(b'Q') => self.handle_query(packet).unwrap_or(unsupported),
+            _ => unsupported,
+        };
+
+        Some(reply)
+    }
+
+    fn handle_query(&mut self, packet: &str) -> Option<String> {
+        if packet.starts_with("qSupported") {
+            return Some(String::from("PacketSize=1000;qXfer:features:read+;QStartNoAckMode+"));
+        }
+        if let Some(args) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let (offset, len) = parse_addr_len(args)?;
+            let Some(end) = offset.checked_add(len) else {
+                return Some(String::from("E01"));
+            };
+            let xml = target_xml();
+            let chunk = xml.get(offset.min(xml.len())..end.min(xml.len()))?;
+            let more = end < xml.len();
+            return Some(format!("{}{}", if more {'m'} else {'l'}, chunk));
+        }
+
+        match packet {
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                Some(String::from("OK"))
+            },
+            "qAttached" => Some(String::from("1")),
+            "qC" => Some(String::from("QC1")),
+            "qfThreadInfo" => Some(String::from("m1")),
+            "qsThreadInfo" => Some(String::from("l")),
+            _ => None,
+        }
+    }
+}
+
+fn stop_reply(reason: Option<&StopReason>) -> String {
+    let signal = match reason {
+        Some(StopReason::Pause) => SIGINT,
+        Some(StopReason::Error(Chip8Error::InvalidOpcode {..})) => SIGILL,
+        Some(StopReason::Error(_)) => SIGSEGV,
+        _ => SIGTRAP,
+    };
+    format!("S{:02x}", signal)
+}
+
+fn register_size(reg: usize) -> usize {
+    match reg {
+        REG_I | REG_PC => 2,
+        _ => 1,
+    }
+}
+
+// little endian hex, as gdb sends and expects registers
+fn read_register(chip8: &Chip8, reg: usize) -> String {
+    let val = match reg {
+        0..=15 => chip8.register(reg) as u16,
+        REG_I => chip8.index_register(),
+        REG_PC => chip8.pc(),
+        REG_SP => chip8.stack().len() as u16,
+        REG_DT => chip8.delay_timer() as u16,
+        REG_ST => chip8.sound_timer as u16,
+        _ => 0,
+    };
+
+    val.to_le_bytes()[..register_size(reg)].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn write_register(chip8: &mut Chip8, reg: usize, hex: &str) -> bool {
+    let bytes = match decode_hex(hex) {
+        Some(bytes) if bytes.len() == register_size(reg) => bytes,
+        _ => return false,
+    };
+    let val = bytes.iter().rev().fold(0u16, |v, b| (v << 8) | *b as u16);
+
+    match reg {
+        0..=15 => chip8.set_register(reg, val as u8),
+        REG_I => chip8.set_index_register(val),
+        REG_PC => chip8.set_pc(val),
+        REG_SP => return chip8.set_stack_pointer(val as usize).is_ok(),
+        REG_DT => chip8.set_delay_timer(val as u8),
+        REG_ST => chip8.sound_timer = val as u8,
+        _ => return false,
+    }
+    true
+}
+
+// "addr,len" in hex
+fn parse_addr_len(args: &str) -> Option<(usize, usize)> {
+    let (addr, len) = args.split_once(',')?;
+    Some((usize::from_str_radix(addr, 16).ok()?, usize::from_str_radix(len, 16).ok()?))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect()
+}
+
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut iter = data.iter();
+    while let Some(b) = iter.next() {
+        match b {
+            b'}' => out.extend(iter.next().map(|e| e ^ 0x20)),
+            _ => out.push(*b),
+        }
+    }
+    out
+}
+
+// register layout for gdb, there's no built in CHIP-8 architecture
+fn target_xml() -> String {
+    let mut regs: Vec<String> = (0..16).map(|i| format!(r#"<reg name="v{:x}" bitsize="8" regnum="{}"/>"#, i, i)).collect();
+    regs.push(format!(r#"<reg name="i" bitsize="16" type="data_ptr" regnum="{}"/>"#, REG_I));
+    regs.push(format!(r#"<reg name="pc" bitsize="16" type="code_ptr" regnum="{}"/>"#, REG_PC));
+    regs.push(format!(r#"<reg name="sp" bitsize="8" regnum="{}"/>"#, REG_SP));
+    regs.push(format!(r#"<reg name="dt" bitsize="8" regnum="{}"/>"#, REG_DT));
+    regs.push(format!(r#"<reg name="st" bitsize="8" regnum="{}"/>"#, REG_ST));
+
+    format!(
+        r#"<?xml version="1.0"?><!DOCTYPE target SYSTEM "gdb-target.dtd"><target version="1.0"><feature name="org.chip8.core">{}</feature></target>"#,
+        regs.join("")
+    )
+}
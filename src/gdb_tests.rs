@@ -0,0 +1,188 @@
+use super::*;
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use crate::drivers::keyboard::create_key_state;
+use crate::rom_builder::RomBuilder;
+
+// a gdb server on an ephemeral port with a connected client
+struct Session {
+	server: GdbServer,
+	stream: TcpStream,
+	chip8: Chip8,
+	dbg: Debugger,
+}
+
+impl Session {
+	fn new(rom: Vec<u8>) -> Session {
+		let server = GdbServer::bind(0).unwrap();
+		let stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+		stream.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+
+		let mut chip8 = Chip8::new();
+		chip8.load_rom(rom).unwrap();
+		let mut session = Session {server, stream, chip8, dbg: Debugger::new()};
+		assert!(matches!(session.poll(), Some(GdbEvent::Connected(_))));
+		assert!(session.server.is_connected());
+		session
+	}
+
+	fn poll(&mut self) -> Option<GdbEvent> {
+		self.server.poll(&mut self.chip8, &mut self.dbg, &create_key_state())
+	}
+
+	// sends a continue and waits for the server to act on it
+	fn continue_(&mut self) {
+		self.send("c");
+		let start = Instant::now();
+		while !self.dbg.is_running() && start.elapsed() < Duration::from_secs(5) {
+			self.poll();
+		}
+		assert!(self.dbg.is_running());
+	}
+
+	// polls the server until a whole reply packet has arrived, skipping acks
+	fn receive(&mut self) -> String {
+		let start = Instant::now();
+		let mut received = Vec::new();
+		let mut buf = [0; 4096];
+
+		while start.elapsed() < Duration::from_secs(5) {
+			self.poll();
+			if let Ok(n) = self.stream.read(&mut buf) {
+				received.extend_from_slice(&buf[..n]);
+			}
+
+			let text = String::from_utf8_lossy(&received).trim_start_matches('+').to_string();
+			if let Some(hash) = text.find('#').filter(|h| text.len() >= h + 3) {
+				assert!(text.starts_with('$'), "unexpected reply {:?}", text);
+				let data = &text[1..hash];
+				let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+				assert_eq!(&text[hash + 1..hash + 3], format!("{:02x}", checksum));
+				return data.to_string();
+			}
+		}
+		panic!("no reply, got {:?}", String::from_utf8_lossy(&received));
+	}
+
+	fn send(&mut self, data: &str) {
+		let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+		write!(self.stream, "${}#{:02x}", data, checksum).unwrap();
+	}
+
+	fn request(&mut self, data: &str) -> String {
+		self.send(data);
+		self.receive()
+	}
+}
+
+#[test]
+fn test_query_and_registers() {
+	let mut s = Session::new(RomBuilder::new().ld(0xA, 0x12).ld_i(0x345).build());
+
+	assert!(s.request("qSupported:multiprocess+").contains("qXfer:features:read+"));
+	assert_eq!(s.request("?"), "S05");
+	assert!(s.request("qXfer:features:read:target.xml:0,1000").contains(r#"name="pc""#));
+	assert_eq!(s.request("vMustReplyEmpty"), "");
+
+	assert_eq!(s.request("s"), "S05");
+	assert_eq!(s.request("s"), "S05");
+
+	let regs = s.request("g");
+	assert_eq!(regs.len(), (16 + 2 + 2 + 3) * 2);
+	assert_eq!(&regs[20..22], "12");
+	// I then pc, little endian
+	assert_eq!(&regs[32..40], "45030402");
+
+	assert_eq!(s.request("P3=7f"), "OK");
+	assert_eq!(s.chip8.register(3), 0x7F);
+	assert_eq!(s.request("p11"), "0402");
+	assert_eq!(s.request("P11=0003"), "OK");
+	assert_eq!(s.chip8.pc(), 0x300);
+	assert_eq!(s.request("p15"), "E01");
+}
+
+#[test]
+fn test_memory() {
+	let mut s = Session::new(RomBuilder::new().ld(0, 1).build());
+
+	assert_eq!(s.request("m200,2"), "6001");
+	assert_eq!(s.request("M300,3:0a0b0c"), "OK");
+	assert_eq!(&s.chip8.memory()[0x300..0x303], [0x0A, 0x0B, 0x0C]);
+	assert_eq!(s.request("MFFF,2:0102"), "E01");
+	// lengths that would overflow the end address
+	assert_eq!(s.request("m200,ffffffffffffffff"), "E01");
+	assert_eq!(s.request("qXfer:features:read:target.xml:10,ffffffffffffffff"), "E01");
+	assert!(s.request("qXfer:features:read:target.xml:0,10").starts_with('m'));
+}
+
+#[test]
+fn test_breakpoint_continue_and_interrupt() {
+	let rom = RomBuilder::new().ld(0, 1).add(0, 1).jp(0x202).build();
+	let mut s = Session::new(rom);
+
+	assert_eq!(s.request("QStartNoAckMode"), "OK");
+	assert_eq!(s.request("Z0,202,2"), "OK");
+	s.continue_();
+	assert_eq!(s.chip8.pc(), 0x200);
+
+	// the stop reply only comes once the main loop has run into the breakpoint
+	assert_eq!(s.dbg.run_frame(&mut s.chip8, &create_key_state(), 10), Some(StopReason::Breakpoint(0x202)));
+	assert_eq!(s.receive(), "S05");
+	assert_eq!(s.chip8.register(0), 1);
+
+	assert_eq!(s.request("z0,202,2"), "OK");
+	s.continue_();
+	s.stream.write_all(&[0x03]).unwrap();
+	assert_eq!(s.receive(), "S02");
+	assert!(!s.dbg.is_running());
+}
+
+#[test]
+fn test_invalid_opcode_stops_with_sigill() {
+	let mut s = Session::new(RomBuilder::new().op(0xF0FF).build());
+
+	assert_eq!(s.request("s"), "S04");
+	assert_eq!(s.chip8.pc(), 0x200);
+}
+
+#[test]
+fn test_bad_checksum_is_nacked() {
+	let mut s = Session::new(RomBuilder::new().build());
+
+	s.stream.write_all(b"$g#00").unwrap();
+	s.poll();
+	let mut buf = [0; 16];
+	let start = Instant::now();
+	let mut n = 0;
+	while n == 0 && start.elapsed() < Duration::from_secs(5) {
+		s.poll();
+		n = s.stream.read(&mut buf).unwrap_or(0);
+	}
+	assert_eq!(&buf[..n], b"-");
+}
+
+#[test]
+fn test_detach_resumes() {
+	let mut s = Session::new(RomBuilder::new().build());
+
+	s.request("Z0,200,2");
+	assert_eq!(s.request("D"), "OK");
+	s.poll();
+	assert!(!s.server.is_connected());
+	assert!(s.dbg.is_running());
+	assert_eq!(s.dbg.breakpoints().count(), 0);
+}
+
+#[test]
+fn test_closing_the_connection_disconnects() {
+	let mut s = Session::new(RomBuilder::new().build());
+
+	s.stream.shutdown(std::net::Shutdown::Both).unwrap();
+	let start = Instant::now();
+	let mut event = None;
+	while event.is_none() && start.elapsed() < Duration::from_secs(5) {
+		event = s.poll();
+	}
+	assert_eq!(event, Some(GdbEvent::Disconnected));
+	assert!(!s.server.is_connected());
+}
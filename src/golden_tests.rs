@@ -0,0 +1,40 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use crate::headless::{Manifest, framebuffer_to_text, text_diff};
+
+// every *.manifest in tests/golden is run headless and the final screen is compared
+// against its expected text art or png. set CHIP8_BLESS=1 to accept the current screens
+#[test]
+fn test_golden_roms() {
+	let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+	let bless = env::var("CHIP8_BLESS").is_ok();
+
+	let mut manifests: Vec<_> = fs::read_dir(&dir).unwrap()
+		.map(|entry| entry.unwrap().path())
+		.filter(|path| path.extension().is_some_and(|ext| ext == "manifest"))
+		.collect();
+	manifests.sort();
+	assert!(!manifests.is_empty(), "no manifests found in {}", dir.display());
+
+	let mut failures = Vec::new();
+
+	for path in manifests.iter() {
+		let manifest = Manifest::load(path).unwrap();
+		let chip8 = manifest.run().unwrap();
+
+		if bless {
+			manifest.bless(&chip8).unwrap();
+			continue;
+		}
+
+		let expected = manifest.expected_text().unwrap();
+		let actual = framebuffer_to_text(&chip8.framebuffer);
+
+		if let Some(diff) = text_diff(&expected, &actual) {
+			failures.push(format!("{} (quirks: {}, {} frames): {}", manifest.name, manifest.quirks_name, manifest.frames, diff));
+		}
+	}
+
+	assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
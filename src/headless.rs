@@ -0,0 +1,292 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::chip8::{Chip8, Chip8Error, PIXEL_WIDTH};
+use crate::config::parse_key_values;
+use crate::drivers::keyboard::{KeyboardDriver, create_key_state};
+use crate::image::Image;
+use crate::palette::Palette;
+use crate::quirks::Quirks;
+use crate::romdb::sha1_hex;
+use crate::framebuffer::Framebuffer;
+
+const PIXEL_ON: char = '#';
+const PIXEL_OFF: char = '.';
+
+// describes a headless run of a ROM and what the screen should look like afterwards.
+// same "key = value" format as the config file, paths are relative to the manifest
+#[derive(Clone, Debug)]
+pub struct Manifest {
+    pub name: String,
+    pub rom: PathBuf,
+    // SHA-1 the ROM has to have, pins the release the expected screens were made with
+    pub sha1: Option<String>,
+    pub quirks_name: String,
+    pub quirks: Quirks,
+    pub frames: u32,
+    pub cycles_per_frame: u32,
+    pub seed: u64,
+    pub input: Option<PathBuf>,
+    pub expected: PathBuf,
+    // bytes written to memory after the ROM is loaded, as (address, value)
+    pub pokes: Vec<(u16, u8)>,
+    // extra pokes for a single quirk profile, applied by with_profile
+    pub profile_pokes: Vec<(String, Vec<(u16, u8)>)>,
+    // profiles a conformance run should try, all of them when empty
+    pub profiles: Vec<String>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+        let mut manifest = Manifest {
+            name: name.clone(),
+            rom: PathBuf::new(),
+            sha1: None,
+            quirks_name: String::from("default"),
+            quirks: Quirks::default(),
+            frames: 60,
+            cycles_per_frame: 10,
+            seed: 0,
+            input: None,
+            expected: dir.join(format!("{}.txt", name)),
+            pokes: Vec::new(),
+            profile_pokes: Vec::new(),
+            profiles: Vec::new(),
+        };
+
+        let err = |key: &str, val: &str| format!("{}: invalid value '{}' for '{}'", path.display(), val, key);
+
+        for (key, val) in parse_key_values(&text).map_err(|e| format!("{}: {}", path.display(), e))? {
+            match key.as_str() {
+                "rom" => manifest.rom = dir.join(&val),
+                "sha1" => {
+                    if val.len() != 40 || !val.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(err(&key, &val));
+                    }
+                    manifest.sha1 = Some(val.to_ascii_lowercase());
+                },
+                "quirks" => {
+                    manifest.quirks = Quirks::by_name(&val).ok_or_else(|| err(&key, &val))?;
+                    manifest.quirks_name = val;
+                },
+                "frames" => manifest.frames = val.parse().map_err(|_| err(&key, &val))?,
+                "cycles-per-frame" => manifest.cycles_per_frame = val.parse().map_err(|_| err(&key, &val))?,
+                "seed" => manifest.seed = val.parse().map_err(|_| err(&key, &val))?,
+                "input" => manifest.input = Some(dir.join(&val)),
+                "expected" => manifest.expected = dir.join(&val),
+                "poke" => manifest.pokes = parse_pokes(&val).ok_or_else(|| err(&key, &val))?,
+                "profiles" => {
+                    manifest.profiles = val.split(',').map(|p| p.trim().to_string()).collect();
+                    if let Some(p) = manifest.profiles.iter().find(|p| Quirks::by_name(p).is_none()) {
+                        return Err(err(&key, p));
+                    }
+                },
+                _ => match key.strip_prefix("poke.") {
+                    Some(profile) if Quirks::by_name(profile).is_some() => {
+                        let pokes = parse_pokes(&val).ok_or_else(|| err(&key, &val))?;
+                        manifest.profile_pokes.push((profile.to_string(), pokes));
+                    },
+                    _ => return Err(format!("{}: unknown key '{}'", path.display(), key)),
+                },
+            }
+        }
+
+        if manifest.rom.as_os_str().is_empty() {
+            return Err(format!("{}: missing 'rom'", path.display()));
+        }
+
+        Ok(manifest)
+    }
+
+    // the same run under another quirk profile, expecting <name>.<profile>.txt
+    pub fn with_profile(&self, profile: &str) -> Result<Manifest, String> {
+        let quirks = Quirks::by_name(profile).ok_or_else(|| format!("unknown quirk profile '{}'", profile))?;
+        let mut manifest = self.clone();
+
+        manifest.quirks = quirks;
+        manifest.quirks_name = profile.to_string();
+        manifest.expected = self.expected.with_file_name(format!("{}.{}.txt", self.name, profile));
+        for (_, pokes) in self.profile_pokes.iter().filter(|(p, _)| p == profile) {
+            manifest.pokes.extend_from_slice(pokes);
+        }
+
+        Ok(manifest)
+    }
+
+    // run the ROM to the end of the last frame
+    pub fn run(&self) -> Result<Chip8, String> {
+        let rom = fs::read(&self.rom).map_err(|e| format!("{}: {}", self.rom.display(), e))?;
+        if let Some(sha1) = &self.sha1 {
+            let actual = sha1_hex(&rom);
+            if actual != *sha1 {
+                return Err(format!("{}: sha1 is {}, the manifest pins {}", self.rom.display(), actual, sha1));
+            }
+        }
+        let input = match &self.input {
+            Some(path) => InputScript::load(path)?,
+            None => InputScript::default(),
+        };
+
+        let mut chip8 = Chip8::new();
+        chip8.quirks = self.quirks;
+        chip8.seed_rng(self.seed);
+        chip8.load_rom(rom).map_err(|e| format!("{}: {}", self.rom.display(), e))?;
+        for (addr, val) in self.pokes.iter() {
+            chip8.write_memory(*addr, &[*val]).map_err(|e| format!("{}: {}", self.name, e))?;
+        }
+
+        run_frames(&mut chip8, self.frames, self.cycles_per_frame, &input)
+            .map_err(|e| format!("{}: {}", self.rom.display(), e))?;
+        Ok(chip8)
+    }
+
+    // expected screen as text art, pngs are converted by treating every
+    // pixel that isn't the top left colour as lit
+    pub fn expected_text(&self) -> Result<String, String> {
+        if self.expects_png() {
+            let image = Image::load_png(&self.expected).map_err(|e| format!("{}: {}", self.expected.display(), e))?;
+            Ok(image_to_text(&image))
+        } else {
+            fs::read_to_string(&self.expected).map_err(|e| format!("{}: {}", self.expected.display(), e))
+        }
+    }
+
+    // overwrite the expected screen with the given chip8 screen
+    pub fn bless(&self, chip8: &Chip8) -> Result<(), String> {
+        let res = if self.expects_png() {
+            chip8.to_image(1, &Palette::default()).save_png(&self.expected)
+        } else {
+            fs::write(&self.expected, framebuffer_to_text(&chip8.framebuffer))
+        };
+
+        res.map_err(|e| format!("{}: {}", self.expected.display(), e))
+    }
+
+    fn expects_png(&self) -> bool {
+        self.expected.extension().is_some_and(|ext| ext == "png")
+    }
+}
+
+// "0x1FF:1, 0x1FE:0x20", addresses and values are hex with an optional 0x
+fn parse_pokes(val: &str) -> Option<Vec<(u16, u8)>> {
+    let hex = |s: &str| {
+        let s = s.trim();
+        s.strip_prefix("0x").unwrap_or(s).to_string()
+    };
+
+    val.split(',')
+        .map(|poke| {
+            let (addr, val) = poke.split_once(':')?;
+            let addr = u16::from_str_radix(&hex(addr), 16).ok()?;
+            let val = u8::from_str_radix(&hex(val), 16).ok()?;
+            Some((addr, val))
+        })
+        .collect()
+}
+
+// key presses by frame, one "<frame> <key> down|up" per line where key is a hex digit
+#[derive(Clone, Debug, Default)]
+pub struct InputScript {
+    events: Vec<(u32, u8, bool)>,
+}
+
+impl InputScript {
+    pub fn load(path: &Path) -> Result<InputScript, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        InputScript::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<InputScript, String> {
+        let mut events = Vec::new();
+
+        for (num, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let event = match parts[..] {
+                [frame, key, state] => {
+                    let frame = frame.parse::<u32>().ok();
+                    let key = u8::from_str_radix(key, 16).ok().filter(|k| *k < 16);
+                    let down = match state {"down" => Some(true), "up" => Some(false), _ => None};
+                    frame.zip(key).zip(down).map(|((f, k), d)| (f, k, d))
+                },
+                _ => None,
+            };
+
+            match event {
+                Some(e) => events.push(e),
+                None => return Err(format!("line {}: expected '<frame> <key> down|up', got '{}'", num + 1, line)),
+            }
+        }
+
+        events.sort_by_key(|e| e.0);
+        Ok(InputScript {events})
+    }
+}
+
+pub fn run_frames(chip8: &mut Chip8, frames: u32, cycles_per_frame: u32, input: &InputScript) -> Result<(), Chip8Error> {
+    let mut key_state = create_key_state();
+    let mut events = input.events.iter().peekable();
+
+    for frame in 0..frames {
+        while let Some((_, key, down)) = events.next_if(|e| e.0 <= frame) {
+            key_state.insert(KeyboardDriver::int_to_key(*key).unwrap(), *down);
+        }
+
+        chip8.run_frame(&key_state, cycles_per_frame)?;
+    }
+
+    Ok(())
+}
+
+pub fn framebuffer_to_text(framebuffer: &Framebuffer) -> String {
+    framebuffer.rows()
+        .map(|row| row.map(|p| if p != 0 {PIXEL_ON} else {PIXEL_OFF}).collect::<String>() + "\n")
+        .collect()
+}
+
+// images larger than the chip8 screen are treated as upscaled screenshots
+pub fn image_to_text(image: &Image) -> String {
+    let background = image.get(0, 0);
+    let scale = (image.width / PIXEL_WIDTH as u32).max(1);
+    let mut text = String::new();
+
+    for y in 0..image.height / scale {
+        for x in 0..image.width / scale {
+            text.push(if image.get(x * scale, y * scale) != background {PIXEL_ON} else {PIXEL_OFF});
+        }
+        text.push('\n');
+    }
+
+    text
+}
+
+// None when equal, otherwise expected and actual side by side with differing rows marked
+pub fn text_diff(expected: &str, actual: &str) -> Option<String> {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    if expected == actual {
+        return None;
+    }
+
+    let width = expected.iter().map(|l| l.len()).max().unwrap_or(0);
+    let mut diff = format!("  {:<width$}   actual\n", "expected", width = width);
+    let mut mismatched = 0;
+
+    for i in 0..expected.len().max(actual.len()) {
+        let e = expected.get(i).copied().unwrap_or("");
+        let a = actual.get(i).copied().unwrap_or("");
+        let marker = if e != a {'>'} else {' '};
+
+        mismatched += e.chars().zip(a.chars()).filter(|(x, y)| x != y).count() + e.len().abs_diff(a.len());
+        diff += &format!("{} {:<width$} | {}\n", marker, e, a, width = width);
+    }
+
+    Some(format!("{} pixels differ\n{}", mismatched, diff))
+}
@@ -0,0 +1,74 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use crate::palette::Rgb;
+
+#[cfg(test)]
+#[path = "./image_tests.rs"]
+mod image_tests;
+
+// 8 bit RGB image, row major
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Image {
+        Image {width, height, pixels: vec![0; (width * height * 3) as usize]}
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Rgb {
+        let i = ((y * self.width + x) * 3) as usize;
+        Rgb(self.pixels[i], self.pixels[i+1], self.pixels[i+2])
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Rgb) {
+        let i = ((y * self.width + x) * 3) as usize;
+        self.pixels[i..i+3].copy_from_slice(&[color.0, color.1, color.2]);
+    }
+
+    // fill a scale x scale block, used to upscale chip8 pixels
+    pub fn fill_block(&mut self, x: u32, y: u32, scale: u32, color: Rgb) {
+        for by in y*scale..(y+1)*scale {
+            for bx in x*scale..(x+1)*scale {
+                self.set(bx, by, color);
+            }
+        }
+    }
+
+    // 8 bit RGB or RGBA pngs, alpha is dropped
+    pub fn load_png(path: &Path) -> io::Result<Image> {
+        let decoder = png::Decoder::new(File::open(path)?);
+        let mut reader = decoder.read_info().map_err(io::Error::other)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(io::Error::other)?;
+
+        let channels = match (info.color_type, info.bit_depth) {
+            (png::ColorType::Rgb, png::BitDepth::Eight) => 3,
+            (png::ColorType::Rgba, png::BitDepth::Eight) => 4,
+            (color, depth) => return Err(io::Error::other(format!("unsupported png format {:?} {:?}", color, depth))),
+        };
+
+        let pixels = buf[..info.buffer_size()].chunks_exact(channels).flat_map(|p| [p[0], p[1], p[2]]).collect();
+        Ok(Image {width: info.width, height: info.height, pixels})
+    }
+
+    pub fn save_png(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(to_io_error)?;
+        writer.write_image_data(&self.pixels).map_err(to_io_error)?;
+
+        Ok(())
+    }
+}
+
+fn to_io_error(e: png::EncodingError) -> io::Error {
+    io::Error::other(e)
+}
@@ -0,0 +1,19 @@
+use super::*;
+use std::env;
+use std::fs;
+
+#[test]
+fn test_png_round_trip() {
+	let dir = env::temp_dir().join("chip8_image_test");
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("round_trip.png");
+
+	let mut image = Image::new(5, 3);
+	image.fill_block(1, 0, 2, Rgb(0x12, 0x34, 0x56));
+	image.set(4, 2, Rgb(0xFF, 0, 0x80));
+	image.save_png(&path).unwrap();
+
+	assert_eq!(Image::load_png(&path).unwrap(), image);
+	assert!(Image::load_png(&dir.join("missing.png")).is_err());
+}
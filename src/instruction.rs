@@ -0,0 +1,264 @@
+use std::error::Error;
+use std::fmt;
+
+#[cfg(test)]
+#[path = "./instruction_tests.rs"]
+mod instruction_tests;
+
+// instruction sets, each one a superset of the one before
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Platform {
+    #[default]
+    Chip8,
+    Schip,
+    XoChip,
+}
+
+impl Platform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::Chip8 => "CHIP-8",
+            Platform::Schip => "SCHIP",
+            Platform::XoChip => "XO-CHIP",
+        }
+    }
+}
+
+// one decoded opcode. x and y are register numbers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Cls,
+    Ret,
+    Jp(u16),
+    Call(u16),
+    SeImm {x: u8, nn: u8},
+    SneImm {x: u8, nn: u8},
+    SeReg {x: u8, y: u8},
+    LdImm {x: u8, nn: u8},
+    AddImm {x: u8, nn: u8},
+    LdReg {x: u8, y: u8},
+    Or {x: u8, y: u8},
+    And {x: u8, y: u8},
+    Xor {x: u8, y: u8},
+    AddReg {x: u8, y: u8},
+    Sub {x: u8, y: u8},
+    Shr {x: u8, y: u8},
+    Subn {x: u8, y: u8},
+    Shl {x: u8, y: u8},
+    SneReg {x: u8, y: u8},
+    LdI(u16),
+    // BNNN, the register added depends on the jump_uses_vx quirk
+    JpV0(u16),
+    Rnd {x: u8, nn: u8},
+    Drw {x: u8, y: u8, n: u8},
+    Skp {x: u8},
+    Sknp {x: u8},
+    LdVxDt {x: u8},
+    LdVxK {x: u8},
+    LdDtVx {x: u8},
+    LdStVx {x: u8},
+    AddI {x: u8},
+    LdF {x: u8},
+    Bcd {x: u8},
+    Store {x: u8},
+    Load {x: u8},
+
+    // SCHIP
+    ScrollDown(u8),
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    Low,
+    High,
+    // DXY0, a 16x16 sprite
+    DrwLarge {x: u8, y: u8},
+    LdHf {x: u8},
+    StoreFlags {x: u8},
+    LoadFlags {x: u8},
+
+    // XO-CHIP
+    ScrollUp(u8),
+    StoreRange {x: u8, y: u8},
+    LoadRange {x: u8, y: u8},
+    // F000 NNNN, the address is the word after the opcode
+    LdILong,
+    Plane(u8),
+    Audio,
+    Pitch {x: u8},
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    pub op: u16,
+    pub platform: Platform,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04X} is not a valid {} instruction", self.op, self.platform.name())
+    }
+}
+
+impl Error for DecodeError {}
+
+// what this interpreter runs
+pub fn decode(op: u16) -> Result<Instruction, DecodeError> {
+    decode_for(Platform::Chip8, op)
+}
+
+pub fn decode_for(platform: Platform, op: u16) -> Result<Instruction, DecodeError> {
+    use Instruction::*;
+
+    let x = ((op >> 8) & 0xF) as u8;
+    let y = ((op >> 4) & 0xF) as u8;
+    let n = (op & 0xF) as u8;
+    let nn = op as u8;
+    let nnn = op & 0xFFF;
+    let schip = platform >= Platform::Schip;
+    let xochip = platform == Platform::XoChip;
+
+    let instr = match (op >> 12, x, y, n) {
+        (0x0, 0, 0xE, 0) => Cls,
+        (0x0, 0, 0xE, 0xE) => Ret,
+        (0x0, 0, 0xC, n) if schip => ScrollDown(n),
+        (0x0, 0, 0xD, n) if xochip => ScrollUp(n),
+        (0x0, 0, 0xF, 0xB) if schip => ScrollRight,
+        (0x0, 0, 0xF, 0xC) if schip => ScrollLeft,
+        (0x0, 0, 0xF, 0xD) if schip => Exit,
+        (0x0, 0, 0xF, 0xE) if schip => Low,
+        (0x0, 0, 0xF, 0xF) if schip => High,
+        (0x1, _, _, _) => Jp(nnn),
+        (0x2, _, _, _) => Call(nnn),
+        (0x3, _, _, _) => SeImm {x, nn},
+        (0x4, _, _, _) => SneImm {x, nn},
+        (0x5, _, _, 2) if xochip => StoreRange {x, y},
+        (0x5, _, _, 3) if xochip => LoadRange {x, y},
+        (0x5, _, _, 0) => SeReg {x, y},
+        // the low nibble was never checked by the original interpreter
+        (0x5, _, _, _) if !xochip => SeReg {x, y},
+        (0x6, _, _, _) => LdImm {x, nn},
+        (0x7, _, _, _) => AddImm {x, nn},
+        (0x8, _, _, 0) => LdReg {x, y},
+        (0x8, _, _, 1) => Or {x, y},
+        (0x8, _, _, 2) => And {x, y},
+        (0x8, _, _, 3) => Xor {x, y},
+        (0x8, _, _, 4) => AddReg {x, y},
+        (0x8, _, _, 5) => Sub {x, y},
+        (0x8, _, _, 6) => Shr {x, y},
+        (0x8, _, _, 7) => Subn {x, y},
+        (0x8, _, _, 0xE) => Shl {x, y},
+        (0x9, _, _, 0) => SneReg {x, y},
+        (0xA, _, _, _) => LdI(nnn),
+        (0xB, _, _, _) => JpV0(nnn),
+        (0xC, _, _, _) => Rnd {x, nn},
+        (0xD, _, _, 0) if schip => DrwLarge {x, y},
+        (0xD, _, _, _) => Drw {x, y, n},
+        (0xE, _, 0x9, 0xE) => Skp {x},
+        (0xE, _, 0xA, 0x1) => Sknp {x},
+        (0xF, 0, 0, 0) if xochip => LdILong,
+        (0xF, _, 0, 1) if xochip => Plane(x),
+        (0xF, 0, 0, 2) if xochip => Audio,
+        (0xF, _, 0, 7) => LdVxDt {x},
+        (0xF, _, 0, 0xA) => LdVxK {x},
+        (0xF, _, 1, 5) => LdDtVx {x},
+        (0xF, _, 1, 8) => LdStVx {x},
+        (0xF, _, 1, 0xE) => AddI {x},
+        (0xF, _, 2, 9) => LdF {x},
+        (0xF, _, 3, 0) if schip => LdHf {x},
+        (0xF, _, 3, 3) => Bcd {x},
+        (0xF, _, 3, 0xA) if xochip => Pitch {x},
+        (0xF, _, 5, 5) => Store {x},
+        (0xF, _, 6, 5) => Load {x},
+        (0xF, _, 7, 5) if schip => StoreFlags {x},
+        (0xF, _, 8, 5) if schip => LoadFlags {x},
+        _ => return Err(DecodeError {op, platform}),
+    };
+
+    Ok(instr)
+}
+
+impl Instruction {
+    // the first platform with this instruction
+    pub fn platform(&self) -> Platform {
+        use Instruction::*;
+
+        match self {
+            ScrollDown(_) | ScrollRight | ScrollLeft | Exit | Low | High | DrwLarge {..} | LdHf {..}
+                | StoreFlags {..} | LoadFlags {..} => Platform::Schip,
+            ScrollUp(_) | StoreRange {..} | LoadRange {..} | LdILong | Plane(_) | Audio | Pitch {..} => Platform::XoChip,
+            _ => Platform::Chip8,
+        }
+    }
+
+    // in bytes, including the address word of F000 NNNN
+    pub fn size(&self) -> usize {
+        if *self == Instruction::LdILong {4} else {2}
+    }
+
+    // the skips, which may step over the following instruction
+    pub fn is_skip(&self) -> bool {
+        use Instruction::*;
+        matches!(self, SeImm {..} | SneImm {..} | SeReg {..} | SneReg {..} | Skp {..} | Sknp {..})
+    }
+}
+
+// mnemonics in the style of Cowgod's reference, e.g. "LD V1, 0x0A"
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Instruction::*;
+
+        match *self {
+            Cls => write!(f, "CLS"),
+            Ret => write!(f, "RET"),
+            Jp(nnn) => write!(f, "JP {:#05X}", nnn),
+            Call(nnn) => write!(f, "CALL {:#05X}", nnn),
+            SeImm {x, nn} => write!(f, "SE V{:X}, {:#04X}", x, nn),
+            SneImm {x, nn} => write!(f, "SNE V{:X}, {:#04X}", x, nn),
+            SeReg {x, y} => write!(f, "SE V{:X}, V{:X}", x, y),
+            LdImm {x, nn} => write!(f, "LD V{:X}, {:#04X}", x, nn),
+            AddImm {x, nn} => write!(f, "ADD V{:X}, {:#04X}", x, nn),
+            LdReg {x, y} => write!(f, "LD V{:X}, V{:X}", x, y),
+            Or {x, y} => write!(f, "OR V{:X}, V{:X}", x, y),
+            And {x, y} => write!(f, "AND V{:X}, V{:X}", x, y),
+            Xor {x, y} => write!(f, "XOR V{:X}, V{:X}", x, y),
+            AddReg {x, y} => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Sub {x, y} => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Shr {x, y} => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Subn {x, y} => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Shl {x, y} => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SneReg {x, y} => write!(f, "SNE V{:X}, V{:X}", x, y),
+            LdI(nnn) => write!(f, "LD I, {:#05X}", nnn),
+            JpV0(nnn) => write!(f, "JP V0, {:#05X}", nnn),
+            Rnd {x, nn} => write!(f, "RND V{:X}, {:#04X}", x, nn),
+            Drw {x, y, n} => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Skp {x} => write!(f, "SKP V{:X}", x),
+            Sknp {x} => write!(f, "SKNP V{:X}", x),
+            LdVxDt {x} => write!(f, "LD V{:X}, DT", x),
+            LdVxK {x} => write!(f, "LD V{:X}, K", x),
+            LdDtVx {x} => write!(f, "LD DT, V{:X}", x),
+            LdStVx {x} => write!(f, "LD ST, V{:X}", x),
+            AddI {x} => write!(f, "ADD I, V{:X}", x),
+            LdF {x} => write!(f, "LD F, V{:X}", x),
+            Bcd {x} => write!(f, "LD B, V{:X}", x),
+            Store {x} => write!(f, "LD [I], V{:X}", x),
+            Load {x} => write!(f, "LD V{:X}, [I]", x),
+            ScrollDown(n) => write!(f, "SCD {}", n),
+            ScrollRight => write!(f, "SCR"),
+            ScrollLeft => write!(f, "SCL"),
+            Exit => write!(f, "EXIT"),
+            Low => write!(f, "LOW"),
+            High => write!(f, "HIGH"),
+            DrwLarge {x, y} => write!(f, "DRW V{:X}, V{:X}, 0", x, y),
+            LdHf {x} => write!(f, "LD HF, V{:X}", x),
+            StoreFlags {x} => write!(f, "LD R, V{:X}", x),
+            LoadFlags {x} => write!(f, "LD V{:X}, R", x),
+            ScrollUp(n) => write!(f, "SCU {}", n),
+            StoreRange {x, y} => write!(f, "SAVE V{:X}-V{:X}", x, y),
+            LoadRange {x, y} => write!(f, "LOAD V{:X}-V{:X}", x, y),
+            LdILong => write!(f, "LD I, LONG"),
+            Plane(n) => write!(f, "PLANE {}", n),
+            Audio => write!(f, "AUDIO"),
+            Pitch {x} => write!(f, "PITCH V{:X}", x),
+        }
+    }
+}
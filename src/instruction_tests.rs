@@ -0,0 +1,65 @@
+use super::*;
+
+#[test]
+fn test_decode_chip8() {
+	assert_eq!(decode(0x00E0), Ok(Instruction::Cls));
+	assert_eq!(decode(0x2ABC), Ok(Instruction::Call(0xABC)));
+	assert_eq!(decode(0x8A5E), Ok(Instruction::Shl {x: 0xA, y: 5}));
+	assert_eq!(decode(0xD125), Ok(Instruction::Drw {x: 1, y: 2, n: 5}));
+	assert_eq!(decode(0xF365), Ok(Instruction::Load {x: 3}));
+	// 5XYN is accepted for any N, DXY0 draws nothing
+	assert_eq!(decode(0x5121), Ok(Instruction::SeReg {x: 1, y: 2}));
+	assert_eq!(decode(0xD120), Ok(Instruction::Drw {x: 1, y: 2, n: 0}));
+
+	for op in [0x0000, 0x00FF, 0x8008, 0x9121, 0xE19F, 0xF0FF, 0xF000] {
+		assert_eq!(decode(op), Err(DecodeError {op, platform: Platform::Chip8}), "{:04X}", op);
+	}
+}
+
+#[test]
+fn test_decode_by_platform() {
+	assert_eq!(decode_for(Platform::Schip, 0x00FF), Ok(Instruction::High));
+	assert_eq!(decode_for(Platform::Schip, 0x00C4), Ok(Instruction::ScrollDown(4)));
+	assert_eq!(decode_for(Platform::Schip, 0xD120), Ok(Instruction::DrwLarge {x: 1, y: 2}));
+	assert!(decode_for(Platform::Schip, 0x00D4).is_err());
+	assert!(decode_for(Platform::Schip, 0xF000).is_err());
+
+	assert_eq!(decode_for(Platform::XoChip, 0x00D4), Ok(Instruction::ScrollUp(4)));
+	assert_eq!(decode_for(Platform::XoChip, 0x5122), Ok(Instruction::StoreRange {x: 1, y: 2}));
+	assert_eq!(decode_for(Platform::XoChip, 0xF201), Ok(Instruction::Plane(2)));
+	assert_eq!(decode_for(Platform::XoChip, 0xF000), Ok(Instruction::LdILong));
+	assert!(decode_for(Platform::XoChip, 0x5121).is_err());
+
+	assert_eq!(Instruction::LdILong.size(), 4);
+	assert_eq!(Instruction::StoreFlags {x: 0}.platform(), Platform::Schip);
+	assert_eq!(Instruction::Cls.platform(), Platform::Chip8);
+}
+
+#[test]
+fn test_extensions_only_add_instructions() {
+	// decoding is the same on every platform except where an extension claims the opcode
+	for op in 0..=0xFFFF {
+		if let Ok(instr) = decode(op) {
+			let schip = decode_for(Platform::Schip, op).unwrap();
+			assert!(schip == instr || matches!(instr, Instruction::Drw {n: 0, ..}), "{:04X}", op);
+			assert_eq!(instr.platform(), Platform::Chip8);
+			assert!(!instr.to_string().is_empty());
+		}
+	}
+}
+
+#[test]
+fn test_mnemonics() {
+	let text = |op| decode_for(Platform::XoChip, op).unwrap().to_string();
+
+	assert_eq!(text(0x00EE), "RET");
+	assert_eq!(text(0x1208), "JP 0x208");
+	assert_eq!(text(0x3A0F), "SE VA, 0x0F");
+	assert_eq!(text(0x8126), "SHR V1, V2");
+	assert_eq!(text(0xB300), "JP V0, 0x300");
+	assert_eq!(text(0xE19E), "SKP V1");
+	assert_eq!(text(0xF50A), "LD V5, K");
+	assert_eq!(text(0xF255), "LD [I], V2");
+	assert_eq!(text(0x5243), "LOAD V2-V4");
+	assert_eq!(DecodeError {op: 0x8008, platform: Platform::Chip8}.to_string(), "8008 is not a valid CHIP-8 instruction");
+}
@@ -0,0 +1,481 @@
+use std::mem::{self, offset_of};
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{types, AbiParam, InstBuilder, MemFlags, Value};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Module};
+use crate::chip8::{Chip8, Chip8Error, Cycle, FrameHook, MEMORY_SIZE};
+use crate::drivers::keyboard::KeyState;
+use crate::instruction::{decode, Instruction};
+use crate::quirks::Quirks;
+
+#[cfg(test)]
+#[path = "./jit_tests.rs"]
+mod jit_tests;
+
+// longest run of instructions compiled into one function
+const MAX_BLOCK_LEN: usize = 32;
+// compiled code is only freed with the whole module, start a new one after this many functions
+const MAX_FUNCTIONS: usize = 4096;
+
+// what compiled code reads and writes, copied out of the Chip8 for a run of blocks
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct JitState {
+    registers: [u8; 16],
+    index_register: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+}
+
+impl JitState {
+    fn load(chip8: &Chip8) -> JitState {
+        let mut state = JitState {
+            index_register: chip8.index_register(),
+            delay_timer: chip8.delay_timer(),
+            sound_timer: chip8.sound_timer,
+            ..JitState::default()
+        };
+        for (x, reg) in state.registers.iter_mut().enumerate() {
+            *reg = chip8.register(x);
+        }
+        state
+    }
+
+    fn store(&self, chip8: &mut Chip8) {
+        for (x, reg) in self.registers.iter().enumerate() {
+            chip8.set_register(x, *reg);
+        }
+        chip8.set_index_register(self.index_register);
+        chip8.set_delay_timer(self.delay_timer);
+        chip8.sound_timer = self.sound_timer;
+    }
+}
+
+// runs compiled blocks in place of the interpreter where they fit in the frame
+struct JitHook<'a> {
+    jit: &'a mut Jit,
+    // Some while compiled code holds newer values than the Chip8
+    state: Option<JitState>,
+}
+
+impl FrameHook for JitHook<'_> {
+    type Stop = ();
+
+    fn before_step(&mut self, chip8: &mut Chip8, left: u32) -> Cycle<()> {
+        if let Some(block) = self.jit.block_at(chip8, chip8.pc() as usize) {
+            // a block never stops part way, the interpreter finishes off the frame instead
+            if block.len <= left {
+                let next = (block.func)(self.state.get_or_insert_with(|| JitState::load(chip8)));
+                chip8.set_pc(next as u16);
+                return Cycle::Ran(block.len);
+            }
+        }
+
+        if let Some(state) = self.state.take() {
+            state.store(chip8);
+        }
+        Cycle::Step
+    }
+
+    fn end_frame(&mut self, chip8: &mut Chip8) {
+        if let Some(state) = self.state.take() {
+            state.store(chip8);
+        }
+    }
+}
+
+// takes the state, returns the pc to continue at
+type BlockFn = extern "C" fn(*mut JitState) -> u32;
+
+struct Block {
+    func: BlockFn,
+    // the code it was compiled from, checked before every run in case the program overwrote it
+    bytes: Vec<u8>,
+    len: u32,
+}
+
+enum Entry {
+    Compiled(Block),
+    // the instruction here has to go through the interpreter
+    Interpret,
+}
+
+// compiles straight-line runs of register instructions to native code and runs everything else,
+// drawing, keys, calls and memory access, with the interpreter. call run_frame instead of
+// Chip8::run_frame, the results are the same
+pub struct Jit {
+    module: JITModule,
+    ctx: FunctionBuilderContext,
+    functions: usize,
+    // compiled with these, everything is thrown away when the Chip8's quirks change
+    quirks: Quirks,
+    entries: Vec<Option<Entry>>,
+}
+
+impl Jit {
+    pub fn new() -> Result<Jit, String> {
+        Ok(Jit {
+            module: new_module()?,
+            ctx: FunctionBuilderContext::new(),
+            functions: 0,
+            quirks: Quirks::default(),
+            entries: (0..MEMORY_SIZE).map(|_| None).collect(),
+        })
+    }
+
+    // number of blocks compiled to native code
+    pub fn compiled_blocks(&self) -> usize {
+        self.entries.iter().filter(|e| matches!(e, Some(Entry::Compiled(_)))).count()
+    }
+
+    pub fn run_frame(&mut self, chip8: &mut Chip8, key_state: &KeyState, cycles: u32) -> Result<(), Chip8Error> {
+        if chip8.quirks != self.quirks {
+            self.flush();
+            self.quirks = chip8.quirks;
+        }
+
+        chip8.run_frame_with(key_state, cycles, &mut JitHook {jit: self, state: None}).map(|_| ())
+    }
+
+    // the compiled block starting at pc, compiling it if it's new or its code changed
+    fn block_at(&mut self, chip8: &Chip8, pc: usize) -> Option<&Block> {
+        let stale = match self.entries.get(pc) {
+            None => return None,
+            Some(Some(Entry::Compiled(block))) => chip8.memory().get(pc..pc + block.bytes.len()) != Some(&block.bytes[..]),
+            Some(Some(Entry::Interpret)) => false,
+            Some(None) => true,
+        };
+        if stale {
+            if self.functions >= MAX_FUNCTIONS {
+                self.flush();
+            }
+            let entry = self.compile(chip8.memory(), pc);
+            self.entries[pc] = Some(entry);
+        }
+
+        match &self.entries[pc] {
+            Some(Entry::Compiled(block)) => Some(block),
+            _ => None,
+        }
+    }
+
+    fn compile(&mut self, memory: &[u8], start: usize) -> Entry {
+        let instrs = scan_block(memory, start);
+        if instrs.is_empty() {
+            return Entry::Interpret;
+        }
+
+        match self.compile_block(&instrs, start as u16) {
+            Ok(func) => {
+                self.functions += 1;
+                let end = start + instrs.len() * 2;
+                Entry::Compiled(Block {func, bytes: memory[start..end].to_vec(), len: instrs.len() as u32})
+            },
+            // the interpreter can always run it instead
+            Err(_) => Entry::Interpret,
+        }
+    }
+
+    fn compile_block(&mut self, instrs: &[Instruction], start: u16) -> Result<BlockFn, String> {
+        let mut ctx = self.module.make_context();
+        let ptr = self.module.target_config().pointer_type();
+        ctx.func.signature.params.push(AbiParam::new(ptr));
+        ctx.func.signature.returns.push(AbiParam::new(types::I32));
+
+        let mut builder = FunctionBuilder::new(&mut ctx.func, &mut self.ctx);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+
+        let state = builder.block_params(entry)[0];
+        let mut gen = Codegen::new(builder, state, self.quirks);
+        let mut next = start as i64;
+        let mut target = None;
+        for instr in instrs {
+            next += 2;
+            target = gen.instruction(*instr, next);
+        }
+        let target = target.unwrap_or_else(|| gen.builder.ins().iconst(types::I32, next));
+        gen.finish(target);
+
+        let id = self.module.declare_anonymous_function(&ctx.func.signature).map_err(|e| e.to_string())?;
+        self.module.define_function(id, &mut ctx).map_err(|e| e.to_string())?;
+        self.module.clear_context(&mut ctx);
+        self.module.finalize_definitions().map_err(|e| e.to_string())?;
+
+        let code = self.module.get_finalized_function(id);
+        // the signature declared above
+        Ok(unsafe { mem::transmute::<*const u8, BlockFn>(code) })
+    }
+
+    // drop every compiled block and the memory holding them
+    fn flush(&mut self) {
+        for entry in self.entries.iter_mut() {
+            *entry = None;
+        }
+        self.functions = 0;
+
+        // a fresh module can always be made once the first one was, the host didn't change
+        let module = mem::replace(&mut self.module, new_module().expect("native target"));
+        // nothing points into the old module now the entries are gone
+        unsafe { module.free_memory() };
+    }
+}
+
+fn new_module() -> Result<JITModule, String> {
+    let mut flags = settings::builder();
+    flags.set("opt_level", "speed").map_err(|e| e.to_string())?;
+    let isa = cranelift_native::builder()?
+        .finish(settings::Flags::new(flags))
+        .map_err(|e| e.to_string())?;
+
+    Ok(JITModule::new(JITBuilder::with_isa(isa, default_libcall_names())))
+}
+
+// instructions compiled code can run: registers, I and the timers, but nothing that touches
+// memory, the screen or the keys, or could fail
+fn compilable(instr: Instruction) -> bool {
+    use Instruction::*;
+    matches!(instr, LdImm {..} | AddImm {..} | LdReg {..} | Or {..} | And {..} | Xor {..} | AddReg {..}
+        | Sub {..} | Shr {..} | Subn {..} | Shl {..} | LdI(_) | AddI {..} | LdF {..}
+        | LdVxDt {..} | LdDtVx {..} | LdStVx {..})
+}
+
+// jumps and skips, compiled as the last instruction of a block
+fn ends_block(instr: Instruction) -> bool {
+    use Instruction::*;
+    matches!(instr, Jp(_) | JpV0(_) | SeImm {..} | SneImm {..} | SeReg {..} | SneReg {..})
+}
+
+// the instructions of the block starting at start, empty if the first has to be interpreted
+fn scan_block(memory: &[u8], start: usize) -> Vec<Instruction> {
+    let mut instrs = Vec::new();
+    let mut pc = start;
+
+    while instrs.len() < MAX_BLOCK_LEN && pc + 1 < memory.len() {
+        let op = (memory[pc] as u16) << 8 | memory[pc + 1] as u16;
+        let instr = match decode(op) {
+            Ok(instr) if compilable(instr) || ends_block(instr) => instr,
+            _ => break,
+        };
+        instrs.push(instr);
+        pc += 2;
+        if ends_block(instr) {
+            break;
+        }
+    }
+
+    instrs
+}
+
+// emits one block, keeping registers in SSA values and writing back the ones that changed
+struct Codegen<'a> {
+    builder: FunctionBuilder<'a>,
+    state: Value,
+    quirks: Quirks,
+    // V0-VF, I, DT and ST as i32s, loaded on first use
+    values: [Option<Value>; 19],
+    dirty: [bool; 19],
+}
+
+const I: usize = 16;
+const DT: usize = 17;
+const ST: usize = 18;
+const VF: usize = 0xF;
+
+impl<'a> Codegen<'a> {
+    fn new(builder: FunctionBuilder<'a>, state: Value, quirks: Quirks) -> Codegen<'a> {
+        Codegen {builder, state, quirks, values: [None; 19], dirty: [false; 19]}
+    }
+
+    fn slot(slot: usize) -> (types::Type, i32) {
+        match slot {
+            I => (types::I16, offset_of!(JitState, index_register) as i32),
+            DT => (types::I8, offset_of!(JitState, delay_timer) as i32),
+            ST => (types::I8, offset_of!(JitState, sound_timer) as i32),
+            x => (types::I8, (offset_of!(JitState, registers) + x) as i32),
+        }
+    }
+
+    fn get(&mut self, slot: usize) -> Value {
+        if let Some(val) = self.values[slot] {
+            return val;
+        }
+
+        let (ty, offset) = Codegen::slot(slot);
+        let val = self.builder.ins().load(ty, MemFlags::trusted(), self.state, offset);
+        let val = self.builder.ins().uextend(types::I32, val);
+        self.values[slot] = Some(val);
+        val
+    }
+
+    fn set(&mut self, slot: usize, val: Value) {
+        self.values[slot] = Some(val);
+        self.dirty[slot] = true;
+    }
+
+    fn reg(&mut self, x: u8) -> Value {
+        self.get(x as usize)
+    }
+
+    fn set_reg(&mut self, x: u8, val: Value) {
+        self.set(x as usize, val);
+    }
+
+    fn imm(&mut self, val: i64) -> Value {
+        self.builder.ins().iconst(types::I32, val)
+    }
+
+    fn byte(&mut self, val: Value) -> Value {
+        self.builder.ins().band_imm(val, 0xFF)
+    }
+
+    fn shift_source(&mut self, x: u8, y: u8) -> Value {
+        if self.quirks.shift_uses_vy {self.reg(y)} else {self.reg(x)}
+    }
+
+    // VX and then VF, so the flag wins when X is F
+    fn set_reg_and_flag(&mut self, x: u8, val: Value, flag: Value) {
+        self.set_reg(x, val);
+        self.set(VF, flag);
+    }
+
+    fn logic_vf_reset(&mut self) {
+        if self.quirks.vf_reset {
+            let zero = self.imm(0);
+            self.set(VF, zero);
+        }
+    }
+
+    // pc after a skip: past the next instruction if the condition holds
+    fn skip(&mut self, cond: Value, next: i64) -> Value {
+        let skipped = self.imm(next + 2);
+        let not_skipped = self.imm(next);
+        self.builder.ins().select(cond, skipped, not_skipped)
+    }
+
+    // emit an instruction, next is the address after it. returns the new pc for jumps and skips
+    fn instruction(&mut self, instr: Instruction, next: i64) -> Option<Value> {
+        use Instruction::*;
+
+        match instr {
+            LdImm {x, nn} => {
+                let val = self.imm(nn as i64);
+                self.set_reg(x, val);
+            },
+            AddImm {x, nn} => {
+                let vx = self.reg(x);
+                let sum = self.builder.ins().iadd_imm(vx, nn as i64);
+                let val = self.byte(sum);
+                self.set_reg(x, val);
+            },
+            LdReg {x, y} => {
+                let vy = self.reg(y);
+                self.set_reg(x, vy);
+            },
+            Or {x, y} | And {x, y} | Xor {x, y} => {
+                let (vx, vy) = (self.reg(x), self.reg(y));
+                let val = match instr {
+                    Or {..} => self.builder.ins().bor(vx, vy),
+                    And {..} => self.builder.ins().band(vx, vy),
+                    _ => self.builder.ins().bxor(vx, vy),
+                };
+                self.set_reg(x, val);
+                self.logic_vf_reset();
+            },
+            AddReg {x, y} => {
+                let (vx, vy) = (self.reg(x), self.reg(y));
+                let sum = self.builder.ins().iadd(vx, vy);
+                let val = self.byte(sum);
+                let carry = self.builder.ins().ushr_imm(sum, 8);
+                self.set_reg_and_flag(x, val, carry);
+            },
+            Sub {x, y} | Subn {x, y} => {
+                let (vx, vy) = (self.reg(x), self.reg(y));
+                let (a, b) = if matches!(instr, Sub {..}) {(vx, vy)} else {(vy, vx)};
+                let diff = self.builder.ins().isub(a, b);
+                let val = self.byte(diff);
+                let no_borrow = self.builder.ins().icmp(IntCC::UnsignedGreaterThanOrEqual, a, b);
+                let flag = self.builder.ins().uextend(types::I32, no_borrow);
+                self.set_reg_and_flag(x, val, flag);
+            },
+            Shr {x, y} => {
+                let src = self.shift_source(x, y);
+                let val = self.builder.ins().ushr_imm(src, 1);
+                let flag = self.builder.ins().band_imm(src, 1);
+                self.set_reg_and_flag(x, val, flag);
+            },
+            Shl {x, y} => {
+                let src = self.shift_source(x, y);
+                let shifted = self.builder.ins().ishl_imm(src, 1);
+                let val = self.byte(shifted);
+                let flag = self.builder.ins().ushr_imm(src, 7);
+                self.set_reg_and_flag(x, val, flag);
+            },
+            LdI(nnn) => {
+                let val = self.imm(nnn as i64);
+                self.set(I, val);
+            },
+            AddI {x} => {
+                let (i, vx) = (self.get(I), self.reg(x));
+                let sum = self.builder.ins().iadd(i, vx);
+                let val = self.builder.ins().band_imm(sum, 0xFFFF);
+                self.set(I, val);
+            },
+            LdF {x} => {
+                let vx = self.reg(x);
+                let digit = self.builder.ins().band_imm(vx, 0xF);
+                let val = self.builder.ins().imul_imm(digit, 5);
+                self.set(I, val);
+            },
+            LdVxDt {x} => {
+                let dt = self.get(DT);
+                self.set_reg(x, dt);
+            },
+            LdDtVx {x} => {
+                let vx = self.reg(x);
+                self.set(DT, vx);
+            },
+            LdStVx {x} => {
+                let vx = self.reg(x);
+                self.set(ST, vx);
+            },
+            Jp(nnn) => return Some(self.imm(nnn as i64)),
+            JpV0(nnn) => {
+                let offset = if self.quirks.jump_uses_vx {self.reg((nnn >> 8) as u8)} else {self.reg(0)};
+                return Some(self.builder.ins().iadd_imm(offset, nnn as i64));
+            },
+            SeImm {x, nn} | SneImm {x, nn} => {
+                let vx = self.reg(x);
+                let cc = if matches!(instr, SeImm {..}) {IntCC::Equal} else {IntCC::NotEqual};
+                let cond = self.builder.ins().icmp_imm(cc, vx, nn as i64);
+                return Some(self.skip(cond, next));
+            },
+            SeReg {x, y} | SneReg {x, y} => {
+                let (vx, vy) = (self.reg(x), self.reg(y));
+                let cc = if matches!(instr, SeReg {..}) {IntCC::Equal} else {IntCC::NotEqual};
+                let cond = self.builder.ins().icmp(cc, vx, vy);
+                return Some(self.skip(cond, next));
+            },
+            _ => unreachable!("{} isn't compiled", instr),
+        }
+
+        None
+    }
+
+    // write back what changed and return the next pc
+    fn finish(mut self, pc: Value) {
+        for slot in 0..self.values.len() {
+            if let (Some(val), true) = (self.values[slot], self.dirty[slot]) {
+                let (ty, offset) = Codegen::slot(slot);
+                let val = self.builder.ins().ireduce(ty, val);
+                self.builder.ins().store(MemFlags::trusted(), val, self.state, offset);
+            }
+        }
+        self.builder.ins().return_(&[pc]);
+        self.builder.finalize();
+    }
+}
@@ -0,0 +1,163 @@
+use super::*;
+use crate::drivers::keyboard::create_key_state;
+use crate::rom_builder::{chip8_with_rom, RomBuilder};
+use proptest::prelude::*;
+
+const CYCLES: u32 = 200;
+
+fn chip8_with_quirks(rom: &[u8], quirks: Quirks) -> Chip8 {
+	let mut chip8 = chip8_with_rom(rom);
+	chip8.quirks = quirks;
+	chip8
+}
+
+// everything the program can observe, to compare the two machines
+#[derive(PartialEq)]
+struct MachineState {
+	registers: Vec<u8>,
+	pc: u16,
+	index_register: u16,
+	timers: (u8, u8),
+	stack: Vec<u16>,
+	memory: Vec<u8>,
+	pixels: Vec<bool>,
+}
+
+fn machine_state(chip8: &Chip8) -> MachineState {
+	MachineState {
+		registers: (0..16).map(|x| chip8.register(x)).collect(),
+		pc: chip8.pc(),
+		index_register: chip8.index_register(),
+		timers: (chip8.delay_timer(), chip8.sound_timer),
+		stack: chip8.stack().to_vec(),
+		memory: chip8.memory().to_vec(),
+		pixels: (0..32).flat_map(|y| (0..64).map(move |x| (x, y))).map(|(x, y)| chip8.pixel(x, y)).collect(),
+	}
+}
+
+// run the ROM frame by frame on the interpreter and the JIT, they must agree after every frame
+fn assert_same_as_interpreter(rom: &[u8], quirks: Quirks, frames: usize) -> Result<(), TestCaseError> {
+	let key_state = create_key_state();
+	let mut interpreted = chip8_with_quirks(rom, quirks);
+	let mut compiled = chip8_with_quirks(rom, quirks);
+	let mut jit = Jit::new().unwrap();
+
+	for frame in 0..frames {
+		let expected = interpreted.run_frame(&key_state, CYCLES);
+		let actual = jit.run_frame(&mut compiled, &key_state, CYCLES);
+
+		prop_assert_eq!(actual, expected, "frame {}", frame);
+		prop_assert!(machine_state(&compiled) == machine_state(&interpreted), "state differs after frame {}", frame);
+		if expected.is_err() {
+			break;
+		}
+	}
+
+	Ok(())
+}
+
+// opcodes with jumps and calls kept inside the ROM so most programs run for a while
+fn program_strategy() -> impl Strategy<Value = Vec<u8>> {
+	prop::collection::vec(any::<u16>(), 1..48).prop_map(|ops| {
+		let len = ops.len() as u16;
+		ops.iter().flat_map(|&op| {
+			let op = match op >> 12 {
+				0x1 | 0x2 | 0xB => (op & 0xF000) | (0x200 + (op % len) * 2),
+				_ => op,
+			};
+			op.to_be_bytes()
+		}).collect()
+	})
+}
+
+fn quirks_strategy() -> impl Strategy<Value = Quirks> {
+	prop::sample::select(Quirks::profile_names()).prop_map(|name| Quirks::by_name(name).unwrap())
+}
+
+proptest! {
+	#[test]
+	fn prop_jit_matches_interpreter(rom in program_strategy(), quirks in quirks_strategy()) {
+		assert_same_as_interpreter(&rom, quirks, 4)?;
+	}
+}
+
+#[test]
+fn test_compiles_arithmetic_loop() {
+	let rom = RomBuilder::new()
+		.ld(0, 1).ld(1, 2)
+		.add(0, 3).add_reg(1, 0).xor(2, 1).shr(3, 2).sub(4, 1).subn(5, 0).shl(6, 5)
+		.ld_i(0x300).add_i(1).ld_font(0).ld_dt(0).ld_from_dt(7).ld_st(1)
+		.se(0, 0xFF).jp(0x204)
+		.jp(0x200)
+		.build();
+
+	let mut chip8 = chip8_with_quirks(&rom, Quirks::default());
+	let mut jit = Jit::new().unwrap();
+	jit.run_frame(&mut chip8, &create_key_state(), 1000).unwrap();
+
+	assert!(jit.compiled_blocks() > 0);
+	assert_same_as_interpreter(&rom, Quirks::default(), 10).unwrap();
+	assert_same_as_interpreter(&rom, Quirks::by_name("chip8").unwrap(), 10).unwrap();
+}
+
+#[test]
+fn test_self_modifying_code_is_recompiled() {
+	// the loop body at 0x206 starts as V0 += 1, FX55 turns it into V0 += 2 after the first pass
+	let rom = RomBuilder::new()
+		.ld(1, 0x70).ld(2, 0x02)
+		.ld_i(0x206)
+		.add(0, 1).add(3, 1)
+		.ld_reg(4, 0).ld_reg(0, 1).ld_reg(1, 2).store(1)
+		.ld_reg(1, 0).ld_reg(0, 4)
+		.jp(0x206)
+		.build();
+
+	let mut chip8 = chip8_with_quirks(&rom, Quirks::default());
+	let mut jit = Jit::new().unwrap();
+	jit.run_frame(&mut chip8, &create_key_state(), 3 + 9 * 3).unwrap();
+
+	// one pass adding 1 and two adding 2
+	assert_eq!(chip8.register(0), 5);
+	assert_eq!(chip8.register(3), 3);
+	assert_same_as_interpreter(&rom, Quirks::default(), 5).unwrap();
+}
+
+#[test]
+fn test_code_written_by_a_debugger_is_recompiled() {
+	let rom = RomBuilder::new().add(0, 1).jp(0x200).build();
+	let key_state = create_key_state();
+	let mut chip8 = chip8_with_quirks(&rom, Quirks::default());
+	let mut jit = Jit::new().unwrap();
+
+	jit.run_frame(&mut chip8, &key_state, 10).unwrap();
+	assert_eq!(chip8.register(0), 5);
+
+	chip8.write_memory(0x200, &[0x70, 0x10]).unwrap();
+	jit.run_frame(&mut chip8, &key_state, 10).unwrap();
+	assert_eq!(chip8.register(0), 5 + 5 * 0x10);
+}
+
+#[test]
+fn test_stops_after_a_draw_with_display_wait() {
+	let rom = RomBuilder::new().add(0, 1).add(1, 1).drw(0, 1, 1).jp(0x200).build();
+	let mut chip8 = chip8_with_quirks(&rom, Quirks::by_name("chip8").unwrap());
+	let mut jit = Jit::new().unwrap();
+
+	jit.run_frame(&mut chip8, &create_key_state(), 1000).unwrap();
+
+	assert_eq!(chip8.pc(), 0x206);
+	assert_same_as_interpreter(&rom, Quirks::by_name("chip8").unwrap(), 10).unwrap();
+}
+
+#[test]
+fn test_errors_leave_the_same_state() {
+	let rom = RomBuilder::new().ld(0, 1).op(0xFFFF).build();
+	let mut chip8 = chip8_with_quirks(&rom, Quirks::default());
+	let mut jit = Jit::new().unwrap();
+
+	let err = jit.run_frame(&mut chip8, &create_key_state(), 10).unwrap_err();
+
+	assert_eq!(err, Chip8Error::InvalidOpcode {pc: 0x202, op: 0xFFFF});
+	assert_eq!(chip8.register(0), 1);
+	assert_eq!(chip8.pc(), 0x202);
+}
@@ -0,0 +1,29 @@
+pub mod drivers;
+pub mod chip8;
+pub mod framebuffer;
+pub mod instruction;
+pub mod utils;
+pub mod palette;
+pub mod config;
+pub mod screen_render;
+pub mod image;
+pub mod screenshot;
+pub mod recorder;
+pub mod quirks;
+pub mod headless;
+pub mod conformance;
+pub mod rom_builder;
+pub mod romdb;
+pub mod analysis;
+pub mod debug;
+pub mod gdb;
+pub mod source_map;
+pub mod dap;
+pub mod frontend;
+pub mod recompiler;
+pub mod script;
+pub mod cheats;
+pub mod run_ahead;
+pub mod scheduler;
+#[cfg(feature = "jit")]
+pub mod jit;
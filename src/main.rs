@@ -1,75 +1,208 @@
-use std::path::Path;
 use std::fs;
 use std::env;
-use sdl2::pixels::Color;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::Ordering;
+
+use chip8::chip8::MAX_ROM_SIZE;
+use chip8::config::Config;
+use chip8::romdb::{RomDb, RomEntry, sha1_hex};
+use chip8::analysis::{analyze, Region};
+use chip8::debug::Debugger;
+use chip8::gdb::GdbServer;
+use chip8::dap::DapSession;
+use chip8::frontend::{self, DebugServer, Interpreter, Runner, STDOUT_IS_DAP};
+use chip8::recompiler;
+use chip8::script::Script;
 
-pub mod drivers;
-pub mod chip8;
-pub mod utils;
-
-use drivers::keyboard::KeyboardDriver;
-use drivers::display::DisplayDriver;
-use drivers::sound::SoundDriver;
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).is_some_and(|a| a == "info") {
+        return info(&args[1..]);
+    }
+    if args.get(1).is_some_and(|a| a == "dap") {
+        return dap(&args[1..]);
+    }
+    if args.get(1).is_some_and(|a| a == "recompile") {
+        return recompile(&args[1..]);
+    }
 
-use chip8::Chip8;
+    let config = Config::from_args(&args).unwrap_or_else(|e| exit_with_error(e));
+    let (rom, entry) = read_rom(&config, &args[0]);
+    if let Some(e) = entry.as_ref() {
+        println!("found '{}' in the ROM database", e.title);
+    }
+    for warning in analyze(&rom).warnings.iter() {
+        eprintln!("warning: {:#05X}: {}", warning.addr, warning.message);
+    }
 
-const PIXEL_WIDTH: u32 = 32;
-const PIXEL_HEIGHT: u32 = 64;
-const DISPLAY_SCALE: u32 = 10;
+    // per ROM settings from the database, the config file and command line still win
+    let (config, warnings) = Config::for_rom(&args, &sha1_hex(&rom), entry.as_ref()).unwrap_or_else(|e| exit_with_error(e));
+    for warning in warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
 
-fn main() {
-    let sdl = sdl2::init().unwrap();
+    // with --gdb the program starts paused and runs under the debugger
+    let debugger = config.gdb_port.map(|port| {
+        let server = GdbServer::bind(port).unwrap_or_else(|e| exit_with_error(format!("gdb port {}: {}", port, e)));
+        println!("waiting for gdb on {}", server.local_addr().unwrap());
+        (DebugServer::Gdb(server), Debugger::new())
+    });
+
+    // a script drives the frames in place of the plain interpreter
+    let mut runner: Box<dyn Runner> = match &config.script {
+        Some(path) => Box::new(Script::load(path, &config).unwrap_or_else(|e| exit_with_error(e))),
+        None => Box::new(Interpreter),
+    };
+
+    if let Err(e) = frontend::run(rom, config, debugger, runner.as_mut()) {
+        exit_with_error(e);
+    }
+}
 
-    // init drivers
-    let mut disp = DisplayDriver::new(&sdl, DISPLAY_SCALE, PIXEL_WIDTH, PIXEL_HEIGHT);
-    let mut kb = KeyboardDriver::new(&sdl);
-    let mut sound = SoundDriver::new(&sdl);
+// chip8 dap [OPTIONS]: a debug adapter on stdin/stdout, the editor's launch request names the ROM
+fn dap(args: &[String]) {
+    STDOUT_IS_DAP.store(true, Ordering::Relaxed);
 
-    // init chip8 VM
-    let mut chip8 = Chip8::new();
+    let mut session = DapSession::new(io::stdin(), io::stdout());
+    let launch = session.wait_for_launch().unwrap_or_else(|e| exit_with_error(e));
 
-    // load rom
-    let args: Vec<String> = env::args().collect();
-    let rom_fp: &Path = Path::new(&args[1]);
-    let rom = fs::read(rom_fp).unwrap();
-    chip8.load_rom(rom);
+    let mut args = args.to_vec();
+    args.push(launch.program.to_string_lossy().to_string());
+    let config = Config::from_args(&args).unwrap_or_else(|e| exit_with_error(e));
+    let (rom, entry) = read_rom(&config, "chip8");
+    let (config, warnings) = Config::for_rom(&args, &sha1_hex(&rom), entry.as_ref()).unwrap_or_else(|e| exit_with_error(e));
+    for warning in warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
 
-    'main: loop {
+    if let Err(e) = frontend::run(rom, config, Some((DebugServer::Dap(session), Debugger::new())), &mut Interpreter) {
+        exit_with_error(e);
+    }
+}
 
-        kb.update();
+// chip8 recompile <rom> [--out PATH]: translate a ROM into a Rust program built on this crate
+fn recompile(args: &[String]) {
+    let mut args = args.to_vec();
+    let out = args.iter().position(|a| a == "--out").map(|i| {
+        let path = args.get(i + 1).map(PathBuf::from).unwrap_or_else(|| exit_with_error("--out needs a path"));
+        args.drain(i..i + 2);
+        path
+    });
+
+    let config = Config::from_args(&args).unwrap_or_else(|e| exit_with_error(e));
+    let (rom, _) = read_rom(&config, "chip8");
+    let rom_path = config.rom_path.unwrap();
+    let name = rom_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let out = out.unwrap_or_else(|| PathBuf::from(format!("{}.rs", name)));
+
+    let source = recompiler::recompile(&rom, &rom_path.file_name().unwrap_or_default().to_string_lossy());
+    fs::write(&out, source).unwrap_or_else(|e| exit_with_error(format!("{}: {}", out.display(), e)));
+    println!("wrote {}, copy it to examples/ and `cargo run --release --example {}` to run it", out.display(),
+        out.file_stem().unwrap_or_default().to_string_lossy());
+}
 
-        if kb.exit_requested {
-            break 'main;
-        }
+// the ROM named on the command line and its database entry, if any
+fn read_rom(config: &Config, program: &str) -> (Vec<u8>, Option<RomEntry>) {
+    let rom_fp = config.rom_path.clone().unwrap_or_else(|| {
+        eprintln!("usage: {} [info|recompile] <PATH TO ROM> [OPTIONS] or {} dap [OPTIONS], see README.md for the list of options", program, program);
+        process::exit(1);
+    });
+    let rom = fs::read(&rom_fp).unwrap_or_else(|e| exit_with_error(format!("{}: {}", rom_fp.display(), e)));
+
+    let romdb = match &config.rom_database {
+        Some(dir) => RomDb::load_dir(dir).unwrap_or_else(|e| exit_with_error(e)),
+        None => RomDb::embedded(),
+    };
+    let entry = romdb.lookup(&rom);
+
+    (rom, entry)
+}
 
-        chip8.exec_cycle(&kb.key_state);
+// chip8 info <rom> [--json]: what a ROM is and what it needs, without running it
+fn info(args: &[String]) {
+    let json = args.iter().any(|a| a == "--json");
+    let args: Vec<String> = args.iter().filter(|a| *a != "--json").cloned().collect();
+
+    let config = Config::from_args(&args).unwrap_or_else(|e| exit_with_error(e));
+    let (rom, entry) = read_rom(&config, "chip8");
+    let analysis = analyze(&rom);
+
+    if json {
+        let mut out = analysis.to_json();
+        out["sha1"] = sha1_hex(&rom).into();
+        out["title"] = entry.as_ref().map(|e| e.title.clone()).into();
+        out["platforms"] = entry.as_ref().map(|e| e.platforms.clone()).unwrap_or_default().into();
+        println!("{}", serde_json::to_string_pretty(&out).unwrap());
+        return;
+    }
 
-        if !chip8.draw_flag {continue;}
+    let fits = match MAX_ROM_SIZE.checked_sub(rom.len()) {
+        Some(free) => format!("fits, {} bytes free", free),
+        None => format!("{} bytes too large for the {} byte program area", rom.len() - MAX_ROM_SIZE, MAX_ROM_SIZE),
+    };
+    println!("size        {} bytes ({})", rom.len(), fits);
+    println!("sha1        {}", sha1_hex(&rom));
+
+    match &entry {
+        Some(e) => {
+            let mut title = e.title.clone();
+            if !e.authors.is_empty() || e.release.is_some() {
+                let by: Vec<String> = e.authors.iter().cloned().chain(e.release.clone()).collect();
+                title += &format!(" ({})", by.join(", "));
+            }
+            println!("title       {}", title);
+            if let Some(description) = &e.description {
+                println!("            {}", description);
+            }
+            println!("platform    {}", e.platform_name.clone().unwrap_or_else(|| e.platforms.join(", ")));
+            if let Some(tickrate) = e.tickrate {
+                println!("tickrate    {} instructions per frame", tickrate);
+            }
+        },
+        None => println!("title       not in the ROM database"),
+    }
 
-        // draw display memory to screen
-        draw_chip8_memory_to_display(&chip8, &mut disp);
+    let extensions: Vec<String> = analysis.extensions_used().iter().map(|ext| {
+        let found: Vec<String> = analysis.extensions.iter()
+            .filter(|(_, _, e)| e == ext)
+            .map(|(addr, op, _)| format!("{:04X} at {:#05X}", op, addr))
+            .collect();
+        format!("{} ({})", ext.name(), found.join(", "))
+    }).collect();
+    println!("extensions  {}", if extensions.is_empty() {String::from("none")} else {extensions.join("; ")});
+
+    for (i, (region, start, end)) in analysis.regions().iter().enumerate() {
+        let label = if i == 0 {"regions"} else {""};
+        let kind = match region {Region::Code => "code", Region::Data => "data"};
+        println!("{:<12}{:#05X}-{:#05X}  {} ({} bytes)", label, start, end, kind, end - start + 1);
+    }
+    if !analysis.indirect_jumps.is_empty() {
+        let jumps: Vec<String> = analysis.indirect_jumps.iter().map(|a| format!("{:#05X}", a)).collect();
+        println!("            code reached through BNNN at {} isn't followed", jumps.join(", "));
+    }
 
-        // handle sound
-        if chip8.sound_timer > 0 && !sound.on{
-            sound.resume();
-        } else if chip8.sound_timer == 0 && sound.on {
-            sound.pause();
-        }
+    let mut keys: Vec<String> = analysis.keys.iter().map(|k| format!("{:X}", k)).collect();
+    if analysis.dynamic_keys {
+        keys.push(String::from("others chosen at run time"));
+    }
+    if analysis.waits_for_key {
+        keys.push(String::from("any key (FX0A)"));
+    }
+    println!("keys        {}", if keys.is_empty() {String::from("none")} else {keys.join(", ")});
+    if let Some(e) = entry.as_ref().filter(|e| !e.keys.is_empty()) {
+        let bindings: Vec<String> = e.keys.iter().map(|(button, key)| format!("{} {:X}", button, key)).collect();
+        println!("buttons     {}", bindings.join(", "));
+    }
 
-        disp.update_display();
+    for (i, warning) in analysis.warnings.iter().enumerate() {
+        let label = if i == 0 {"warnings"} else {""};
+        println!("{:<12}{:#05X}  {}", label, warning.addr, warning.message);
     }
 }
 
-fn draw_chip8_memory_to_display(chip8: &Chip8, disp: &mut DisplayDriver) {
-        for (x, row) in chip8.display_memory.iter().enumerate() {
-            for (y, col) in row.iter().enumerate() {
-
-                let color: Color = match col {
-                    1 => Color::WHITE,
-                    _ => Color::BLACK,
-                };
-
-                disp.draw_pixel(y as i32, x as i32, color);
-            }
-        }
-}
\ No newline at end of file
+fn exit_with_error<E: std::fmt::Display>(e: E) -> ! {
+    eprintln!("error: {}", e);
+    process::exit(1);
+}
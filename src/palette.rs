@@ -0,0 +1,106 @@
+#[cfg(test)]
+#[path = "./palette_tests.rs"]
+mod palette_tests;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    // parse "#RRGGBB" or "RRGGBB"
+    pub fn from_hex(hex: &str) -> Option<Rgb> {
+        let hex = hex.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i+2], 16).ok();
+        Some(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    // linear blend towards other, t=0 is self and t=1 is other
+    pub fn blend(self, other: Rgb, t: f32) -> Rgb {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Rgb(mix(self.0, other.0), mix(self.1, other.1), mix(self.2, other.2))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    // index 0 is the colour of an unlit pixel, higher indices are the
+    // pixel values produced by combining bitplanes
+    pub colors: Vec<Rgb>,
+}
+
+const PRESETS: [(&str, &[u32]); 7] = [
+    ("mono",    &[0x000000, 0xFFFFFF]),
+    ("green",   &[0x0A1A0A, 0x33FF66]),
+    ("amber",   &[0x1A1000, 0xFFB000]),
+    ("lcd",     &[0x8BAC0F, 0x0F380F]),
+    ("inverse", &[0xFFFFFF, 0x000000]),
+    ("octo",    &[0x996600, 0xFFCC00, 0xFF6600, 0x662200]),
+    ("cga16",   &[
+        0x000000, 0xFFFFFF, 0xAA0000, 0x00AA00, 0x0000AA, 0xAA5500, 0x00AAAA, 0xAA00AA,
+        0x555555, 0xAAAAAA, 0xFF5555, 0x55FF55, 0x5555FF, 0xFFFF55, 0x55FFFF, 0xFF55FF,
+    ]),
+];
+
+impl Palette {
+    pub fn by_name(name: &str) -> Option<Palette> {
+        PRESETS.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(n, colors)| Palette {
+                name: n.to_string(),
+                colors: colors.iter().map(|c| Rgb((c >> 16) as u8, (c >> 8) as u8, *c as u8)).collect(),
+            })
+    }
+
+    pub fn preset_names() -> Vec<&'static str> {
+        PRESETS.iter().map(|(n, _)| *n).collect()
+    }
+
+    // comma separated hex colours, e.g. "#000000,#33FF66"
+    pub fn from_hex_list(list: &str) -> Option<Palette> {
+        let colors = list.split(',').map(Rgb::from_hex).collect::<Option<Vec<Rgb>>>()?;
+        if colors.len() < 2 {
+            return None;
+        }
+
+        Some(Palette {name: String::from("custom"), colors})
+    }
+
+    // next preset in the list, used when cycling palettes at runtime
+    pub fn next(&self) -> Palette {
+        let pos = PRESETS.iter().position(|(n, _)| *n == self.name);
+        let next = match pos {
+            Some(i) => (i + 1) % PRESETS.len(),
+            None => 0,
+        };
+
+        Palette::by_name(PRESETS[next].0).unwrap()
+    }
+
+    pub fn background(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> Rgb {
+        self.colors[1]
+    }
+
+    // colour for a pixel value, values past the end of a small palette
+    // fall back to the foreground colour
+    pub fn color(&self, val: u32) -> Rgb {
+        match self.colors.get(val as usize) {
+            Some(c) => *c,
+            None => self.foreground(),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::by_name("mono").unwrap()
+    }
+}
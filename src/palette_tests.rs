@@ -0,0 +1,39 @@
+use super::*;
+
+#[test]
+fn test_rgb_from_hex() {
+	assert_eq!(Rgb::from_hex("#33FF66"), Some(Rgb(0x33, 0xFF, 0x66)));
+	assert_eq!(Rgb::from_hex("0a1b2c"), Some(Rgb(0x0A, 0x1B, 0x2C)));
+	assert_eq!(Rgb::from_hex("#33FF6"), None);
+	assert_eq!(Rgb::from_hex("#GGGGGG"), None);
+}
+
+#[test]
+fn test_palette_from_hex_list() {
+	let palette = Palette::from_hex_list("#000000, #FFB000").unwrap();
+
+	assert_eq!(palette.background(), Rgb(0, 0, 0));
+	assert_eq!(palette.foreground(), Rgb(0xFF, 0xB0, 0));
+	assert!(Palette::from_hex_list("#000000").is_none());
+}
+
+#[test]
+fn test_palette_color_falls_back_to_foreground() {
+	let palette = Palette::by_name("green").unwrap();
+
+	assert_eq!(palette.color(0), palette.background());
+	assert_eq!(palette.color(3), palette.foreground());
+}
+
+#[test]
+fn test_palette_cycle() {
+	let names = Palette::preset_names();
+	let mut palette = Palette::default();
+
+	for _ in 0..names.len() {
+		palette = palette.next();
+	}
+
+	assert_eq!(palette, Palette::default());
+	assert_eq!(Palette::from_hex_list("#000000,#FFFFFF").unwrap().next().name, names[0]);
+}
@@ -0,0 +1,48 @@
+// behaviours that differ between CHIP-8 interpreters, ROMs written for one
+// interpreter often rely on its particular set
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quirks {
+    pub vf_reset: bool,         // 8XY1, 8XY2 and 8XY3 reset VF to 0
+    pub memory_increment: bool, // FX55 and FX65 leave I pointing past the last register
+    pub shift_uses_vy: bool,    // 8XY6 and 8XYE shift VY into VX instead of shifting VX in place
+    pub jump_uses_vx: bool,     // BXNN jumps to XNN + VX instead of NNN + V0
+    pub wrap_sprites: bool,     // sprites wrap around the screen edges instead of being clipped
+    pub display_wait: bool,     // DXYN waits for the next frame, at most one sprite drawn per frame
+}
+
+const PROFILES: [(&str, Quirks); 5] = [
+    // what this interpreter has always done
+    ("default", Quirks {
+        vf_reset: false, memory_increment: false, shift_uses_vy: false,
+        jump_uses_vx: false, wrap_sprites: false, display_wait: false,
+    }),
+    // original COSMAC VIP interpreter
+    ("chip8", Quirks {
+        vf_reset: true, memory_increment: true, shift_uses_vy: true,
+        jump_uses_vx: false, wrap_sprites: false, display_wait: true,
+    }),
+    // CHIP-8 as implemented by most modern interpreters
+    ("modern", Quirks {
+        vf_reset: false, memory_increment: true, shift_uses_vy: true,
+        jump_uses_vx: false, wrap_sprites: false, display_wait: false,
+    }),
+    // SUPER-CHIP 1.1
+    ("schip", Quirks {
+        vf_reset: false, memory_increment: false, shift_uses_vy: false,
+        jump_uses_vx: true, wrap_sprites: false, display_wait: false,
+    }),
+    ("xochip", Quirks {
+        vf_reset: false, memory_increment: true, shift_uses_vy: true,
+        jump_uses_vx: false, wrap_sprites: true, display_wait: false,
+    }),
+];
+
+impl Quirks {
+    pub fn by_name(name: &str) -> Option<Quirks> {
+        PROFILES.iter().find(|(n, _)| *n == name).map(|(_, q)| *q)
+    }
+
+    pub fn profile_names() -> Vec<&'static str> {
+        PROFILES.iter().map(|(n, _)| *n).collect()
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt::Write;
+use crate::analysis::analyze;
+use crate::chip8::{Chip8, Chip8Error};
+use crate::drivers::keyboard::KeyState;
+use crate::frontend::Runner;
+use crate::instruction::{decode, Instruction};
+use crate::romdb::sha1_hex;
+
+#[cfg(test)]
+#[path = "./recompiler_tests.rs"]
+mod recompiler_tests;
+
+// a basic block of a recompiled ROM, a Rust function doing what its instructions do
+pub struct CompiledBlock {
+    pub start: u16,
+    // number of instructions, counted against the frame's cycles
+    pub len: u32,
+    // the bytes it was compiled from, it only runs while memory still holds them
+    pub code: &'static [u8],
+    // leaves pc wherever the block continues
+    pub run: fn(&mut Chip8, &KeyState) -> Result<(), Chip8Error>,
+}
+
+// the runtime half of a recompiled ROM: runs its blocks, and the interpreter wherever there
+// isn't one, after a computed jump or once the program has written over its code
+pub struct Recompiled {
+    // sorted by start address
+    pub blocks: &'static [CompiledBlock],
+}
+
+impl Runner for Recompiled {
+    fn run_frame(&mut self, chip8: &mut Chip8, key_state: &KeyState, cycles: u32) -> Result<(), Chip8Error> {
+        chip8.draw_flag = false;
+
+        let mut left = cycles;
+        while left > 0 {
+            match self.block_at(chip8) {
+                // a block never stops part way, the interpreter finishes off the frame instead
+                Some(block) if block.len <= left => {
+                    (block.run)(chip8, key_state)?;
+                    left -= block.len;
+                },
+                _ => {
+                    chip8.step(key_state)?;
+                    left -= 1;
+                },
+            }
+
+            // blocks end after a draw, so this stops at the same place as the interpreter
+            if chip8.draw_flag && chip8.quirks.display_wait {
+                break;
+            }
+        }
+
+        chip8.tick_timers();
+        Ok(())
+    }
+}
+
+impl Recompiled {
+    fn block_at(&self, chip8: &Chip8) -> Option<&'static CompiledBlock> {
+        let pc = chip8.pc();
+        let block = &self.blocks[self.blocks.binary_search_by_key(&pc, |b| b.start).ok()?];
+        let memory = chip8.memory().get(pc as usize..pc as usize + block.code.len())?;
+        if memory == block.code {Some(block)} else {None}
+    }
+}
+
+// how an instruction is translated
+enum Translation {
+    // straight Rust on the local copies of the registers
+    Inline(String),
+    // sets pc, the end of a block
+    Branch(String),
+    // handed to Chip8::step_decoded, with or without ending the block
+    Execute {ends_block: bool},
+}
+
+fn translate(instr: Instruction, next: u16) -> Option<Translation> {
+    use Instruction::*;
+    use Translation::*;
+
+    let t = match instr {
+        LdImm {x, nn} => Inline(format!("v[{:#X}] = {:#04X};", x, nn)),
+        AddImm {x, nn} => Inline(format!("v[{:#X}] = v[{:#X}].wrapping_add({:#04X});", x, x, nn)),
+        LdReg {x, y} => Inline(format!("v[{:#X}] = v[{:#X}];", x, y)),
+        Or {x, y} => Inline(format!("v[{:#X}] |= v[{:#X}]; if q.vf_reset {{v[0xF] = 0}}", x, y)),
+        And {x, y} => Inline(format!("v[{:#X}] &= v[{:#X}]; if q.vf_reset {{v[0xF] = 0}}", x, y)),
+        Xor {x, y} => Inline(format!("v[{:#X}] ^= v[{:#X}]; if q.vf_reset {{v[0xF] = 0}}", x, y)),
+        AddReg {x, y} => Inline(format!("let (r, c) = v[{:#X}].overflowing_add(v[{:#X}]); v[{:#X}] = r; v[0xF] = c as u8;", x, y, x)),
+        Sub {x, y} => Inline(format!("let (r, b) = v[{:#X}].overflowing_sub(v[{:#X}]); v[{:#X}] = r; v[0xF] = !b as u8;", x, y, x)),
+        Subn {x, y} => Inline(format!("let (r, b) = v[{:#X}].overflowing_sub(v[{:#X}]); v[{:#X}] = r; v[0xF] = !b as u8;", y, x, x)),
+        Shr {x, y} => Inline(format!("let s = if q.shift_uses_vy {{v[{:#X}]}} else {{v[{:#X}]}}; v[{:#X}] = s >> 1; v[0xF] = s & 1;", y, x, x)),
+        Shl {x, y} => Inline(format!("let s = if q.shift_uses_vy {{v[{:#X}]}} else {{v[{:#X}]}}; v[{:#X}] = s << 1; v[0xF] = s >> 7;", y, x, x)),
+        LdI(nnn) => Inline(format!("i = {:#05X};", nnn)),
+        AddI {x} => Inline(format!("i = i.wrapping_add(v[{:#X}] as u16);", x)),
+        LdF {x} => Inline(format!("i = (v[{:#X}] & 0xF) as u16 * 5;", x)),
+        LdVxDt {x} => Inline(format!("v[{:#X}] = m.delay_timer();", x)),
+        LdDtVx {x} => Inline(format!("m.set_delay_timer(v[{:#X}]);", x)),
+        LdStVx {x} => Inline(format!("m.sound_timer = v[{:#X}];", x)),
+
+        Jp(nnn) => Branch(format!("m.set_pc({:#05X});", nnn)),
+        // the target is looked up when the block returns, the interpreter runs it if there's no block there
+        JpV0(nnn) => Branch(format!("m.set_pc((if q.jump_uses_vx {{v[{:#X}]}} else {{v[0x0]}}) as u16 + {:#05X});", nnn >> 8, nnn)),
+        SeImm {x, nn} => Branch(skip(&format!("v[{:#X}] == {:#04X}", x, nn), next)),
+        SneImm {x, nn} => Branch(skip(&format!("v[{:#X}] != {:#04X}", x, nn), next)),
+        SeReg {x, y} => Branch(skip(&format!("v[{:#X}] == v[{:#X}]", x, y), next)),
+        SneReg {x, y} => Branch(skip(&format!("v[{:#X}] != v[{:#X}]", x, y), next)),
+
+        Cls | Rnd {..} | Load {..} => Execute {ends_block: false},
+        // FX55 and FX33 could write over the rest of the block, the memory check before the next
+        // block catches it
+        Ret | Call(_) | Drw {..} | Skp {..} | Sknp {..} | LdVxK {..} | Bcd {..} | Store {..} => Execute {ends_block: true},
+
+        // SCHIP and XO-CHIP, decode never returns these
+        _ => return None,
+    };
+
+    Some(t)
+}
+
+fn skip(cond: &str, next: u16) -> String {
+    format!("m.set_pc(if {} {{{:#05X}}} else {{{:#05X}}});", cond, next.wrapping_add(2), next)
+}
+
+// one generated function, instructions as (address, opcode)
+struct Piece {
+    start: u16,
+    instrs: Vec<(u16, u16)>,
+    body: String,
+}
+
+impl Piece {
+    fn new(start: u16) -> Piece {
+        Piece {start, instrs: Vec::new(), body: String::new()}
+    }
+
+    fn line(&mut self, line: &str) {
+        self.body += "    ";
+        self.body += line;
+        self.body += "\n";
+    }
+
+    // hand the locals back before the Chip8 runs anything itself
+    fn sync(&mut self) {
+        self.line("m.set_registers(v);");
+        self.line("m.set_index_register(i);");
+    }
+}
+
+// the ROM as a Rust program: a function per basic block found by following the program from
+// 0x200, and a main that runs them with the usual window, keys and options
+pub fn recompile(rom: &[u8], name: &str) -> String {
+    let analysis = analyze(rom);
+    let mut pieces = Vec::new();
+
+    for block in analysis.blocks.values() {
+        let mut piece: Option<Piece> = None;
+
+        for addr in (block.start..=block.end).step_by(2) {
+            let op = analysis.code[&addr];
+            let next = addr.wrapping_add(2);
+            let (instr, translation) = match decode(op).ok().and_then(|instr| Some((instr, translate(instr, next)?))) {
+                Some(t) => t,
+                // the interpreter reports the error when it gets here
+                None => break,
+            };
+
+            let p = piece.get_or_insert_with(|| Piece::new(addr));
+            p.instrs.push((addr, op));
+            p.line(&format!("// {:#05X}  {}", addr, instr));
+
+            let ends_block = match translation {
+                Translation::Inline(code) => {
+                    p.line(&code);
+                    false
+                },
+                Translation::Branch(code) => {
+                    p.sync();
+                    p.line(&code);
+                    true
+                },
+                Translation::Execute {ends_block} => {
+                    p.sync();
+                    p.line(&format!("m.step_decoded({:#05X}, {:?}, keys)?;", addr, instr));
+                    if !ends_block {
+                        p.line("v = m.registers();");
+                        p.line("i = m.index_register();");
+                    }
+                    ends_block
+                },
+            };
+            if ends_block {
+                pieces.extend(piece.take());
+            }
+        }
+
+        // falls through into the next block, or stops at an instruction left to the interpreter
+        if let Some(mut p) = piece.take() {
+            let next = p.instrs.last().unwrap().0.wrapping_add(2);
+            p.sync();
+            p.line(&format!("m.set_pc({:#05X});", next));
+            pieces.push(p);
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// {} recompiled by `chip8 recompile`, {} blocks. it builds against the chip8 crate,", name, pieces.len()).unwrap();
+    writeln!(out, "// e.g. copy it to examples/ and `cargo run --release --example <name> -- [OPTIONS]`").unwrap();
+    writeln!(out, "#![allow(unused_mut, unused_variables, unused_assignments)]").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "use chip8::chip8::{{Chip8, Chip8Error}};").unwrap();
+    writeln!(out, "use chip8::config::Config;").unwrap();
+    writeln!(out, "use chip8::drivers::keyboard::KeyState;").unwrap();
+    writeln!(out, "use chip8::frontend;").unwrap();
+    writeln!(out, "use chip8::instruction::Instruction::*;").unwrap();
+    writeln!(out, "use chip8::recompiler::{{CompiledBlock, Recompiled}};").unwrap();
+    writeln!(out, "use chip8::romdb::RomDb;").unwrap();
+    writeln!(out).unwrap();
+
+    // pub so tests can load the program as a module
+    writeln!(out, "pub static ROM: [u8; {}] = [", rom.len()).unwrap();
+    for line in rom.chunks(16) {
+        writeln!(out, "    {},", bytes(line)).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out, "const SHA1: &str = \"{}\";", sha1_hex(rom)).unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "pub static BLOCKS: [CompiledBlock; {}] = [", pieces.len()).unwrap();
+    for piece in pieces.iter() {
+        let code: Vec<u8> = piece.instrs.iter().flat_map(|(_, op)| op.to_be_bytes()).collect();
+        writeln!(out, "    CompiledBlock {{start: {:#05X}, len: {}, code: &[{}], run: block_{:03x}}},",
+            piece.start, piece.instrs.len(), bytes(&code), piece.start).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    for piece in pieces.iter() {
+        writeln!(out).unwrap();
+        writeln!(out, "fn block_{:03x}(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {{", piece.start).unwrap();
+        writeln!(out, "    let mut v = m.registers();").unwrap();
+        writeln!(out, "    let mut i = m.index_register();").unwrap();
+        writeln!(out, "    let q = m.quirks;").unwrap();
+        out += &piece.body;
+        writeln!(out, "    Ok(())").unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "fn main() {{").unwrap();
+    writeln!(out, "    let args: Vec<String> = std::env::args().collect();").unwrap();
+    writeln!(out, "    let entry = RomDb::embedded().lookup(&ROM);").unwrap();
+    writeln!(out, "    let result = Config::for_rom(&args, SHA1, entry.as_ref()).map_err(|e| e.to_string())").unwrap();
+    writeln!(out, "        .and_then(|(config, warnings)| {{").unwrap();
+    writeln!(out, "            for warning in warnings.iter() {{").unwrap();
+    writeln!(out, "                eprintln!(\"warning: {{}}\", warning);").unwrap();
+    writeln!(out, "            }}").unwrap();
+    writeln!(out, "            frontend::run(ROM.to_vec(), config, None, &mut Recompiled {{blocks: &BLOCKS}}).map_err(|e| e.to_string())").unwrap();
+    writeln!(out, "        }});").unwrap();
+    writeln!(out, "    if let Err(e) = result {{").unwrap();
+    writeln!(out, "        eprintln!(\"error: {{}}\", e);").unwrap();
+    writeln!(out, "        std::process::exit(1);").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    out
+}
+
+fn bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:#04X}", b)).collect::<Vec<String>>().join(", ")
+}
@@ -0,0 +1,78 @@
+use super::*;
+use crate::drivers::keyboard::create_key_state;
+use crate::rom_builder::{chip8_with_rom, RomBuilder};
+
+// what recompile emits for `ADD V0, 0x01; JP 0x200`, written out by hand
+fn add_and_jump(m: &mut Chip8, _keys: &KeyState) -> Result<(), Chip8Error> {
+	let mut v = m.registers();
+	v[0x0] = v[0x0].wrapping_add(0x01);
+	m.set_registers(v);
+	m.set_pc(0x200);
+	Ok(())
+}
+
+static BLOCKS: [CompiledBlock; 1] = [
+	CompiledBlock {start: 0x200, len: 2, code: &[0x70, 0x01, 0x12, 0x00], run: add_and_jump},
+];
+
+#[test]
+fn test_runs_blocks() {
+	let mut chip8 = chip8_with_rom(&RomBuilder::new().add(0, 1).jp(0x200).build());
+
+	Recompiled {blocks: &BLOCKS}.run_frame(&mut chip8, &create_key_state(), 10).unwrap();
+
+	assert_eq!(chip8.register(0), 5);
+	assert_eq!(chip8.pc(), 0x200);
+}
+
+#[test]
+fn test_overwritten_code_runs_on_the_interpreter() {
+	let mut chip8 = chip8_with_rom(&RomBuilder::new().add(0, 1).jp(0x200).build());
+	chip8.write_memory(0x200, &[0x70, 0x10]).unwrap();
+
+	Recompiled {blocks: &BLOCKS}.run_frame(&mut chip8, &create_key_state(), 10).unwrap();
+
+	assert_eq!(chip8.register(0), 5 * 0x10);
+}
+
+#[test]
+fn test_blocks_longer_than_the_rest_of_the_frame_are_interpreted() {
+	let mut chip8 = chip8_with_rom(&RomBuilder::new().add(0, 1).jp(0x200).build());
+
+	Recompiled {blocks: &BLOCKS}.run_frame(&mut chip8, &create_key_state(), 3).unwrap();
+
+	assert_eq!(chip8.register(0), 2);
+	assert_eq!(chip8.pc(), 0x202);
+}
+
+#[test]
+fn test_recompile_emits_a_function_per_block() {
+	let rom = RomBuilder::new()
+		.ld(0, 1).add(0, 2)
+		.drw(0, 0, 5)
+		.se(0, 3).jp(0x200)
+		.jp(0x206)
+		.build();
+
+	let source = recompile(&rom, "test.ch8");
+
+	assert!(source.contains("CompiledBlock {start: 0x200, len: 3, code: &[0x60, 0x01, 0x70, 0x02, 0xD0, 0x05], run: block_200}"));
+	assert!(source.contains("fn block_200(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {"));
+	assert!(source.contains("    v[0x0] = v[0x0].wrapping_add(0x02);\n"));
+	assert!(source.contains("    m.step_decoded(0x204, Drw { x: 0, y: 0, n: 5 }, keys)?;\n"));
+	assert!(source.contains("    m.set_pc(if v[0x0] == 0x03 {0x20A} else {0x208});\n"));
+	// the skip starts a block of its own, it's a jump target
+	assert!(source.contains("CompiledBlock {start: 0x206, len: 1, code: &[0x30, 0x03], run: block_206}"));
+	assert!(source.contains("pub static ROM: [u8; 12] = [\n    0x60, 0x01, 0x70, 0x02, 0xD0, 0x05, 0x30, 0x03, 0x12, 0x00, 0x12, 0x06,\n];"));
+}
+
+#[test]
+fn test_recompile_leaves_invalid_opcodes_to_the_interpreter() {
+	let rom = RomBuilder::new().ld(0, 1).op(0xFFFF).build();
+
+	let source = recompile(&rom, "test.ch8");
+
+	assert!(source.contains("CompiledBlock {start: 0x200, len: 1, code: &[0x60, 0x01], run: block_200}"));
+	assert!(source.contains("    m.set_pc(0x202);\n"));
+	assert!(!source.contains("block_202"));
+}
@@ -0,0 +1,221 @@
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use crate::chip8::{Chip8, MAX_IMAGE_SCALE, PIXEL_WIDTH, PIXEL_HEIGHT};
+use crate::palette::Palette;
+use crate::utils::timestamp;
+
+#[cfg(test)]
+#[path = "./recorder_tests.rs"]
+mod recorder_tests;
+
+const FRAME_RATE: u64 = 60;
+const SAMPLE_RATE: u32 = 44100;
+const SAMPLES_PER_FRAME: u32 = SAMPLE_RATE / FRAME_RATE as u32;
+const BEEP_FREQ: f32 = 440.0;
+const BEEP_VOLUME: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    // animated gif, indexed with the palette colours
+    Gif,
+    // raw RGB24 frames plus a WAV of the beeper, for encoding with e.g. ffmpeg
+    Raw,
+}
+
+pub struct Recorder {
+    sink: Sink,
+    path: PathBuf,
+    scale: u32,
+    palette: Palette,
+    frames: u64,
+}
+
+enum Sink {
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        size: (u16, u16),
+        // frames are written one behind so identical frames can be merged into a longer delay
+        pending: Option<(Vec<u8>, u16)>,
+    },
+    Raw {
+        video: BufWriter<File>,
+        audio: BufWriter<File>,
+        phase: f32,
+    },
+}
+
+impl Recorder {
+    // creates <dir>/chip8-<timestamp>.gif, or the directory <dir>/chip8-<timestamp>/ for raw output
+    pub fn start(format: RecordFormat, dir: &Path, scale: u32, palette: &Palette) -> io::Result<Recorder> {
+        fs::create_dir_all(dir)?;
+        let scale = scale.max(1);
+        let name = format!("chip8-{}", timestamp());
+
+        let (sink, path) = match format {
+            RecordFormat::Gif => {
+                let path = dir.join(format!("{}.gif", name));
+                // gif sizes are 16 bit
+                let size = |pixels: usize| (pixels as u32).checked_mul(scale).and_then(|n| u16::try_from(n).ok())
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("record scale {} is too large for a gif", scale)));
+                let (width, height) = (size(PIXEL_WIDTH)?, size(PIXEL_HEIGHT)?);
+
+                let mut encoder = gif::Encoder::new(BufWriter::new(File::create(&path)?), width, height, &gif_palette(palette))
+                    .map_err(to_io_error)?;
+                encoder.set_repeat(gif::Repeat::Infinite).map_err(to_io_error)?;
+
+                (Sink::Gif {encoder, size: (width, height), pending: None}, path)
+            },
+            RecordFormat::Raw => {
+                // frames come from to_image, which caps the scale
+                if scale > MAX_IMAGE_SCALE {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("record scale {} is too large, at most {}", scale, MAX_IMAGE_SCALE)));
+                }
+                let path = dir.join(name);
+                fs::create_dir_all(&path)?;
+
+                let video = BufWriter::new(File::create(path.join("frames.rgb"))?);
+                let mut audio = BufWriter::new(File::create(path.join("audio.wav"))?);
+                // sizes are filled in by finish()
+                write_wav_header(&mut audio, 0)?;
+
+                (Sink::Raw {video, audio, phase: 0.0}, path)
+            },
+        };
+
+        Ok(Recorder {sink, path, scale, palette: palette.clone(), frames: 0})
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // should be called once per emulated 60 Hz frame
+    pub fn capture(&mut self, chip8: &Chip8) -> io::Result<()> {
+        match &mut self.sink {
+            Sink::Gif {encoder, size, pending} => {
+                let indices = indexed_frame(chip8, self.scale, self.palette.colors.len());
+
+                // gif delays are in 1/100 s, spread the rounding so the total stays exact
+                let delay = (centis(self.frames + 1) - centis(self.frames)) as u16;
+
+                match pending {
+                    Some((prev, prev_delay)) if *prev == indices && *prev_delay < u16::MAX - delay => *prev_delay += delay,
+                    _ => {
+                        if let Some((prev, prev_delay)) = pending.take() {
+                            write_gif_frame(encoder, *size, prev, prev_delay)?;
+                        }
+                        *pending = Some((indices, delay));
+                    },
+                }
+            },
+            Sink::Raw {video, audio, phase} => {
+                video.write_all(&chip8.to_image(self.scale, &self.palette).pixels)?;
+
+                let phase_inc = BEEP_FREQ / SAMPLE_RATE as f32;
+                for _ in 0..SAMPLES_PER_FRAME {
+                    let sample = match chip8.sound_timer {
+                        0 => 0.0,
+                        _ if *phase <= 0.5 => BEEP_VOLUME,
+                        _ => -BEEP_VOLUME,
+                    };
+                    audio.write_all(&((sample * i16::MAX as f32) as i16).to_le_bytes())?;
+                    *phase = (*phase + phase_inc) % 1.0;
+                }
+            },
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    // flush everything to disk, returns the path written to and for raw output a hint on how to
+    // encode it
+    pub fn finish(self) -> io::Result<(PathBuf, Option<String>)> {
+        let hint = match self.sink {
+            Sink::Gif {mut encoder, size, pending} => {
+                if let Some((prev, prev_delay)) = pending {
+                    write_gif_frame(&mut encoder, size, prev, prev_delay)?;
+                }
+                encoder.into_inner()?.flush()?;
+                None
+            },
+            Sink::Raw {mut video, audio, ..} => {
+                video.flush()?;
+
+                let mut audio = audio.into_inner().map_err(|e| e.into_error())?;
+                let data_len = (self.frames * SAMPLES_PER_FRAME as u64 * 2) as u32;
+                audio.seek(SeekFrom::Start(0))?;
+                write_wav_header(&mut audio, data_len)?;
+
+                let (width, height) = (PIXEL_WIDTH as u32 * self.scale, PIXEL_HEIGHT as u32 * self.scale);
+                Some(format!(
+                    "raw frames are {}x{} rgb24 at {} fps, e.g. ffmpeg -f rawvideo -pixel_format rgb24 -video_size {}x{} -framerate {} -i frames.rgb -i audio.wav out.mp4",
+                    width, height, FRAME_RATE, width, height, FRAME_RATE,
+                ))
+            },
+        };
+
+        Ok((self.path, hint))
+    }
+}
+
+// elapsed time in 1/100 s at the start of frame n
+fn centis(n: u64) -> u64 {
+    (n * 100 + FRAME_RATE / 2) / FRAME_RATE
+}
+
+// global colour table, gif needs a power of 2 number of entries
+fn gif_palette(palette: &Palette) -> Vec<u8> {
+    let len = palette.colors.len().next_power_of_two().max(2);
+    let mut table: Vec<u8> = palette.colors.iter().flat_map(|c| [c.0, c.1, c.2]).collect();
+    table.resize(len * 3, 0);
+    table
+}
+
+fn indexed_frame(chip8: &Chip8, scale: u32, num_colors: usize) -> Vec<u8> {
+    let scale = scale as usize;
+    let width = PIXEL_WIDTH * scale;
+    let mut indices = vec![0; width * PIXEL_HEIGHT * scale];
+
+    for (i, out) in indices.iter_mut().enumerate() {
+        let val = chip8.framebuffer.value(i % width / scale, i / width / scale) as usize;
+        // values past the end of the palette use the foreground colour, like Palette::color
+        *out = if val < num_colors {val as u8} else {1};
+    }
+
+    indices
+}
+
+fn write_gif_frame(encoder: &mut gif::Encoder<BufWriter<File>>, (width, height): (u16, u16), indices: Vec<u8>, delay: u16) -> io::Result<()> {
+    let frame = gif::Frame {
+        width,
+        height,
+        buffer: Cow::Owned(indices),
+        delay,
+        ..gif::Frame::default()
+    };
+
+    encoder.write_frame(&frame).map_err(to_io_error)
+}
+
+// 16 bit mono PCM
+fn write_wav_header<W: Write>(w: &mut W, data_len: u32) -> io::Result<()> {
+    w.write_all(b"RIFF")?;
+    w.write_all(&(36 + data_len).to_le_bytes())?;
+    w.write_all(b"WAVEfmt ")?;
+    w.write_all(&16u32.to_le_bytes())?;             // fmt chunk size
+    w.write_all(&1u16.to_le_bytes())?;              // PCM
+    w.write_all(&1u16.to_le_bytes())?;              // channels
+    w.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    w.write_all(&(SAMPLE_RATE * 2).to_le_bytes())?; // byte rate
+    w.write_all(&2u16.to_le_bytes())?;              // block align
+    w.write_all(&16u16.to_le_bytes())?;             // bits per sample
+    w.write_all(b"data")?;
+    w.write_all(&data_len.to_le_bytes())
+}
+
+fn to_io_error(e: gif::EncodingError) -> io::Error {
+    io::Error::other(e)
+}
@@ -0,0 +1,69 @@
+use super::*;
+use std::env;
+
+#[test]
+fn test_gif_delays_add_up_to_one_second() {
+	let total: u64 = (0..60).map(|n| centis(n + 1) - centis(n)).sum();
+	assert_eq!(total, 100);
+}
+
+#[test]
+fn test_gif_merges_identical_frames() {
+	let dir = env::temp_dir().join("chip8_recorder_gif_test");
+	let mut chip8 = Chip8::new();
+
+	let mut rec = Recorder::start(RecordFormat::Gif, &dir, 1, &Palette::default()).unwrap();
+	for _ in 0..30 {
+		rec.capture(&chip8).unwrap();
+	}
+	chip8.framebuffer.set_pixel(0, 0, 1);
+	for _ in 0..30 {
+		rec.capture(&chip8).unwrap();
+	}
+	let (path, hint) = rec.finish().unwrap();
+	assert_eq!(hint, None);
+
+	let mut decoder = gif::DecodeOptions::new().read_info(File::open(&path).unwrap()).unwrap();
+	let mut delays = Vec::new();
+	while let Some(frame) = decoder.read_next_frame().unwrap() {
+		delays.push(frame.delay);
+	}
+	fs::remove_file(path).unwrap();
+
+	assert_eq!(delays, vec![50, 50]);
+}
+
+#[test]
+fn test_raw_recording_writes_frames_and_wav() {
+	let dir = env::temp_dir().join("chip8_recorder_raw_test");
+	let mut chip8 = Chip8::new();
+	chip8.sound_timer = 5;
+
+	let mut rec = Recorder::start(RecordFormat::Raw, &dir, 2, &Palette::default()).unwrap();
+	for _ in 0..3 {
+		rec.capture(&chip8).unwrap();
+	}
+	let (path, hint) = rec.finish().unwrap();
+	assert!(hint.unwrap().contains("-video_size 128x64"));
+
+	let frames = fs::read(path.join("frames.rgb")).unwrap();
+	let wav = fs::read(path.join("audio.wav")).unwrap();
+	fs::remove_dir_all(path).unwrap();
+
+	assert_eq!(frames.len(), 3 * PIXEL_WIDTH * 2 * PIXEL_HEIGHT * 2 * 3);
+	assert_eq!(wav.len(), 44 + 3 * SAMPLES_PER_FRAME as usize * 2);
+	assert_eq!(&wav[40..44], &(3 * SAMPLES_PER_FRAME * 2).to_le_bytes());
+}
+
+#[test]
+fn test_gif_scale_must_fit_16_bits() {
+	let dir = env::temp_dir().join("chip8_recorder_scale_test");
+
+	for scale in [1024, u32::MAX] {
+		let err = Recorder::start(RecordFormat::Gif, &dir, scale, &Palette::default()).err().unwrap();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+	}
+	let err = Recorder::start(RecordFormat::Raw, &dir, MAX_IMAGE_SCALE + 1, &Palette::default()).err().unwrap();
+	assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+	assert!(fs::read_dir(&dir).unwrap().next().is_none());
+}
@@ -0,0 +1,126 @@
+// assembles a ROM one instruction at a time, mostly for tests and benchmarks
+//
+//     let rom = RomBuilder::new().ld(0, 0x10).add(0, 1).drw(0, 1, 5).build();
+//
+// x and y are register indices, addresses are absolute
+use crate::chip8::Chip8;
+
+#[derive(Clone, Debug, Default)]
+pub struct RomBuilder {
+    bytes: Vec<u8>,
+}
+
+impl RomBuilder {
+    pub fn new() -> RomBuilder {
+        RomBuilder::default()
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    // address the next instruction will be loaded at
+    pub fn addr(&self) -> u16 {
+        0x200 + self.bytes.len() as u16
+    }
+
+    pub fn op(mut self, op: u16) -> RomBuilder {
+        self.bytes.extend_from_slice(&op.to_be_bytes());
+        self
+    }
+
+    pub fn bytes(mut self, bytes: &[u8]) -> RomBuilder {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    fn xnn(self, prefix: u16, x: u8, nn: u8) -> RomBuilder {
+        self.op((prefix << 12) | ((x as u16 & 0xF) << 8) | nn as u16)
+    }
+
+    fn xyn(self, prefix: u16, x: u8, y: u8, n: u8) -> RomBuilder {
+        self.op((prefix << 12) | ((x as u16 & 0xF) << 8) | ((y as u16 & 0xF) << 4) | (n as u16 & 0xF))
+    }
+
+    fn nnn(self, prefix: u16, nnn: u16) -> RomBuilder {
+        self.op((prefix << 12) | (nnn & 0xFFF))
+    }
+
+    // 00E0
+    pub fn cls(self) -> RomBuilder { self.op(0x00E0) }
+    // 00EE
+    pub fn ret(self) -> RomBuilder { self.op(0x00EE) }
+    // 1NNN
+    pub fn jp(self, nnn: u16) -> RomBuilder { self.nnn(1, nnn) }
+    // 2NNN
+    pub fn call(self, nnn: u16) -> RomBuilder { self.nnn(2, nnn) }
+    // 3XNN
+    pub fn se(self, x: u8, nn: u8) -> RomBuilder { self.xnn(3, x, nn) }
+    // 4XNN
+    pub fn sne(self, x: u8, nn: u8) -> RomBuilder { self.xnn(4, x, nn) }
+    // 5XY0
+    pub fn se_reg(self, x: u8, y: u8) -> RomBuilder { self.xyn(5, x, y, 0) }
+    // 6XNN
+    pub fn ld(self, x: u8, nn: u8) -> RomBuilder { self.xnn(6, x, nn) }
+    // 7XNN
+    pub fn add(self, x: u8, nn: u8) -> RomBuilder { self.xnn(7, x, nn) }
+    // 8XY0
+    pub fn ld_reg(self, x: u8, y: u8) -> RomBuilder { self.xyn(8, x, y, 0) }
+    // 8XY1
+    pub fn or(self, x: u8, y: u8) -> RomBuilder { self.xyn(8, x, y, 1) }
+    // 8XY2
+    pub fn and(self, x: u8, y: u8) -> RomBuilder { self.xyn(8, x, y, 2) }
+    // 8XY3
+    pub fn xor(self, x: u8, y: u8) -> RomBuilder { self.xyn(8, x, y, 3) }
+    // 8XY4
+    pub fn add_reg(self, x: u8, y: u8) -> RomBuilder { self.xyn(8, x, y, 4) }
+    // 8XY5
+    pub fn sub(self, x: u8, y: u8) -> RomBuilder { self.xyn(8, x, y, 5) }
+    // 8XY6
+    pub fn shr(self, x: u8, y: u8) -> RomBuilder { self.xyn(8, x, y, 6) }
+    // 8XY7
+    pub fn subn(self, x: u8, y: u8) -> RomBuilder { self.xyn(8, x, y, 7) }
+    // 8XYE
+    pub fn shl(self, x: u8, y: u8) -> RomBuilder { self.xyn(8, x, y, 0xE) }
+    // 9XY0
+    pub fn sne_reg(self, x: u8, y: u8) -> RomBuilder { self.xyn(9, x, y, 0) }
+    // ANNN
+    pub fn ld_i(self, nnn: u16) -> RomBuilder { self.nnn(0xA, nnn) }
+    // BNNN
+    pub fn jp_v0(self, nnn: u16) -> RomBuilder { self.nnn(0xB, nnn) }
+    // CXNN
+    pub fn rnd(self, x: u8, nn: u8) -> RomBuilder { self.xnn(0xC, x, nn) }
+    // DXYN
+    pub fn drw(self, x: u8, y: u8, n: u8) -> RomBuilder { self.xyn(0xD, x, y, n) }
+    // EX9E
+    pub fn skp(self, x: u8) -> RomBuilder { self.xnn(0xE, x, 0x9E) }
+    // EXA1
+    pub fn sknp(self, x: u8) -> RomBuilder { self.xnn(0xE, x, 0xA1) }
+    // FX07
+    pub fn ld_from_dt(self, x: u8) -> RomBuilder { self.xnn(0xF, x, 0x07) }
+    // FX0A
+    pub fn ld_key(self, x: u8) -> RomBuilder { self.xnn(0xF, x, 0x0A) }
+    // FX15
+    pub fn ld_dt(self, x: u8) -> RomBuilder { self.xnn(0xF, x, 0x15) }
+    // FX18
+    pub fn ld_st(self, x: u8) -> RomBuilder { self.xnn(0xF, x, 0x18) }
+    // FX1E
+    pub fn add_i(self, x: u8) -> RomBuilder { self.xnn(0xF, x, 0x1E) }
+    // FX29
+    pub fn ld_font(self, x: u8) -> RomBuilder { self.xnn(0xF, x, 0x29) }
+    // FX33
+    pub fn bcd(self, x: u8) -> RomBuilder { self.xnn(0xF, x, 0x33) }
+    // FX55
+    pub fn store(self, x: u8) -> RomBuilder { self.xnn(0xF, x, 0x55) }
+    // FX65
+    pub fn load(self, x: u8) -> RomBuilder { self.xnn(0xF, x, 0x65) }
+}
+
+// a fresh Chip8 with the ROM loaded and a fixed seed for CXNN, so runs repeat exactly.
+// panics if the ROM doesn't fit
+pub fn chip8_with_rom(rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.seed_rng(1);
+    chip8.load_rom(rom.to_vec()).unwrap();
+    chip8
+}
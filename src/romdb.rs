@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use crate::quirks::Quirks;
+
+#[cfg(test)]
+#[path = "./romdb_tests.rs"]
+mod romdb_tests;
+
+// known ROMs keyed by the SHA-1 of their bytes, in the format of the community
+// chip-8-database (https://github.com/chip-8/chip-8-database). a few entries are
+// compiled in, the full database can be loaded from a directory
+pub struct RomDb {
+    programs: Vec<Program>,
+    hashes: HashMap<String, usize>,
+    platforms: Vec<Platform>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Program {
+    title: String,
+    description: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    release: Option<String>,
+    roms: HashMap<String, Rom>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+    file: Option<String>,
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, PlatformQuirks>,
+    tickrate: Option<u32>,
+    colors: Option<Colors>,
+    #[serde(default)]
+    keys: HashMap<String, u8>,
+}
+
+#[derive(Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Platform {
+    id: String,
+    name: String,
+    default_tickrate: Option<u32>,
+    #[serde(default)]
+    quirks: PlatformQuirks,
+}
+
+// missing quirks are false, in quirkyPlatforms they keep the platform's value
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlatformQuirks {
+    shift: Option<bool>,
+    memory_increment_by_x: Option<bool>,
+    memory_leave_i_unchanged: Option<bool>,
+    wrap: Option<bool>,
+    jump: Option<bool>,
+    vblank: Option<bool>,
+    logic: Option<bool>,
+}
+
+impl PlatformQuirks {
+    fn overridden_by(self, other: PlatformQuirks) -> PlatformQuirks {
+        PlatformQuirks {
+            shift: other.shift.or(self.shift),
+            memory_increment_by_x: other.memory_increment_by_x.or(self.memory_increment_by_x),
+            memory_leave_i_unchanged: other.memory_leave_i_unchanged.or(self.memory_leave_i_unchanged),
+            wrap: other.wrap.or(self.wrap),
+            jump: other.jump.or(self.jump),
+            vblank: other.vblank.or(self.vblank),
+            logic: other.logic.or(self.logic),
+        }
+    }
+
+    // I is always left at I + X + 1 or untouched here, so incrementing by X counts as incrementing
+    fn to_quirks(self) -> Quirks {
+        Quirks {
+            vf_reset: self.logic.unwrap_or(false),
+            memory_increment: !self.memory_leave_i_unchanged.unwrap_or(false),
+            shift_uses_vy: !self.shift.unwrap_or(false),
+            jump_uses_vx: self.jump.unwrap_or(false),
+            wrap_sprites: self.wrap.unwrap_or(false),
+            display_wait: self.vblank.unwrap_or(false),
+        }
+    }
+}
+
+// everything the database knows about one ROM
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomEntry {
+    pub sha1: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub release: Option<String>,
+    pub file: Option<String>,
+    // platforms the ROM runs on, the first one is used
+    pub platforms: Vec<String>,
+    pub platform_name: Option<String>,
+    pub quirks: Option<Quirks>,
+    pub tickrate: Option<u32>,
+    pub colors: Vec<String>,
+    // button name to chip8 key, e.g. ("up", 5), sorted by name
+    pub keys: Vec<(String, u8)>,
+}
+
+impl RomEntry {
+    // the entry as config settings, applied before the config file and command line
+    pub fn settings(&self) -> Vec<(String, String)> {
+        let mut settings = Vec::new();
+
+        if let Some(q) = self.quirks {
+            for (key, val) in [
+                ("vf-reset", q.vf_reset), ("memory-increment", q.memory_increment),
+                ("shift-uses-vy", q.shift_uses_vy), ("jump-uses-vx", q.jump_uses_vx),
+                ("wrap-sprites", q.wrap_sprites), ("display-wait", q.display_wait),
+            ] {
+                settings.push((key.to_string(), val.to_string()));
+            }
+        }
+        if let Some(tickrate) = self.tickrate {
+            settings.push((String::from("cycles-per-frame"), tickrate.to_string()));
+        }
+        if !self.colors.is_empty() {
+            settings.push((String::from("colors"), self.colors.join(",")));
+        }
+        if !self.keys.is_empty() {
+            let keys: Vec<String> = self.keys.iter().map(|(button, key)| format!("{}:{:X}", button, key)).collect();
+            settings.push((String::from("keys"), keys.join(",")));
+        }
+
+        settings
+    }
+}
+
+impl RomDb {
+    pub fn embedded() -> RomDb {
+        RomDb::from_json(
+            include_str!("../data/chip-8-database/programs.json"),
+            include_str!("../data/chip-8-database/sha1-hashes.json"),
+            include_str!("../data/chip-8-database/platforms.json"),
+        ).expect("embedded ROM database is invalid")
+    }
+
+    // a checkout of the upstream database, programs.json, sha1-hashes.json and platforms.json
+    pub fn load_dir(dir: &Path) -> Result<RomDb, String> {
+        let read = |name: &str| {
+            let path = dir.join(name);
+            fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))
+        };
+
+        RomDb::from_json(&read("programs.json")?, &read("sha1-hashes.json")?, &read("platforms.json")?)
+            .map_err(|e| format!("{}: {}", dir.display(), e))
+    }
+
+    pub fn from_json(programs: &str, hashes: &str, platforms: &str) -> Result<RomDb, String> {
+        let programs: Vec<Program> = serde_json::from_str(programs).map_err(|e| format!("programs.json: {}", e))?;
+        let hashes: HashMap<String, usize> = serde_json::from_str(hashes).map_err(|e| format!("sha1-hashes.json: {}", e))?;
+        let platforms = serde_json::from_str(platforms).map_err(|e| format!("platforms.json: {}", e))?;
+
+        if let Some((hash, _)) = hashes.iter().find(|(_, i)| **i >= programs.len()) {
+            return Err(format!("sha1-hashes.json: {} points past the end of programs.json", hash));
+        }
+
+        Ok(RomDb {programs, hashes, platforms})
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> Option<RomEntry> {
+        self.lookup_hash(&sha1_hex(rom))
+    }
+
+    pub fn lookup_hash(&self, sha1: &str) -> Option<RomEntry> {
+        let sha1 = sha1.to_ascii_lowercase();
+        let program = &self.programs[*self.hashes.get(&sha1)?];
+        let rom = program.roms.get(&sha1)?;
+
+        let platform_id = rom.platforms.first();
+        let platform = platform_id.and_then(|id| self.platforms.iter().find(|p| p.id == *id));
+        let quirks = platform.map(|p| {
+            let overrides = rom.quirky_platforms.get(&p.id).copied().unwrap_or_default();
+            p.quirks.overridden_by(overrides).to_quirks()
+        });
+
+        let mut keys: Vec<(String, u8)> = rom.keys.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        keys.sort();
+
+        Some(RomEntry {
+            sha1,
+            title: program.title.clone(),
+            description: program.description.clone(),
+            authors: program.authors.clone(),
+            release: program.release.clone(),
+            file: rom.file.clone(),
+            platforms: rom.platforms.clone(),
+            platform_name: platform.map(|p| p.name.clone()),
+            quirks,
+            tickrate: rom.tickrate.or(platform.and_then(|p| p.default_tickrate)),
+            colors: rom.colors.as_ref().map(|c| c.pixels.clone()).unwrap_or_default(),
+            keys,
+        })
+    }
+}
+
+// lowercase hex, the form the database uses as keys
+pub fn sha1_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(bytes))
+}
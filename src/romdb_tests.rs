@@ -0,0 +1,75 @@
+use super::*;
+
+const PLATFORMS: &str = r#"[
+	{"id": "originalChip8", "name": "Cosmac VIP CHIP-8", "defaultTickrate": 15,
+	 "quirks": {"shift": false, "memoryLeaveIUnchanged": false, "wrap": false, "jump": false, "vblank": true, "logic": true}},
+	{"id": "superchip", "name": "SUPER-CHIP 1.1", "defaultTickrate": 30,
+	 "quirks": {"shift": true, "memoryLeaveIUnchanged": true, "jump": true}}
+]"#;
+
+const PROGRAMS: &str = r##"[
+	{"title": "Quirky", "unknownField": 1, "roms": {
+		"a9993e364706816aba3e25717850c26c9cd0d89d": {
+			"platforms": ["originalChip8", "superchip"],
+			"quirkyPlatforms": {"originalChip8": {"vblank": false}},
+			"tickrate": 20,
+			"colors": {"pixels": ["#000000", "#ff0000"], "buzzer": "#ffaa00"},
+			"keys": {"up": 5, "down": 8}
+		}
+	}},
+	{"title": "Plain", "roms": {"0000000000000000000000000000000000000000": {"platforms": ["superchip"]}}}
+]"##;
+
+const HASHES: &str = r#"{"a9993e364706816aba3e25717850c26c9cd0d89d": 0, "0000000000000000000000000000000000000000": 1}"#;
+
+#[test]
+fn test_sha1_hex() {
+	assert_eq!(sha1_hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+}
+
+#[test]
+fn test_lookup_applies_platform_and_overrides() {
+	let db = RomDb::from_json(PROGRAMS, HASHES, PLATFORMS).unwrap();
+	let entry = db.lookup(b"abc").unwrap();
+
+	assert_eq!(entry.title, "Quirky");
+	assert_eq!(entry.platform_name.as_deref(), Some("Cosmac VIP CHIP-8"));
+	assert_eq!(entry.tickrate, Some(20));
+	assert_eq!(entry.keys, [(String::from("down"), 8), (String::from("up"), 5)]);
+	// original chip8 quirks with vblank switched off for this ROM
+	assert_eq!(entry.quirks, Some(Quirks {display_wait: false, ..Quirks::by_name("chip8").unwrap()}));
+
+	let plain = db.lookup_hash("0000000000000000000000000000000000000000").unwrap();
+	assert_eq!(plain.tickrate, Some(30));
+	assert_eq!(plain.quirks, Some(Quirks {jump_uses_vx: true, ..Quirks::default()}));
+
+	assert!(db.lookup(b"not in the database").is_none());
+}
+
+#[test]
+fn test_settings() {
+	let db = RomDb::from_json(PROGRAMS, HASHES, PLATFORMS).unwrap();
+	let settings = db.lookup(b"abc").unwrap().settings();
+	let get = |key: &str| settings.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+	assert_eq!(get("vf-reset"), Some("true"));
+	assert_eq!(get("display-wait"), Some("false"));
+	assert_eq!(get("cycles-per-frame"), Some("20"));
+	assert_eq!(get("colors"), Some("#000000,#ff0000"));
+	assert_eq!(get("keys"), Some("down:8,up:5"));
+}
+
+#[test]
+fn test_bad_hash_index() {
+	assert!(RomDb::from_json(PROGRAMS, r#"{"abc": 2}"#, PLATFORMS).is_err());
+}
+
+#[test]
+fn test_embedded_database() {
+	let db = RomDb::embedded();
+	let pong = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("pong.chp8")).unwrap();
+	let entry = db.lookup(&pong).unwrap();
+
+	assert_eq!(entry.title, "Pong");
+	assert_eq!(entry.quirks, Quirks::by_name("chip8"));
+}
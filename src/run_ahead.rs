@@ -0,0 +1,29 @@
+use crate::chip8::Chip8;
+use crate::cheats::CheatList;
+use crate::drivers::keyboard::KeyState;
+use crate::framebuffer::Framebuffer;
+
+#[cfg(test)]
+#[path = "./run_ahead_tests.rs"]
+mod run_ahead_tests;
+
+// RetroArch style run-ahead: after the real frame has run, run `frames` more with the keys held
+// now and show the screen from the last of them, then put the machine back. games that react
+// to a key a few frames after reading it look like they react straight away
+//
+// the frames ahead run on the interpreter so scripts don't see frames that are thrown away,
+// cheats are held in them like in real ones. an error ends them early, the real frame reports it
+pub fn run_ahead(chip8: &mut Chip8, key_state: &KeyState, cycles: u32, frames: u32, cheats: &CheatList) -> Framebuffer {
+    let snapshot = chip8.snapshot();
+
+    for _ in 0..frames {
+        if chip8.run_frame(key_state, cycles).is_err() {
+            break;
+        }
+        cheats.apply(chip8);
+    }
+
+    let shown = chip8.framebuffer;
+    chip8.restore(&snapshot);
+    shown
+}
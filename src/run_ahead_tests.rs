@@ -0,0 +1,68 @@
+use super::*;
+use crate::cheats::Cheat;
+use crate::drivers::keyboard::{create_key_state, KeyboardDriver};
+use crate::rom_builder::{chip8_with_rom, RomBuilder};
+
+// draws a dot at V0 and moves it right a pixel every frame key 5 is held
+fn chip8_with_dot() -> Chip8 {
+	let rom = RomBuilder::new()
+		.ld_i(0x300).ld(2, 5)
+		.cls().drw(0, 1, 1)
+		.sknp(2).add(0, 1)
+		// wait for the delay timer to run out at the end of the frame
+		.ld(3, 1).ld_st(3).ld_dt(3)
+		.ld_from_dt(4).se(4, 0).jp(0x212)
+		.jp(0x204)
+		.build();
+
+	let mut chip8 = chip8_with_rom(&rom);
+	chip8.write_memory(0x300, &[0x80]).unwrap();
+	chip8
+}
+
+fn dot_x(framebuffer: &Framebuffer) -> Option<usize> {
+	(0..64).find(|&x| framebuffer.pixel(x, 0))
+}
+
+#[test]
+fn test_shows_frames_ahead_and_leaves_the_machine_alone() {
+	let mut chip8 = chip8_with_dot();
+	let mut key_state = create_key_state();
+	key_state.insert(KeyboardDriver::int_to_key(5).unwrap(), true);
+	chip8.run_frame(&key_state, 20).unwrap();
+	let before = (chip8.registers(), chip8.pc(), chip8.framebuffer, chip8.delay_timer(), chip8.sound_timer);
+
+	let shown = run_ahead(&mut chip8, &key_state, 20, 3, &CheatList::default());
+
+	assert_eq!(dot_x(&chip8.framebuffer), Some(0));
+	assert_eq!(dot_x(&shown), Some(3));
+	assert_eq!((chip8.registers(), chip8.pc(), chip8.framebuffer, chip8.delay_timer(), chip8.sound_timer), before);
+
+	// the frames ahead are the ones that run next
+	for _ in 0..3 {
+		chip8.run_frame(&key_state, 20).unwrap();
+	}
+	assert_eq!(chip8.framebuffer, shown);
+}
+
+#[test]
+fn test_zero_frames_shows_the_current_frame() {
+	let mut chip8 = chip8_with_dot();
+	chip8.run_frame(&create_key_state(), 20).unwrap();
+
+	assert_eq!(run_ahead(&mut chip8, &create_key_state(), 20, 0, &CheatList::default()), chip8.framebuffer);
+}
+
+#[test]
+fn test_cheats_are_held_ahead() {
+	let mut chip8 = chip8_with_dot();
+	chip8.run_frame(&create_key_state(), 20).unwrap();
+	// the sprite byte at 0x300 is the dot, a cheat makes it two pixels wide
+	let mut cheats = CheatList::default();
+	cheats.add(Cheat {addr: 0x300, value: 0xC0, compare: None});
+
+	let shown = run_ahead(&mut chip8, &create_key_state(), 20, 2, &cheats);
+
+	assert!(shown.pixel(1, 0));
+	assert_eq!(chip8.memory()[0x300], 0x80);
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+#[path = "./scheduler_tests.rs"]
+mod scheduler_tests;
+
+// most frames run in one display frame while fast forwarding, so a slow machine still draws
+const MAX_FRAMES_PER_UPDATE: u32 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    Normal,
+    FastForward,
+    SlowMotion,
+}
+
+// decides how many 60 Hz frames the emulator runs each time the screen is drawn, which is
+// always at 60 Hz. speeds other than 1 are frames run or skipped, never different sleeps, so a
+// frame is always `cycles_per_frame` instructions and one timer tick
+#[derive(Clone, Debug)]
+pub struct Scheduler {
+    speed: Speed,
+    paused: bool,
+    // frames asked for with frame advance while paused
+    advance: u32,
+    // fractions of a frame owed by the speeds between whole frames
+    credit: f32,
+    fast_forward: f32,
+    slow_motion: f32,
+    mute_fast_forward: bool,
+}
+
+impl Scheduler {
+    // fast_forward and slow_motion are multiples of normal speed, e.g. 4 and 0.25
+    pub fn new(fast_forward: f32, slow_motion: f32, mute_fast_forward: bool) -> Scheduler {
+        Scheduler {
+            speed: Speed::Normal,
+            paused: false,
+            advance: 0,
+            credit: 0.0,
+            fast_forward,
+            slow_motion,
+            mute_fast_forward,
+        }
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // frames per displayed frame at the current speed
+    pub fn multiplier(&self) -> f32 {
+        match self.speed {
+            Speed::Normal => 1.0,
+            Speed::FastForward => self.fast_forward,
+            Speed::SlowMotion => self.slow_motion,
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.advance = 0;
+        self.credit = 0.0;
+    }
+
+    // pause if running, then run exactly one frame
+    pub fn advance_frame(&mut self) {
+        self.paused = true;
+        self.advance += 1;
+    }
+
+    pub fn toggle_fast_forward(&mut self) {
+        self.set_speed(if self.speed == Speed::FastForward {Speed::Normal} else {Speed::FastForward});
+    }
+
+    pub fn toggle_slow_motion(&mut self) {
+        self.set_speed(if self.speed == Speed::SlowMotion {Speed::Normal} else {Speed::SlowMotion});
+    }
+
+    fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+        self.credit = 0.0;
+    }
+
+    // the beeper is silent while paused and, unless told otherwise, fast forwarding. the tone
+    // is made by the sound driver so its pitch never changes, only how long a beep lasts
+    pub fn muted(&self) -> bool {
+        self.paused || (self.speed == Speed::FastForward && self.mute_fast_forward)
+    }
+
+    // frames to run before the next displayed frame
+    pub fn frames_due(&mut self) -> u32 {
+        if self.paused {
+            return std::mem::take(&mut self.advance);
+        }
+
+        self.credit += self.multiplier();
+        let frames = (self.credit.floor() as u32).min(MAX_FRAMES_PER_UPDATE);
+        // a machine too slow to keep up drops the frames instead of falling further behind
+        self.credit = self.credit.fract();
+        frames
+    }
+}
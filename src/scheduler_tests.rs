@@ -0,0 +1,71 @@
+use super::*;
+
+fn frames(scheduler: &mut Scheduler, updates: usize) -> Vec<u32> {
+	(0..updates).map(|_| scheduler.frames_due()).collect()
+}
+
+#[test]
+fn test_normal_speed_runs_a_frame_per_update() {
+	let mut scheduler = Scheduler::new(4.0, 0.5, true);
+
+	assert_eq!(frames(&mut scheduler, 3), [1, 1, 1]);
+	assert!(!scheduler.muted());
+}
+
+#[test]
+fn test_fast_forward_and_slow_motion() {
+	let mut scheduler = Scheduler::new(2.5, 0.25, true);
+
+	scheduler.toggle_fast_forward();
+	assert_eq!(frames(&mut scheduler, 4), [2, 3, 2, 3]);
+	assert!(scheduler.muted());
+
+	scheduler.toggle_slow_motion();
+	assert_eq!(scheduler.speed(), Speed::SlowMotion);
+	assert_eq!(frames(&mut scheduler, 8), [0, 0, 0, 1, 0, 0, 0, 1]);
+	assert!(!scheduler.muted());
+
+	scheduler.toggle_slow_motion();
+	assert_eq!(scheduler.speed(), Speed::Normal);
+	assert_eq!(frames(&mut scheduler, 2), [1, 1]);
+}
+
+#[test]
+fn test_fast_forward_can_keep_the_sound() {
+	let mut scheduler = Scheduler::new(4.0, 0.5, false);
+	scheduler.toggle_fast_forward();
+
+	assert_eq!(frames(&mut scheduler, 2), [4, 4]);
+	assert!(!scheduler.muted());
+}
+
+#[test]
+fn test_fast_forward_is_capped() {
+	let mut scheduler = Scheduler::new(1000.0, 0.5, true);
+	scheduler.toggle_fast_forward();
+
+	assert_eq!(frames(&mut scheduler, 2), [MAX_FRAMES_PER_UPDATE, MAX_FRAMES_PER_UPDATE]);
+}
+
+#[test]
+fn test_pause_and_frame_advance() {
+	let mut scheduler = Scheduler::new(4.0, 0.5, true);
+
+	scheduler.toggle_pause();
+	assert_eq!(frames(&mut scheduler, 2), [0, 0]);
+	assert!(scheduler.muted());
+
+	scheduler.advance_frame();
+	assert_eq!(frames(&mut scheduler, 2), [1, 0]);
+	scheduler.advance_frame();
+	scheduler.advance_frame();
+	assert_eq!(frames(&mut scheduler, 2), [2, 0]);
+
+	scheduler.toggle_pause();
+	assert_eq!(frames(&mut scheduler, 2), [1, 1]);
+
+	// frame advance while running pauses first
+	scheduler.advance_frame();
+	assert!(scheduler.is_paused());
+	assert_eq!(frames(&mut scheduler, 2), [1, 0]);
+}
@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+use crate::chip8::{PIXEL_WIDTH, PIXEL_HEIGHT};
+use crate::framebuffer::Framebuffer;
+use crate::palette::{Palette, Rgb};
+
+#[cfg(test)]
+#[path = "./screen_render_tests.rs"]
+mod screen_render_tests;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    // present display memory as is
+    Direct,
+    // a pixel lit `k` frames ago keeps decay^k of its brightness, for the last `frames` frames
+    Phosphor {frames: usize, decay: f32},
+    // a pixel is lit if it was lit in any of the last `frames` frames
+    FrameOr {frames: usize},
+}
+
+impl RenderMode {
+    pub fn from_name(name: &str, frames: usize, decay: f32) -> Option<RenderMode> {
+        match name {
+            "direct" => Some(RenderMode::Direct),
+            "phosphor" => Some(RenderMode::Phosphor {frames, decay}),
+            "frame-or" => Some(RenderMode::FrameOr {frames}),
+            _ => None,
+        }
+    }
+
+    fn history_len(&self) -> usize {
+        match *self {
+            RenderMode::Direct => 1,
+            RenderMode::Phosphor {frames, ..} | RenderMode::FrameOr {frames} => frames.max(1),
+        }
+    }
+}
+
+// filter between the chip8 display memory and the display driver, keeps the
+// last few frames around so flickering sprites can be smoothed out
+pub struct ScreenRenderer {
+    mode: RenderMode,
+    history: VecDeque<Framebuffer>,   // newest frame first
+}
+
+impl ScreenRenderer {
+    pub fn new(mode: RenderMode) -> ScreenRenderer {
+        ScreenRenderer {mode, history: VecDeque::new()}
+    }
+
+    pub fn mode(&self) -> RenderMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: RenderMode) {
+        self.mode = mode;
+        self.history.truncate(mode.history_len());
+    }
+
+    // should be called once per emulated frame, whether or not the chip8 drew anything
+    pub fn push_frame(&mut self, framebuffer: &Framebuffer) {
+        self.history.push_front(*framebuffer);
+        self.history.truncate(self.mode.history_len());
+    }
+
+    // brightness in 0..=1 and the pixel value it comes from
+    pub fn pixel(&self, x: usize, y: usize) -> (f32, u32) {
+        let mut weight = 1.0;
+
+        for frame in self.history.iter() {
+            let val = frame.value(x, y);
+            if val != 0 {
+                return (weight, val);
+            }
+
+            match self.mode {
+                RenderMode::Direct => break,
+                RenderMode::Phosphor {decay, ..} => weight *= decay,
+                RenderMode::FrameOr {..} => (),
+            }
+        }
+
+        (0.0, 0)
+    }
+
+    // colour of every pixel as (row, col)
+    pub fn colors(&self, palette: &Palette) -> [[Rgb; PIXEL_WIDTH]; PIXEL_HEIGHT] {
+        let mut colors = [[palette.background(); PIXEL_WIDTH]; PIXEL_HEIGHT];
+
+        for (y, row) in colors.iter_mut().enumerate() {
+            for (x, color) in row.iter_mut().enumerate() {
+                let (intensity, val) = self.pixel(x, y);
+                if val != 0 {
+                    *color = palette.background().blend(palette.color(val), intensity);
+                }
+            }
+        }
+
+        colors
+    }
+}
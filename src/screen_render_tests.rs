@@ -0,0 +1,64 @@
+use super::*;
+
+fn frame_with_pixel(x: usize, y: usize) -> Framebuffer {
+	let mut frame = Framebuffer::new();
+	frame.set_pixel(x, y, 1);
+	frame
+}
+
+#[test]
+fn test_direct_mode_shows_latest_frame() {
+	let mut renderer = ScreenRenderer::new(RenderMode::Direct);
+
+	renderer.push_frame(&frame_with_pixel(3, 4));
+	renderer.push_frame(&frame_with_pixel(5, 6));
+
+	assert_eq!(renderer.pixel(3, 4), (0.0, 0));
+	assert_eq!(renderer.pixel(5, 6), (1.0, 1));
+}
+
+#[test]
+fn test_phosphor_mode_decays() {
+	let mut renderer = ScreenRenderer::new(RenderMode::Phosphor {frames: 3, decay: 0.5});
+	let empty = Framebuffer::new();
+
+	renderer.push_frame(&frame_with_pixel(1, 1));
+	assert_eq!(renderer.pixel(1, 1), (1.0, 1));
+
+	renderer.push_frame(&empty);
+	assert_eq!(renderer.pixel(1, 1), (0.5, 1));
+
+	renderer.push_frame(&empty);
+	assert_eq!(renderer.pixel(1, 1), (0.25, 1));
+
+	// dropped out of the history
+	renderer.push_frame(&empty);
+	assert_eq!(renderer.pixel(1, 1), (0.0, 0));
+}
+
+#[test]
+fn test_frame_or_mode() {
+	let mut renderer = ScreenRenderer::new(RenderMode::FrameOr {frames: 2});
+
+	renderer.push_frame(&frame_with_pixel(1, 1));
+	renderer.push_frame(&frame_with_pixel(2, 2));
+
+	assert_eq!(renderer.pixel(1, 1), (1.0, 1));
+	assert_eq!(renderer.pixel(2, 2), (1.0, 1));
+
+	renderer.push_frame(&frame_with_pixel(2, 2));
+	assert_eq!(renderer.pixel(1, 1), (0.0, 0));
+}
+
+#[test]
+fn test_colors_blend_towards_background() {
+	let mut renderer = ScreenRenderer::new(RenderMode::Phosphor {frames: 2, decay: 0.5});
+	let palette = Palette::default();
+
+	renderer.push_frame(&frame_with_pixel(0, 0));
+	renderer.push_frame(&Framebuffer::new());
+
+	let colors = renderer.colors(&palette);
+	assert_eq!(colors[0][0], Rgb(128, 128, 128));
+	assert_eq!(colors[0][1], palette.background());
+}
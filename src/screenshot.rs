@@ -0,0 +1,15 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::image::Image;
+use crate::utils::timestamp;
+
+// save as <dir>/chip8-<timestamp>.png, creating the directory if needed
+pub fn save_screenshot(image: &Image, dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let path = dir.join(format!("chip8-{}.png", timestamp()));
+    image.save_png(&path)?;
+
+    Ok(path)
+}
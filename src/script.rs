@@ -0,0 +1,368 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, Scope, AST};
+use crate::chip8::{Chip8, Chip8Error, Cycle, FrameHook, FONT_SET, MEMORY_SIZE, PIXEL_HEIGHT, PIXEL_WIDTH};
+use crate::config::Config;
+use crate::drivers::keyboard::{KeyState, KeyboardDriver};
+use crate::frontend::Runner;
+use crate::palette::{Palette, Rgb};
+use crate::screenshot::save_screenshot;
+
+#[cfg(test)]
+#[path = "./script_tests.rs"]
+mod script_tests;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// a Rhai script loaded with --script, run alongside the ROM. its top level runs once before the
+// first frame and usually registers callbacks, see README.md for the functions it can call
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    host: Rc<RefCell<Host>>,
+    started: bool,
+    // a runtime error stops the script, the ROM carries on without it
+    stopped: bool,
+    overlay: Vec<(usize, usize, Rgb)>,
+}
+
+// what the functions registered with the engine share
+struct Host {
+    // the machine isn't ours, the frontend's is swapped in here while script code runs
+    chip8: Chip8,
+    // keys the script holds down or up, over what the keyboard says
+    keys: [Option<bool>; 16],
+    key_state: KeyState,
+    cycles: u32,
+    frame: u64,
+    on_frame: Vec<FnPtr>,
+    on_pc: BTreeMap<u16, Vec<FnPtr>>,
+    on_write: BTreeMap<u16, Vec<FnPtr>>,
+    overlay: Vec<(usize, usize, Rgb)>,
+    quit: bool,
+    screenshot_dir: PathBuf,
+    screenshot_scale: u32,
+    palette: Palette,
+}
+
+impl Host {
+    fn key_state(&self, keyboard: &KeyState) -> KeyState {
+        let mut keys = keyboard.clone();
+        for (key, held) in self.keys.iter().enumerate() {
+            if let (Some(held), Some(code)) = (held, KeyboardDriver::int_to_key(key as u8)) {
+                keys.insert(code, *held);
+            }
+        }
+        keys
+    }
+}
+
+impl Script {
+    pub fn load(path: &Path, config: &Config) -> Result<Script, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Script::new(&source, config).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn new(source: &str, config: &Config) -> Result<Script, String> {
+        let host = Rc::new(RefCell::new(Host {
+            chip8: Chip8::new(),
+            keys: [None; 16],
+            key_state: KeyState::new(),
+            cycles: config.cycles_per_frame,
+            frame: 0,
+            on_frame: Vec::new(),
+            on_pc: BTreeMap::new(),
+            on_write: BTreeMap::new(),
+            overlay: Vec::new(),
+            quit: false,
+            screenshot_dir: config.screenshot_dir.clone(),
+            screenshot_scale: config.screenshot_scale,
+            palette: config.palette.clone(),
+        }));
+
+        let mut engine = Engine::new();
+        register_api(&mut engine, &host);
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+
+        Ok(Script {engine, ast, scope: Scope::new(), host, started: false, stopped: false, overlay: Vec::new()})
+    }
+
+    // run script code against chip8, stopping the script if it fails
+    fn with_machine(&mut self, chip8: &mut Chip8, f: impl FnOnce(&mut Script) -> ScriptResult<()>) {
+        std::mem::swap(chip8, &mut self.host.borrow_mut().chip8);
+        let res = f(self);
+        std::mem::swap(chip8, &mut self.host.borrow_mut().chip8);
+
+        if let Err(e) = res {
+            eprintln!("script stopped: {}", e);
+            self.stopped = true;
+        }
+    }
+
+    // callbacks are cloned out first, they may register more
+    fn call_hooks(&mut self, chip8: &mut Chip8, hooks: Vec<FnPtr>, args: Vec<Dynamic>) {
+        if self.stopped || hooks.is_empty() {
+            return;
+        }
+
+        self.with_machine(chip8, |script| {
+            for hook in hooks {
+                let _: Dynamic = hook.call(&script.engine, &script.ast, args.clone())?;
+            }
+            Ok(())
+        });
+    }
+
+}
+
+// runs pc and memory write callbacks around each instruction of a frame
+struct ScriptHook<'a> {
+    script: &'a mut Script,
+    // watched addresses and their values before the instruction
+    watched: Vec<(u16, u8)>,
+}
+
+impl FrameHook for ScriptHook<'_> {
+    type Stop = ();
+
+    fn before_step(&mut self, chip8: &mut Chip8, _left: u32) -> Cycle<()> {
+        let pc = chip8.pc();
+        let hooks = self.script.host.borrow().on_pc.get(&pc).cloned().unwrap_or_default();
+        self.script.call_hooks(chip8, hooks, vec![Dynamic::from(pc as i64)]);
+
+        self.watched = self.script.host.borrow().on_write.keys().map(|&a| (a, chip8.memory()[a as usize])).collect();
+        Cycle::Step
+    }
+
+    fn after_step(&mut self, chip8: &mut Chip8) {
+        for (addr, old) in std::mem::take(&mut self.watched) {
+            let new = chip8.memory()[addr as usize];
+            if new != old {
+                let hooks = self.script.host.borrow().on_write[&addr].clone();
+                let args = vec![Dynamic::from(addr as i64), Dynamic::from(old as i64), Dynamic::from(new as i64)];
+                self.script.call_hooks(chip8, hooks, args);
+            }
+        }
+    }
+}
+
+impl Runner for Script {
+    fn run_frame(&mut self, chip8: &mut Chip8, key_state: &KeyState, cycles: u32) -> Result<(), Chip8Error> {
+        if self.stopped {
+            return chip8.run_frame(key_state, cycles);
+        }
+
+        {
+            let mut host = self.host.borrow_mut();
+            host.cycles = cycles;
+            host.key_state = host.key_state(key_state);
+            host.overlay.clear();
+        }
+
+        if !self.started {
+            self.started = true;
+            self.with_machine(chip8, |script| script.engine.run_ast_with_scope(&mut script.scope, &script.ast));
+        }
+
+        // keys pressed by the top level or callbacks in the last frame count from this one
+        let keys = self.host.borrow().key_state(key_state);
+        let hooked = {
+            let host = self.host.borrow();
+            !host.on_pc.is_empty() || !host.on_write.is_empty()
+        };
+        let res = if hooked {
+            chip8.run_frame_with(&keys, cycles, &mut ScriptHook {script: self, watched: Vec::new()}).map(|_| ())
+        } else {
+            chip8.run_frame(&keys, cycles)
+        };
+
+        let frame = {
+            let mut host = self.host.borrow_mut();
+            host.frame += 1;
+            host.frame
+        };
+        let hooks = self.host.borrow().on_frame.clone();
+        self.call_hooks(chip8, hooks, vec![Dynamic::from(frame as i64)]);
+
+        self.overlay = self.host.borrow().overlay.clone();
+        res
+    }
+
+    fn overlay(&self) -> &[(usize, usize, Rgb)] {
+        &self.overlay
+    }
+
+    // true once the script called quit()
+    fn exit_requested(&self) -> bool {
+        self.host.borrow().quit
+    }
+}
+
+fn addr(val: i64) -> ScriptResult<usize> {
+    usize::try_from(val).ok().filter(|a| *a < MEMORY_SIZE).ok_or_else(|| format!("address {:#x} is past the end of memory", val).into())
+}
+
+fn byte(val: i64) -> ScriptResult<u8> {
+    u8::try_from(val).map_err(|_| format!("{} doesn't fit in a byte", val).into())
+}
+
+fn nibble(val: i64, what: &str) -> ScriptResult<usize> {
+    usize::try_from(val).ok().filter(|x| *x < 16).ok_or_else(|| format!("no {} {}, expected 0 to 15", what, val).into())
+}
+
+fn color(hex: &str) -> ScriptResult<Rgb> {
+    Rgb::from_hex(hex).ok_or_else(|| format!("invalid colour '{}', expected e.g. #FF0000", hex).into())
+}
+
+// HUD drawing is clipped to the screen
+fn hud_pixel(host: &mut Host, x: i64, y: i64, color: Rgb) {
+    if (0..PIXEL_WIDTH as i64).contains(&x) && (0..PIXEL_HEIGHT as i64).contains(&y) {
+        host.overlay.push((x as usize, y as usize, color));
+    }
+}
+
+fn register_api(engine: &mut Engine, host: &Rc<RefCell<Host>>) {
+    // memory and registers
+    let h = host.clone();
+    engine.register_fn("peek", move |a: i64| -> ScriptResult<i64> {
+        Ok(h.borrow().chip8.memory()[addr(a)?] as i64)
+    });
+    let h = host.clone();
+    engine.register_fn("poke", move |a: i64, val: i64| -> ScriptResult<()> {
+        let (a, val) = (addr(a)?, byte(val)?);
+        h.borrow_mut().chip8.write_memory(a as u16, &[val]).map_err(|e| e.to_string().into())
+    });
+    let h = host.clone();
+    engine.register_fn("reg", move |x: i64| -> ScriptResult<i64> {
+        Ok(h.borrow().chip8.register(nibble(x, "register")?) as i64)
+    });
+    let h = host.clone();
+    engine.register_fn("set_reg", move |x: i64, val: i64| -> ScriptResult<()> {
+        let (x, val) = (nibble(x, "register")?, byte(val)?);
+        h.borrow_mut().chip8.set_register(x, val);
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("pc", move || h.borrow().chip8.pc() as i64);
+    let h = host.clone();
+    engine.register_fn("set_pc", move |a: i64| -> ScriptResult<()> {
+        let a = addr(a)?;
+        h.borrow_mut().chip8.set_pc(a as u16);
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("index", move || h.borrow().chip8.index_register() as i64);
+    let h = host.clone();
+    engine.register_fn("set_index", move |a: i64| -> ScriptResult<()> {
+        let a = u16::try_from(a).map_err(|_| format!("{} doesn't fit in I", a))?;
+        h.borrow_mut().chip8.set_index_register(a);
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("delay_timer", move || h.borrow().chip8.delay_timer() as i64);
+    let h = host.clone();
+    engine.register_fn("sound_timer", move || h.borrow().chip8.sound_timer as i64);
+    let h = host.clone();
+    engine.register_fn("pixel", move |x: i64, y: i64| {
+        let on_screen = (0..PIXEL_WIDTH as i64).contains(&x) && (0..PIXEL_HEIGHT as i64).contains(&y);
+        on_screen && h.borrow().chip8.pixel(x as usize, y as usize)
+    });
+
+    // input and time
+    let h = host.clone();
+    engine.register_fn("press", move |key: i64| -> ScriptResult<()> {
+        h.borrow_mut().keys[nibble(key, "key")?] = Some(true);
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("release", move |key: i64| -> ScriptResult<()> {
+        h.borrow_mut().keys[nibble(key, "key")?] = Some(false);
+        Ok(())
+    });
+    // hand the key back to the keyboard
+    let h = host.clone();
+    engine.register_fn("unhold", move |key: i64| -> ScriptResult<()> {
+        h.borrow_mut().keys[nibble(key, "key")?] = None;
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("frame", move || h.borrow().frame as i64);
+    // run frames straight away with the keys held so far, callbacks don't fire for them
+    let h = host.clone();
+    engine.register_fn("advance", move |frames: i64| -> ScriptResult<()> {
+        let mut host = h.borrow_mut();
+        let host = &mut *host;
+        let keys = host.key_state(&host.key_state);
+        for _ in 0..frames {
+            host.chip8.run_frame(&keys, host.cycles).map_err(|e| e.to_string())?;
+            host.frame += 1;
+        }
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("screenshot", move || -> ScriptResult<String> {
+        let host = h.borrow();
+        let image = host.chip8.to_image(host.screenshot_scale, &host.palette);
+        let path = save_screenshot(&image, &host.screenshot_dir).map_err(|e| format!("could not save screenshot: {}", e))?;
+        Ok(path.display().to_string())
+    });
+    let h = host.clone();
+    engine.register_fn("quit", move || h.borrow_mut().quit = true);
+
+    // callbacks
+    let h = host.clone();
+    engine.register_fn("on_frame", move |f: FnPtr| h.borrow_mut().on_frame.push(f));
+    let h = host.clone();
+    engine.register_fn("on_pc", move |a: i64, f: FnPtr| -> ScriptResult<()> {
+        let a = addr(a)? as u16;
+        h.borrow_mut().on_pc.entry(a).or_default().push(f);
+        Ok(())
+    });
+    let h = host.clone();
+    engine.register_fn("on_write", move |a: i64, f: FnPtr| -> ScriptResult<()> {
+        let a = addr(a)? as u16;
+        h.borrow_mut().on_write.entry(a).or_default().push(f);
+        Ok(())
+    });
+
+    // HUD, drawn over the screen until the end of the next frame
+    let h = host.clone();
+    engine.register_fn("hud_rect", move |x: i64, y: i64, w: i64, hgt: i64, c: &str| -> ScriptResult<()> {
+        let c = color(c)?;
+        let mut host = h.borrow_mut();
+        // clipped up front, the size can be anything a script passes
+        let clip = |start: i64, len: i64, max: usize| start.clamp(0, max as i64)..start.saturating_add(len).clamp(0, max as i64);
+        for py in clip(y, hgt, PIXEL_HEIGHT) {
+            for px in clip(x, w, PIXEL_WIDTH) {
+                hud_pixel(&mut host, px, py, c);
+            }
+        }
+        Ok(())
+    });
+    // hex digits in the chip8 font, 5 pixels apart. anything else leaves a gap
+    let h = host.clone();
+    engine.register_fn("hud_text", move |x: i64, y: i64, text: &str, c: &str| -> ScriptResult<()> {
+        let c = color(c)?;
+        let mut host = h.borrow_mut();
+        for (n, digit) in text.chars().enumerate() {
+            let left = x.saturating_add((n as i64).saturating_mul(5));
+            if left >= PIXEL_WIDTH as i64 {
+                break;
+            }
+            let Some(digit) = digit.to_digit(16) else {continue};
+            let glyph = &FONT_SET[digit as usize * 5..digit as usize * 5 + 5];
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..4 {
+                    if bits & (0x80 >> col) != 0 {
+                        hud_pixel(&mut host, left.saturating_add(col), y.saturating_add(row as i64), c);
+                    }
+                }
+            }
+        }
+        Ok(())
+    });
+}
@@ -0,0 +1,109 @@
+use super::*;
+use crate::drivers::keyboard::create_key_state;
+use crate::rom_builder::{chip8_with_rom, RomBuilder};
+
+fn script(source: &str) -> Script {
+	Script::new(source, &Config::default()).unwrap()
+}
+
+#[test]
+fn test_top_level_reads_and_writes_the_machine() {
+	let mut chip8 = chip8_with_rom(&RomBuilder::new().add(0, 1).jp(0x200).build());
+	let mut script = script("poke(0x300, peek(0x200)); set_reg(1, 42); set_index(0x123);");
+
+	script.run_frame(&mut chip8, &create_key_state(), 10).unwrap();
+
+	assert_eq!(chip8.memory()[0x300], 0x70);
+	assert_eq!(chip8.register(1), 42);
+	assert_eq!(chip8.index_register(), 0x123);
+	assert_eq!(chip8.register(0), 5);
+}
+
+#[test]
+fn test_callbacks_fire() {
+	// two passes through the loop a frame, each writing V0 to 0x300
+	let rom = RomBuilder::new().add(0, 1).ld_i(0x300).store(0).jp(0x200).build();
+	let mut chip8 = chip8_with_rom(&rom);
+	let mut script = script(r#"
+		on_frame(|n| set_reg(5, n));
+		on_pc(0x202, |pc| set_reg(6, reg(6) + 1));
+		on_write(0x300, |addr, before, after| { if after != before + 1 { throw "skipped a value" } set_reg(7, after); });
+	"#);
+
+	for _ in 0..3 {
+		script.run_frame(&mut chip8, &create_key_state(), 8).unwrap();
+	}
+
+	assert_eq!(chip8.register(5), 3);
+	assert_eq!(chip8.register(6), 6);
+	assert_eq!(chip8.register(7), 6);
+	assert!(!script.stopped);
+}
+
+#[test]
+fn test_press_and_advance() {
+	// V0 counts frames with key 5 down, FX0A waits for it first
+	let rom = RomBuilder::new().ld_key(1).ld(2, 5).skp(2).jp(0x204).add(0, 1).jp(0x204).build();
+	let mut chip8 = chip8_with_rom(&rom);
+	let mut script = script("press(5); advance(2); set_reg(3, reg(0)); release(5); on_frame(|n| if n == 4 { quit() });");
+	let key_state = create_key_state();
+
+	script.run_frame(&mut chip8, &key_state, 4).unwrap();
+	assert!(chip8.register(3) > 0);
+
+	// released for the frames after, advance counted as frames 1 and 2
+	assert!(!script.exit_requested());
+	let held = chip8.register(0);
+	script.run_frame(&mut chip8, &key_state, 4).unwrap();
+	assert_eq!(chip8.register(0), held);
+	assert!(script.exit_requested());
+}
+
+#[test]
+fn test_hud() {
+	let mut chip8 = chip8_with_rom(&RomBuilder::new().jp(0x200).build());
+	let mut script = script(r##"on_frame(|n| { hud_rect(62, 0, 4, 1, "#FF0000"); hud_text(0, 10, "1", "#00FF00"); });"##);
+
+	script.run_frame(&mut chip8, &create_key_state(), 1).unwrap();
+
+	let red = Rgb(0xFF, 0, 0);
+	let green = Rgb(0, 0xFF, 0);
+	// clipped at the right edge
+	assert_eq!(script.overlay().iter().filter(|p| p.2 == red).count(), 2);
+	// the font's 1 is 0x20, 0x60, 0x20, 0x20, 0x70
+	assert_eq!(script.overlay().iter().filter(|p| p.2 == green).count(), 8);
+	assert!(script.overlay().contains(&(2, 10, green)));
+}
+
+#[test]
+fn test_hud_clips_huge_sizes() {
+	let mut chip8 = chip8_with_rom(&RomBuilder::new().jp(0x200).build());
+	let mut script = script(r##"
+		hud_rect(-5, 30, 9223372036854775807, 9223372036854775807, "#FF0000");
+		hud_rect(9223372036854775807, 9223372036854775807, 9223372036854775807, 1, "#FF0000");
+		hud_text(9223372036854775807, 9223372036854775807, "ffff", "#FF0000");
+		hud_text(-9223372036854775807, 0, "1111", "#FF0000");
+	"##);
+
+	script.run_frame(&mut chip8, &create_key_state(), 1).unwrap();
+
+	assert!(!script.stopped);
+	// the bottom two rows, the rest is off screen
+	assert_eq!(script.overlay().len(), 2 * 64);
+}
+
+#[test]
+fn test_errors_stop_the_script() {
+	let mut chip8 = chip8_with_rom(&RomBuilder::new().add(0, 1).jp(0x200).build());
+	let mut script = script("on_frame(|n| poke(0x1000, 1));");
+	let key_state = create_key_state();
+
+	script.run_frame(&mut chip8, &key_state, 2).unwrap();
+	assert!(script.stopped);
+
+	// the ROM carries on
+	script.run_frame(&mut chip8, &key_state, 2).unwrap();
+	assert_eq!(chip8.register(0), 2);
+
+	assert!(Script::new("let x = ;", &Config::default()).is_err());
+}
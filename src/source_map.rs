@@ -0,0 +1,62 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+
+#[cfg(test)]
+#[path = "./source_map_tests.rs"]
+mod source_map_tests;
+
+// which source line each instruction came from, written by an assembler as
+// {"mappings": [{"file": "pong.8o", "line": 12, "address": 512}, ..]}. lines start at 1,
+// relative file names are relative to the map
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    mappings: Vec<Mapping>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Mapping {
+    pub file: PathBuf,
+    pub line: u32,
+    pub address: u16,
+}
+
+#[derive(Deserialize)]
+struct SourceMapFile {
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    pub fn load(path: &Path) -> Result<SourceMap, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut map = SourceMap::from_json(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for m in map.mappings.iter_mut() {
+            m.file = dir.join(&m.file);
+        }
+        Ok(map)
+    }
+
+    pub fn from_json(text: &str) -> Result<SourceMap, String> {
+        let file: SourceMapFile = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let mut mappings = file.mappings;
+        mappings.sort_by(|a, b| (&a.file, a.line, a.address).cmp(&(&b.file, b.line, b.address)));
+        Ok(SourceMap {mappings})
+    }
+
+    // the first instruction on the line, or on the next line that has one, as breakpoints
+    // on blank lines and comments move down to the next instruction
+    pub fn address_at(&self, file: &Path, line: u32) -> Option<&Mapping> {
+        self.mappings.iter().find(|m| same_file(&m.file, file) && m.line >= line)
+    }
+
+    pub fn location(&self, addr: u16) -> Option<&Mapping> {
+        self.mappings.iter().find(|m| m.address == addr)
+    }
+}
+
+// editors send absolute paths, the map may hold relative ones
+fn same_file(mapped: &Path, file: &Path) -> bool {
+    mapped == file || file.ends_with(mapped)
+}
@@ -0,0 +1,38 @@
+use super::*;
+use std::env;
+
+const MAP: &str = r#"{"mappings": [
+	{"file": "game.8o", "line": 7, "address": 516},
+	{"file": "game.8o", "line": 3, "address": 512},
+	{"file": "game.8o", "line": 4, "address": 514},
+	{"file": "lib/sprites.8o", "line": 2, "address": 530}
+]}"#;
+
+#[test]
+fn test_lines_to_addresses() {
+	let map = SourceMap::from_json(MAP).unwrap();
+
+	assert_eq!(map.address_at(Path::new("game.8o"), 3).map(|m| m.address), Some(0x200));
+	// nothing on lines 5 and 6
+	assert_eq!(map.address_at(Path::new("/home/me/game.8o"), 5).map(|m| (m.line, m.address)), Some((7, 0x204)));
+	assert_eq!(map.address_at(Path::new("game.8o"), 8), None);
+	assert_eq!(map.address_at(Path::new("sprites.8o"), 1), None);
+
+	assert_eq!(map.location(0x212).map(|m| m.line), Some(2));
+	assert_eq!(map.location(0x213), None);
+}
+
+#[test]
+fn test_load_resolves_relative_files() {
+	let dir = env::temp_dir().join("chip8_source_map_test");
+	fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("game.map.json");
+	fs::write(&path, MAP).unwrap();
+
+	let map = SourceMap::load(&path).unwrap();
+	assert_eq!(map.location(0x212).unwrap().file, dir.join("lib/sprites.8o"));
+	assert!(map.address_at(&dir.join("lib/sprites.8o"), 1).is_some());
+
+	assert!(SourceMap::from_json("{}").is_err());
+	fs::remove_dir_all(&dir).unwrap();
+}
@@ -32,4 +32,11 @@ fn test_split_bytes() {
 
 	assert_eq!(res.0, 0x1A);
 	assert_eq!(res.1, 0x2C);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_format_timestamp() {
+	assert_eq!(format_timestamp(0), "19700101-000000-000");
+	assert_eq!(format_timestamp(951_782_400_123), "20000229-000000-123");
+	assert_eq!(format_timestamp(1_792_368_245_007), "20261019-000405-007");
+}
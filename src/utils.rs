@@ -1,3 +1,5 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 #[cfg(test)]
 #[path = "./util_tests.rs"]
 mod util_tests;
@@ -21,6 +23,10 @@ pub fn concat_bytes(b1: u8, b2: u8) -> u16 {
     ((b1 as u16) << 8) | b2 as u16
 }
 
+pub fn split_bytes(val: u16) -> (u8, u8) {
+    ((val >> 8) as u8, val as u8)
+}
+
 pub struct ArithmeticOverflow{
     pub val: u8,
     pub overflowed: bool,
@@ -52,4 +58,29 @@ pub fn to_binary_encoded_decimal(val: u8, len: usize) -> Vec<u8> {
     }
 
     vec
-}
\ No newline at end of file
+}
+
+// current UTC time as "YYYYMMDD-HHMMSS-mmm", used for output file names
+pub fn timestamp() -> String {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format_timestamp(since_epoch.as_millis() as u64)
+}
+
+pub fn format_timestamp(unix_millis: u64) -> String {
+    let secs = unix_millis / 1000;
+    let days = (secs / 86400) as i64;
+    let (h, m, s) = (secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+
+    // days since epoch to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe/1460 + doe/36524 - doe/146096) / 365;
+    let doy = doe - (365*yoe + yoe/4 - yoe/100);
+    let mp = (5*doy + 2) / 153;
+    let day = doy - (153*mp + 2)/5 + 1;
+    let month = if mp < 10 {mp + 3} else {mp - 9};
+    let year = yoe + era*400 + if month <= 2 {1} else {0};
+
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}", year, month, day, h, m, s, unix_millis % 1000)
+}
@@ -0,0 +1,62 @@
+// runs ROMs recompiled by `chip8 recompile` against the interpreter. the programs under
+// tests/recompiled are recompile's output checked in, rebuild them after changing it with
+//
+//     cargo run -- recompile pong.chp8 --out tests/recompiled/pong.rs
+
+use chip8::drivers::keyboard::{create_key_state, KeyboardDriver, KeyState};
+use chip8::frontend::Runner;
+use chip8::quirks::Quirks;
+use chip8::recompiler::{recompile, Recompiled};
+use chip8::rom_builder::chip8_with_rom;
+
+#[allow(dead_code)]
+#[path = "recompiled/pong.rs"]
+mod pong;
+
+const CYCLES: u32 = 10;
+const FRAMES: u32 = 300;
+
+// key 1 moves the left paddle up and 4 moves it down, like tests/golden/pong.input
+fn pong_keys(frame: u32) -> KeyState {
+	let mut key_state = create_key_state();
+	for (key, frames) in [(1, 30..60), (4, 120..200)] {
+		key_state.insert(KeyboardDriver::int_to_key(key).unwrap(), frames.contains(&frame));
+	}
+	key_state
+}
+
+#[test]
+fn test_recompiled_pong_is_up_to_date() {
+	let source = recompile(&pong::ROM, "pong.chp8");
+
+	assert!(source == include_str!("recompiled/pong.rs"), "tests/recompiled/pong.rs is out of date, rebuild it with chip8 recompile");
+	assert_eq!(&pong::ROM[..], include_bytes!("../pong.chp8"));
+}
+
+#[test]
+fn test_recompiled_pong_matches_interpreter() {
+	for profile in Quirks::profile_names() {
+		let quirks = Quirks::by_name(profile).unwrap();
+		let mut interpreted = chip8_with_rom(&pong::ROM);
+		let mut compiled = chip8_with_rom(&pong::ROM);
+		interpreted.quirks = quirks;
+		compiled.quirks = quirks;
+		let mut runner = Recompiled {blocks: &pong::BLOCKS};
+
+		for frame in 0..FRAMES {
+			let key_state = pong_keys(frame);
+			let expected = interpreted.run_frame(&key_state, CYCLES);
+			let actual = runner.run_frame(&mut compiled, &key_state, CYCLES);
+
+			assert_eq!(actual, expected, "{} frame {}", profile, frame);
+			assert_eq!(compiled.registers(), interpreted.registers(), "{} frame {}", profile, frame);
+			assert_eq!(
+				(compiled.pc(), compiled.index_register(), compiled.stack(), compiled.delay_timer(), compiled.sound_timer),
+				(interpreted.pc(), interpreted.index_register(), interpreted.stack(), interpreted.delay_timer(), interpreted.sound_timer),
+				"{} frame {}", profile, frame,
+			);
+			assert!(compiled.memory() == interpreted.memory(), "{} memory differs after frame {}", profile, frame);
+			assert!(compiled.framebuffer == interpreted.framebuffer, "{} screen differs after frame {}", profile, frame);
+		}
+	}
+}
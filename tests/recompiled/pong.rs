@@ -0,0 +1,986 @@
+// pong.chp8 recompiled by `chip8 recompile`, 66 blocks. it builds against the chip8 crate,
+// e.g. copy it to examples/ and `cargo run --release --example <name> -- [OPTIONS]`
+#![allow(unused_mut, unused_variables, unused_assignments)]
+
+use chip8::chip8::{Chip8, Chip8Error};
+use chip8::config::Config;
+use chip8::drivers::keyboard::KeyState;
+use chip8::frontend;
+use chip8::instruction::Instruction::*;
+use chip8::recompiler::{CompiledBlock, Recompiled};
+use chip8::romdb::RomDb;
+
+pub static ROM: [u8; 264] = [
+    0x22, 0xF6, 0x6B, 0x0C, 0x6C, 0x3F, 0x6D, 0x0C, 0xA2, 0xEA, 0xDA, 0xB6, 0xDC, 0xD6, 0x6E, 0x00,
+    0x22, 0xD4, 0x66, 0x03, 0x68, 0x02, 0x60, 0x60, 0xF0, 0x15, 0xF0, 0x07, 0x30, 0x00, 0x12, 0x1A,
+    0xC7, 0x17, 0x77, 0x08, 0x69, 0xFF, 0xA2, 0xF0, 0xD6, 0x71, 0xA2, 0xEA, 0xDA, 0xB6, 0xDC, 0xD6,
+    0x60, 0x01, 0xE0, 0xA1, 0x7B, 0xFE, 0x60, 0x04, 0xE0, 0xA1, 0x7B, 0x02, 0x60, 0x1F, 0x8B, 0x02,
+    0xDA, 0xB6, 0x60, 0x0C, 0xE0, 0xA1, 0x7D, 0xFE, 0x60, 0x0D, 0xE0, 0xA1, 0x7D, 0x02, 0x60, 0x1F,
+    0x8D, 0x02, 0xDC, 0xD6, 0xA2, 0xF0, 0xD6, 0x71, 0x86, 0x84, 0x87, 0x94, 0x60, 0x3F, 0x86, 0x02,
+    0x61, 0x1F, 0x87, 0x12, 0x46, 0x00, 0x12, 0x78, 0x46, 0x3F, 0x12, 0x82, 0x47, 0x1F, 0x69, 0xFF,
+    0x47, 0x00, 0x69, 0x01, 0xD6, 0x71, 0x12, 0x2A, 0x68, 0x02, 0x63, 0x01, 0x80, 0x70, 0x80, 0xB5,
+    0x12, 0x8A, 0x68, 0xFE, 0x63, 0x0A, 0x80, 0x70, 0x80, 0xD5, 0x3F, 0x01, 0x12, 0xA2, 0x61, 0x02,
+    0x80, 0x15, 0x3F, 0x01, 0x12, 0xBA, 0x80, 0x15, 0x3F, 0x01, 0x12, 0xC8, 0x80, 0x15, 0x3F, 0x01,
+    0x12, 0xC2, 0x60, 0x20, 0xF0, 0x18, 0x22, 0xD4, 0x8E, 0x34, 0x22, 0xD4, 0x66, 0x3E, 0x33, 0x01,
+    0x66, 0x03, 0x68, 0xFE, 0x33, 0x01, 0x68, 0x02, 0x12, 0x16, 0x79, 0xFF, 0x49, 0xFE, 0x69, 0xFF,
+    0x12, 0xC8, 0x79, 0x01, 0x49, 0x02, 0x69, 0x01, 0x60, 0x04, 0xF0, 0x18, 0x76, 0x01, 0x46, 0x40,
+    0x76, 0xFE, 0x12, 0x6C, 0xA2, 0xF2, 0xFE, 0x33, 0xF2, 0x65, 0xF1, 0x29, 0x64, 0x14, 0x65, 0x00,
+    0xD4, 0x55, 0x74, 0x15, 0xF2, 0x29, 0xD4, 0x55, 0x00, 0xEE, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80,
+    0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x6B, 0x20, 0x6C, 0x00, 0xA2, 0xEA, 0xDB, 0xC1, 0x7C, 0x01,
+    0x3C, 0x20, 0x12, 0xFC, 0x6A, 0x00, 0x00, 0xEE,
+];
+const SHA1: &str = "a60611339661e3ab2d8af024ad1da5880a6f8665";
+
+pub static BLOCKS: [CompiledBlock; 66] = [
+    CompiledBlock {start: 0x200, len: 1, code: &[0x22, 0xF6], run: block_200},
+    CompiledBlock {start: 0x202, len: 5, code: &[0x6B, 0x0C, 0x6C, 0x3F, 0x6D, 0x0C, 0xA2, 0xEA, 0xDA, 0xB6], run: block_202},
+    CompiledBlock {start: 0x20C, len: 1, code: &[0xDC, 0xD6], run: block_20c},
+    CompiledBlock {start: 0x20E, len: 2, code: &[0x6E, 0x00, 0x22, 0xD4], run: block_20e},
+    CompiledBlock {start: 0x212, len: 2, code: &[0x66, 0x03, 0x68, 0x02], run: block_212},
+    CompiledBlock {start: 0x216, len: 2, code: &[0x60, 0x60, 0xF0, 0x15], run: block_216},
+    CompiledBlock {start: 0x21A, len: 2, code: &[0xF0, 0x07, 0x30, 0x00], run: block_21a},
+    CompiledBlock {start: 0x21E, len: 1, code: &[0x12, 0x1A], run: block_21e},
+    CompiledBlock {start: 0x220, len: 5, code: &[0xC7, 0x17, 0x77, 0x08, 0x69, 0xFF, 0xA2, 0xF0, 0xD6, 0x71], run: block_220},
+    CompiledBlock {start: 0x22A, len: 2, code: &[0xA2, 0xEA, 0xDA, 0xB6], run: block_22a},
+    CompiledBlock {start: 0x22E, len: 1, code: &[0xDC, 0xD6], run: block_22e},
+    CompiledBlock {start: 0x230, len: 2, code: &[0x60, 0x01, 0xE0, 0xA1], run: block_230},
+    CompiledBlock {start: 0x234, len: 1, code: &[0x7B, 0xFE], run: block_234},
+    CompiledBlock {start: 0x236, len: 2, code: &[0x60, 0x04, 0xE0, 0xA1], run: block_236},
+    CompiledBlock {start: 0x23A, len: 1, code: &[0x7B, 0x02], run: block_23a},
+    CompiledBlock {start: 0x23C, len: 3, code: &[0x60, 0x1F, 0x8B, 0x02, 0xDA, 0xB6], run: block_23c},
+    CompiledBlock {start: 0x242, len: 2, code: &[0x60, 0x0C, 0xE0, 0xA1], run: block_242},
+    CompiledBlock {start: 0x246, len: 1, code: &[0x7D, 0xFE], run: block_246},
+    CompiledBlock {start: 0x248, len: 2, code: &[0x60, 0x0D, 0xE0, 0xA1], run: block_248},
+    CompiledBlock {start: 0x24C, len: 1, code: &[0x7D, 0x02], run: block_24c},
+    CompiledBlock {start: 0x24E, len: 3, code: &[0x60, 0x1F, 0x8D, 0x02, 0xDC, 0xD6], run: block_24e},
+    CompiledBlock {start: 0x254, len: 2, code: &[0xA2, 0xF0, 0xD6, 0x71], run: block_254},
+    CompiledBlock {start: 0x258, len: 7, code: &[0x86, 0x84, 0x87, 0x94, 0x60, 0x3F, 0x86, 0x02, 0x61, 0x1F, 0x87, 0x12, 0x46, 0x00], run: block_258},
+    CompiledBlock {start: 0x266, len: 1, code: &[0x12, 0x78], run: block_266},
+    CompiledBlock {start: 0x268, len: 1, code: &[0x46, 0x3F], run: block_268},
+    CompiledBlock {start: 0x26A, len: 1, code: &[0x12, 0x82], run: block_26a},
+    CompiledBlock {start: 0x26C, len: 1, code: &[0x47, 0x1F], run: block_26c},
+    CompiledBlock {start: 0x26E, len: 1, code: &[0x69, 0xFF], run: block_26e},
+    CompiledBlock {start: 0x270, len: 1, code: &[0x47, 0x00], run: block_270},
+    CompiledBlock {start: 0x272, len: 1, code: &[0x69, 0x01], run: block_272},
+    CompiledBlock {start: 0x274, len: 1, code: &[0xD6, 0x71], run: block_274},
+    CompiledBlock {start: 0x276, len: 1, code: &[0x12, 0x2A], run: block_276},
+    CompiledBlock {start: 0x278, len: 5, code: &[0x68, 0x02, 0x63, 0x01, 0x80, 0x70, 0x80, 0xB5, 0x12, 0x8A], run: block_278},
+    CompiledBlock {start: 0x282, len: 4, code: &[0x68, 0xFE, 0x63, 0x0A, 0x80, 0x70, 0x80, 0xD5], run: block_282},
+    CompiledBlock {start: 0x28A, len: 1, code: &[0x3F, 0x01], run: block_28a},
+    CompiledBlock {start: 0x28C, len: 1, code: &[0x12, 0xA2], run: block_28c},
+    CompiledBlock {start: 0x28E, len: 3, code: &[0x61, 0x02, 0x80, 0x15, 0x3F, 0x01], run: block_28e},
+    CompiledBlock {start: 0x294, len: 1, code: &[0x12, 0xBA], run: block_294},
+    CompiledBlock {start: 0x296, len: 2, code: &[0x80, 0x15, 0x3F, 0x01], run: block_296},
+    CompiledBlock {start: 0x29A, len: 1, code: &[0x12, 0xC8], run: block_29a},
+    CompiledBlock {start: 0x29C, len: 2, code: &[0x80, 0x15, 0x3F, 0x01], run: block_29c},
+    CompiledBlock {start: 0x2A0, len: 1, code: &[0x12, 0xC2], run: block_2a0},
+    CompiledBlock {start: 0x2A2, len: 3, code: &[0x60, 0x20, 0xF0, 0x18, 0x22, 0xD4], run: block_2a2},
+    CompiledBlock {start: 0x2A8, len: 2, code: &[0x8E, 0x34, 0x22, 0xD4], run: block_2a8},
+    CompiledBlock {start: 0x2AC, len: 2, code: &[0x66, 0x3E, 0x33, 0x01], run: block_2ac},
+    CompiledBlock {start: 0x2B0, len: 1, code: &[0x66, 0x03], run: block_2b0},
+    CompiledBlock {start: 0x2B2, len: 2, code: &[0x68, 0xFE, 0x33, 0x01], run: block_2b2},
+    CompiledBlock {start: 0x2B6, len: 1, code: &[0x68, 0x02], run: block_2b6},
+    CompiledBlock {start: 0x2B8, len: 1, code: &[0x12, 0x16], run: block_2b8},
+    CompiledBlock {start: 0x2BA, len: 2, code: &[0x79, 0xFF, 0x49, 0xFE], run: block_2ba},
+    CompiledBlock {start: 0x2BE, len: 1, code: &[0x69, 0xFF], run: block_2be},
+    CompiledBlock {start: 0x2C0, len: 1, code: &[0x12, 0xC8], run: block_2c0},
+    CompiledBlock {start: 0x2C2, len: 2, code: &[0x79, 0x01, 0x49, 0x02], run: block_2c2},
+    CompiledBlock {start: 0x2C6, len: 1, code: &[0x69, 0x01], run: block_2c6},
+    CompiledBlock {start: 0x2C8, len: 4, code: &[0x60, 0x04, 0xF0, 0x18, 0x76, 0x01, 0x46, 0x40], run: block_2c8},
+    CompiledBlock {start: 0x2D0, len: 1, code: &[0x76, 0xFE], run: block_2d0},
+    CompiledBlock {start: 0x2D2, len: 1, code: &[0x12, 0x6C], run: block_2d2},
+    CompiledBlock {start: 0x2D4, len: 2, code: &[0xA2, 0xF2, 0xFE, 0x33], run: block_2d4},
+    CompiledBlock {start: 0x2D8, len: 5, code: &[0xF2, 0x65, 0xF1, 0x29, 0x64, 0x14, 0x65, 0x00, 0xD4, 0x55], run: block_2d8},
+    CompiledBlock {start: 0x2E2, len: 3, code: &[0x74, 0x15, 0xF2, 0x29, 0xD4, 0x55], run: block_2e2},
+    CompiledBlock {start: 0x2E8, len: 1, code: &[0x00, 0xEE], run: block_2e8},
+    CompiledBlock {start: 0x2F6, len: 3, code: &[0x6B, 0x20, 0x6C, 0x00, 0xA2, 0xEA], run: block_2f6},
+    CompiledBlock {start: 0x2FC, len: 1, code: &[0xDB, 0xC1], run: block_2fc},
+    CompiledBlock {start: 0x2FE, len: 2, code: &[0x7C, 0x01, 0x3C, 0x20], run: block_2fe},
+    CompiledBlock {start: 0x302, len: 1, code: &[0x12, 0xFC], run: block_302},
+    CompiledBlock {start: 0x304, len: 2, code: &[0x6A, 0x00, 0x00, 0xEE], run: block_304},
+];
+
+fn block_200(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x200  CALL 0x2F6
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x200, Call(758), keys)?;
+    Ok(())
+}
+
+fn block_202(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x202  LD VB, 0x0C
+    v[0xB] = 0x0C;
+    // 0x204  LD VC, 0x3F
+    v[0xC] = 0x3F;
+    // 0x206  LD VD, 0x0C
+    v[0xD] = 0x0C;
+    // 0x208  LD I, 0x2EA
+    i = 0x2EA;
+    // 0x20A  DRW VA, VB, 6
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x20A, Drw { x: 10, y: 11, n: 6 }, keys)?;
+    Ok(())
+}
+
+fn block_20c(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x20C  DRW VC, VD, 6
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x20C, Drw { x: 12, y: 13, n: 6 }, keys)?;
+    Ok(())
+}
+
+fn block_20e(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x20E  LD VE, 0x00
+    v[0xE] = 0x00;
+    // 0x210  CALL 0x2D4
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x210, Call(724), keys)?;
+    Ok(())
+}
+
+fn block_212(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x212  LD V6, 0x03
+    v[0x6] = 0x03;
+    // 0x214  LD V8, 0x02
+    v[0x8] = 0x02;
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x216);
+    Ok(())
+}
+
+fn block_216(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x216  LD V0, 0x60
+    v[0x0] = 0x60;
+    // 0x218  LD DT, V0
+    m.set_delay_timer(v[0x0]);
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x21A);
+    Ok(())
+}
+
+fn block_21a(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x21A  LD V0, DT
+    v[0x0] = m.delay_timer();
+    // 0x21C  SE V0, 0x00
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x0] == 0x00 {0x220} else {0x21E});
+    Ok(())
+}
+
+fn block_21e(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x21E  JP 0x21A
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x21A);
+    Ok(())
+}
+
+fn block_220(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x220  RND V7, 0x17
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x220, Rnd { x: 7, nn: 23 }, keys)?;
+    v = m.registers();
+    i = m.index_register();
+    // 0x222  ADD V7, 0x08
+    v[0x7] = v[0x7].wrapping_add(0x08);
+    // 0x224  LD V9, 0xFF
+    v[0x9] = 0xFF;
+    // 0x226  LD I, 0x2F0
+    i = 0x2F0;
+    // 0x228  DRW V6, V7, 1
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x228, Drw { x: 6, y: 7, n: 1 }, keys)?;
+    Ok(())
+}
+
+fn block_22a(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x22A  LD I, 0x2EA
+    i = 0x2EA;
+    // 0x22C  DRW VA, VB, 6
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x22C, Drw { x: 10, y: 11, n: 6 }, keys)?;
+    Ok(())
+}
+
+fn block_22e(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x22E  DRW VC, VD, 6
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x22E, Drw { x: 12, y: 13, n: 6 }, keys)?;
+    Ok(())
+}
+
+fn block_230(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x230  LD V0, 0x01
+    v[0x0] = 0x01;
+    // 0x232  SKNP V0
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x232, Sknp { x: 0 }, keys)?;
+    Ok(())
+}
+
+fn block_234(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x234  ADD VB, 0xFE
+    v[0xB] = v[0xB].wrapping_add(0xFE);
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x236);
+    Ok(())
+}
+
+fn block_236(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x236  LD V0, 0x04
+    v[0x0] = 0x04;
+    // 0x238  SKNP V0
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x238, Sknp { x: 0 }, keys)?;
+    Ok(())
+}
+
+fn block_23a(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x23A  ADD VB, 0x02
+    v[0xB] = v[0xB].wrapping_add(0x02);
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x23C);
+    Ok(())
+}
+
+fn block_23c(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x23C  LD V0, 0x1F
+    v[0x0] = 0x1F;
+    // 0x23E  AND VB, V0
+    v[0xB] &= v[0x0]; if q.vf_reset {v[0xF] = 0}
+    // 0x240  DRW VA, VB, 6
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x240, Drw { x: 10, y: 11, n: 6 }, keys)?;
+    Ok(())
+}
+
+fn block_242(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x242  LD V0, 0x0C
+    v[0x0] = 0x0C;
+    // 0x244  SKNP V0
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x244, Sknp { x: 0 }, keys)?;
+    Ok(())
+}
+
+fn block_246(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x246  ADD VD, 0xFE
+    v[0xD] = v[0xD].wrapping_add(0xFE);
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x248);
+    Ok(())
+}
+
+fn block_248(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x248  LD V0, 0x0D
+    v[0x0] = 0x0D;
+    // 0x24A  SKNP V0
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x24A, Sknp { x: 0 }, keys)?;
+    Ok(())
+}
+
+fn block_24c(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x24C  ADD VD, 0x02
+    v[0xD] = v[0xD].wrapping_add(0x02);
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x24E);
+    Ok(())
+}
+
+fn block_24e(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x24E  LD V0, 0x1F
+    v[0x0] = 0x1F;
+    // 0x250  AND VD, V0
+    v[0xD] &= v[0x0]; if q.vf_reset {v[0xF] = 0}
+    // 0x252  DRW VC, VD, 6
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x252, Drw { x: 12, y: 13, n: 6 }, keys)?;
+    Ok(())
+}
+
+fn block_254(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x254  LD I, 0x2F0
+    i = 0x2F0;
+    // 0x256  DRW V6, V7, 1
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x256, Drw { x: 6, y: 7, n: 1 }, keys)?;
+    Ok(())
+}
+
+fn block_258(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x258  ADD V6, V8
+    let (r, c) = v[0x6].overflowing_add(v[0x8]); v[0x6] = r; v[0xF] = c as u8;
+    // 0x25A  ADD V7, V9
+    let (r, c) = v[0x7].overflowing_add(v[0x9]); v[0x7] = r; v[0xF] = c as u8;
+    // 0x25C  LD V0, 0x3F
+    v[0x0] = 0x3F;
+    // 0x25E  AND V6, V0
+    v[0x6] &= v[0x0]; if q.vf_reset {v[0xF] = 0}
+    // 0x260  LD V1, 0x1F
+    v[0x1] = 0x1F;
+    // 0x262  AND V7, V1
+    v[0x7] &= v[0x1]; if q.vf_reset {v[0xF] = 0}
+    // 0x264  SNE V6, 0x00
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x6] != 0x00 {0x268} else {0x266});
+    Ok(())
+}
+
+fn block_266(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x266  JP 0x278
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x278);
+    Ok(())
+}
+
+fn block_268(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x268  SNE V6, 0x3F
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x6] != 0x3F {0x26C} else {0x26A});
+    Ok(())
+}
+
+fn block_26a(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x26A  JP 0x282
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x282);
+    Ok(())
+}
+
+fn block_26c(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x26C  SNE V7, 0x1F
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x7] != 0x1F {0x270} else {0x26E});
+    Ok(())
+}
+
+fn block_26e(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x26E  LD V9, 0xFF
+    v[0x9] = 0xFF;
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x270);
+    Ok(())
+}
+
+fn block_270(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x270  SNE V7, 0x00
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x7] != 0x00 {0x274} else {0x272});
+    Ok(())
+}
+
+fn block_272(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x272  LD V9, 0x01
+    v[0x9] = 0x01;
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x274);
+    Ok(())
+}
+
+fn block_274(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x274  DRW V6, V7, 1
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x274, Drw { x: 6, y: 7, n: 1 }, keys)?;
+    Ok(())
+}
+
+fn block_276(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x276  JP 0x22A
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x22A);
+    Ok(())
+}
+
+fn block_278(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x278  LD V8, 0x02
+    v[0x8] = 0x02;
+    // 0x27A  LD V3, 0x01
+    v[0x3] = 0x01;
+    // 0x27C  LD V0, V7
+    v[0x0] = v[0x7];
+    // 0x27E  SUB V0, VB
+    let (r, b) = v[0x0].overflowing_sub(v[0xB]); v[0x0] = r; v[0xF] = !b as u8;
+    // 0x280  JP 0x28A
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x28A);
+    Ok(())
+}
+
+fn block_282(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x282  LD V8, 0xFE
+    v[0x8] = 0xFE;
+    // 0x284  LD V3, 0x0A
+    v[0x3] = 0x0A;
+    // 0x286  LD V0, V7
+    v[0x0] = v[0x7];
+    // 0x288  SUB V0, VD
+    let (r, b) = v[0x0].overflowing_sub(v[0xD]); v[0x0] = r; v[0xF] = !b as u8;
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x28A);
+    Ok(())
+}
+
+fn block_28a(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x28A  SE VF, 0x01
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0xF] == 0x01 {0x28E} else {0x28C});
+    Ok(())
+}
+
+fn block_28c(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x28C  JP 0x2A2
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2A2);
+    Ok(())
+}
+
+fn block_28e(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x28E  LD V1, 0x02
+    v[0x1] = 0x02;
+    // 0x290  SUB V0, V1
+    let (r, b) = v[0x0].overflowing_sub(v[0x1]); v[0x0] = r; v[0xF] = !b as u8;
+    // 0x292  SE VF, 0x01
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0xF] == 0x01 {0x296} else {0x294});
+    Ok(())
+}
+
+fn block_294(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x294  JP 0x2BA
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2BA);
+    Ok(())
+}
+
+fn block_296(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x296  SUB V0, V1
+    let (r, b) = v[0x0].overflowing_sub(v[0x1]); v[0x0] = r; v[0xF] = !b as u8;
+    // 0x298  SE VF, 0x01
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0xF] == 0x01 {0x29C} else {0x29A});
+    Ok(())
+}
+
+fn block_29a(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x29A  JP 0x2C8
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2C8);
+    Ok(())
+}
+
+fn block_29c(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x29C  SUB V0, V1
+    let (r, b) = v[0x0].overflowing_sub(v[0x1]); v[0x0] = r; v[0xF] = !b as u8;
+    // 0x29E  SE VF, 0x01
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0xF] == 0x01 {0x2A2} else {0x2A0});
+    Ok(())
+}
+
+fn block_2a0(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2A0  JP 0x2C2
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2C2);
+    Ok(())
+}
+
+fn block_2a2(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2A2  LD V0, 0x20
+    v[0x0] = 0x20;
+    // 0x2A4  LD ST, V0
+    m.sound_timer = v[0x0];
+    // 0x2A6  CALL 0x2D4
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x2A6, Call(724), keys)?;
+    Ok(())
+}
+
+fn block_2a8(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2A8  ADD VE, V3
+    let (r, c) = v[0xE].overflowing_add(v[0x3]); v[0xE] = r; v[0xF] = c as u8;
+    // 0x2AA  CALL 0x2D4
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x2AA, Call(724), keys)?;
+    Ok(())
+}
+
+fn block_2ac(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2AC  LD V6, 0x3E
+    v[0x6] = 0x3E;
+    // 0x2AE  SE V3, 0x01
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x3] == 0x01 {0x2B2} else {0x2B0});
+    Ok(())
+}
+
+fn block_2b0(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2B0  LD V6, 0x03
+    v[0x6] = 0x03;
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2B2);
+    Ok(())
+}
+
+fn block_2b2(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2B2  LD V8, 0xFE
+    v[0x8] = 0xFE;
+    // 0x2B4  SE V3, 0x01
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x3] == 0x01 {0x2B8} else {0x2B6});
+    Ok(())
+}
+
+fn block_2b6(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2B6  LD V8, 0x02
+    v[0x8] = 0x02;
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2B8);
+    Ok(())
+}
+
+fn block_2b8(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2B8  JP 0x216
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x216);
+    Ok(())
+}
+
+fn block_2ba(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2BA  ADD V9, 0xFF
+    v[0x9] = v[0x9].wrapping_add(0xFF);
+    // 0x2BC  SNE V9, 0xFE
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x9] != 0xFE {0x2C0} else {0x2BE});
+    Ok(())
+}
+
+fn block_2be(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2BE  LD V9, 0xFF
+    v[0x9] = 0xFF;
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2C0);
+    Ok(())
+}
+
+fn block_2c0(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2C0  JP 0x2C8
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2C8);
+    Ok(())
+}
+
+fn block_2c2(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2C2  ADD V9, 0x01
+    v[0x9] = v[0x9].wrapping_add(0x01);
+    // 0x2C4  SNE V9, 0x02
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x9] != 0x02 {0x2C8} else {0x2C6});
+    Ok(())
+}
+
+fn block_2c6(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2C6  LD V9, 0x01
+    v[0x9] = 0x01;
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2C8);
+    Ok(())
+}
+
+fn block_2c8(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2C8  LD V0, 0x04
+    v[0x0] = 0x04;
+    // 0x2CA  LD ST, V0
+    m.sound_timer = v[0x0];
+    // 0x2CC  ADD V6, 0x01
+    v[0x6] = v[0x6].wrapping_add(0x01);
+    // 0x2CE  SNE V6, 0x40
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0x6] != 0x40 {0x2D2} else {0x2D0});
+    Ok(())
+}
+
+fn block_2d0(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2D0  ADD V6, 0xFE
+    v[0x6] = v[0x6].wrapping_add(0xFE);
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2D2);
+    Ok(())
+}
+
+fn block_2d2(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2D2  JP 0x26C
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x26C);
+    Ok(())
+}
+
+fn block_2d4(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2D4  LD I, 0x2F2
+    i = 0x2F2;
+    // 0x2D6  LD B, VE
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x2D6, Bcd { x: 14 }, keys)?;
+    Ok(())
+}
+
+fn block_2d8(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2D8  LD V2, [I]
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x2D8, Load { x: 2 }, keys)?;
+    v = m.registers();
+    i = m.index_register();
+    // 0x2DA  LD F, V1
+    i = (v[0x1] & 0xF) as u16 * 5;
+    // 0x2DC  LD V4, 0x14
+    v[0x4] = 0x14;
+    // 0x2DE  LD V5, 0x00
+    v[0x5] = 0x00;
+    // 0x2E0  DRW V4, V5, 5
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x2E0, Drw { x: 4, y: 5, n: 5 }, keys)?;
+    Ok(())
+}
+
+fn block_2e2(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2E2  ADD V4, 0x15
+    v[0x4] = v[0x4].wrapping_add(0x15);
+    // 0x2E4  LD F, V2
+    i = (v[0x2] & 0xF) as u16 * 5;
+    // 0x2E6  DRW V4, V5, 5
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x2E6, Drw { x: 4, y: 5, n: 5 }, keys)?;
+    Ok(())
+}
+
+fn block_2e8(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2E8  RET
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x2E8, Ret, keys)?;
+    Ok(())
+}
+
+fn block_2f6(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2F6  LD VB, 0x20
+    v[0xB] = 0x20;
+    // 0x2F8  LD VC, 0x00
+    v[0xC] = 0x00;
+    // 0x2FA  LD I, 0x2EA
+    i = 0x2EA;
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2FC);
+    Ok(())
+}
+
+fn block_2fc(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2FC  DRW VB, VC, 1
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x2FC, Drw { x: 11, y: 12, n: 1 }, keys)?;
+    Ok(())
+}
+
+fn block_2fe(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x2FE  ADD VC, 0x01
+    v[0xC] = v[0xC].wrapping_add(0x01);
+    // 0x300  SE VC, 0x20
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(if v[0xC] == 0x20 {0x304} else {0x302});
+    Ok(())
+}
+
+fn block_302(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x302  JP 0x2FC
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.set_pc(0x2FC);
+    Ok(())
+}
+
+fn block_304(m: &mut Chip8, keys: &KeyState) -> Result<(), Chip8Error> {
+    let mut v = m.registers();
+    let mut i = m.index_register();
+    let q = m.quirks;
+    // 0x304  LD VA, 0x00
+    v[0xA] = 0x00;
+    // 0x306  RET
+    m.set_registers(v);
+    m.set_index_register(i);
+    m.step_decoded(0x306, Ret, keys)?;
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let entry = RomDb::embedded().lookup(&ROM);
+    let result = Config::for_rom(&args, SHA1, entry.as_ref()).map_err(|e| e.to_string())
+        .and_then(|(config, warnings)| {
+            for warning in warnings.iter() {
+                eprintln!("warning: {}", warning);
+            }
+            frontend::run(ROM.to_vec(), config, None, &mut Recompiled {blocks: &BLOCKS}).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
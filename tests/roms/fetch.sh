@@ -0,0 +1,19 @@
+#!/bin/sh
+# downloads the Timendus chip8-test-suite ROMs the manifests here run, see README.md
+# usage: tests/roms/fetch.sh [git ref], defaults to the v4.2 release
+set -e
+
+ref=${1:-v4.2}
+dir=$(dirname "$0")
+
+for rom in 1-chip8-logo 2-ibm-logo 3-corax+ 4-flags 5-quirks 6-keypad; do
+    curl -fsSL -o "$dir/$rom.ch8" "https://raw.githubusercontent.com/Timendus/chip8-test-suite/$ref/bin/$rom.ch8"
+    # the expected screens only hold for the ROM their manifest pins
+    sha1=$(sed -n 's/^sha1 *= *//p' "$dir/$rom.manifest")
+    if [ -n "$sha1" ] && ! echo "$sha1  $dir/$rom.ch8" | sha1sum -c --status; then
+        echo "$rom.ch8 doesn't match the sha1 in $rom.manifest" >&2
+        rm "$dir/$rom.ch8"
+        exit 1
+    fi
+    echo "$rom.ch8"
+done